@@ -0,0 +1,25 @@
+#version 450
+
+// turns each triangle into a line along the normal of each of its vertices
+layout(triangles) in;
+layout(line_strip, max_vertices = 6) out;
+
+layout(location = 0) in vec3 v_normal[];
+
+#include "globals.glsl"
+
+const float LENGTH = 0.02;
+
+void main() {
+     mat4 view_proj = camera.proj * camera.view;
+
+     for (int i = 0; i < 3; i++) {
+          vec4 start = gl_in[i].gl_Position;
+
+          gl_Position = view_proj * start;
+          EmitVertex();
+          gl_Position = view_proj * (start + vec4(v_normal[i] * LENGTH, 0.0));
+          EmitVertex();
+          EndPrimitive();
+     }
+}
@@ -0,0 +1,16 @@
+#version 450
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 normal;
+
+layout(location = 0) out vec3 v_normal;
+
+layout(push_constant) uniform Model {
+  mat4 model;
+} model;
+
+// left in world space, the geometry shader projects it
+void main() {
+     v_normal = normalize(mat3(model.model) * normal);
+     gl_Position = model.model * vec4(position, 1.0);
+}
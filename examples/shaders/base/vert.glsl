@@ -6,15 +6,12 @@ layout(location = 2) in vec2 tex_coord;
 
 layout(location = 0) out vec3 v_normal;
 
-layout(set = 0, binding = 0) uniform Model {
+#include "globals.glsl"
+
+layout(push_constant) uniform Model {
   mat4 model;
 } model;
 
-layout(set = 1, binding = 0) uniform Camera {
-  mat4 view;
-  mat4 proj;
-} camera;
-
 void main() {
      v_normal = normal;
      gl_Position = camera.proj * camera.view * model.model * vec4(position, 1.0);
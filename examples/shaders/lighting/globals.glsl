@@ -0,0 +1,14 @@
+// bound once per frame with System::set_globals
+
+layout(set = 0, binding = 0) uniform Camera {
+  mat4 view;
+  mat4 proj;
+  vec3 pos;
+} camera;
+
+layout(set = 0, binding = 1) uniform Light {
+  vec3 pos;
+  vec3 ambient;
+  vec3 diffuse;
+  vec3 specular;
+} light;
@@ -12,30 +12,19 @@ layout(location = 3) out vec3 tan_light_pos;
 layout(location = 4) out vec3 tan_cam_pos;
 layout(location = 5) out vec3 tan_frag_pos;
 
-layout(set = 0, binding = 0) uniform Model {
-  mat4 model;
-} model;
+#include "globals.glsl"
 
-layout(set = 0, binding = 1) uniform Material {
+layout(set = 2, binding = 0) uniform Material {
   float shininess;
 } material;
 
-layout(set = 1, binding = 0) uniform Camera {
-  mat4 view;
-  mat4 proj;
-  vec3 pos;
-} camera;
-
-layout(set = 2, binding = 0) uniform Light {
-  vec3 pos;
-  vec3 ambient;
-  vec3 diffuse;
-  vec3 specular;
-} light;
-
-layout(set = 3, binding = 0) uniform sampler2D diffuse_texture;
-layout(set = 3, binding = 1) uniform sampler2D specular_texture;
-layout(set = 3, binding = 2) uniform sampler2D normal_texture;
+layout(set = 2, binding = 1) uniform sampler2D diffuse_texture;
+layout(set = 2, binding = 2) uniform sampler2D specular_texture;
+layout(set = 2, binding = 3) uniform sampler2D normal_texture;
+
+layout(set = 3, binding = 0) uniform Model {
+  mat4 model;
+} model;
 
 void main() {
     v_pos = vec3(model.model * vec4(position, 1.0));
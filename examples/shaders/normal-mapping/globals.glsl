@@ -0,0 +1,11 @@
+// bound once per frame with System::set_globals
+
+layout(set = 0, binding = 0) uniform Camera {
+    mat4 view;
+    mat4 proj;
+    vec3 pos;
+} camera;
+
+layout(set = 0, binding = 1) uniform Light {
+    vec3 pos;
+} light;
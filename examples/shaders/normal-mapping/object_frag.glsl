@@ -9,21 +9,13 @@ layout(location = 5) in vec3 tan_frag_pos;
 
 layout(location = 0) out vec4 f_color;
 
-layout(set = 0, binding = 0) uniform Model {
-  mat4 model;
-} model;
-
-layout(set = 1, binding = 0) uniform Camera {
-  mat4 view;
-  mat4 proj;
-  vec3 pos;
-} camera;
+#include "globals.glsl"
 
-layout(set = 2, binding = 0) uniform Light {
-  vec3 pos;
-} light;
+layout(set = 2, binding = 0) uniform sampler2D normal_tex;
 
-layout(set = 3, binding = 0) uniform sampler2D normal_tex;
+layout(set = 3, binding = 0) uniform Model {
+  mat4 model;
+} model;
 
 void main() {
     vec3 m_ambient = vec3(0.2);
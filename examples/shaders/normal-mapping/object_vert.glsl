@@ -12,21 +12,13 @@ layout(location = 3) out vec3 tan_light_pos;
 layout(location = 4) out vec3 tan_cam_pos;
 layout(location = 5) out vec3 tan_frag_pos;
 
-layout(set = 0, binding = 0) uniform Model {
-    mat4 model;
-} model;
-
-layout(set = 1, binding = 0) uniform Camera {
-    mat4 view;
-    mat4 proj;
-    vec3 pos;
-} camera;
+#include "globals.glsl"
 
-layout(set = 2, binding = 0) uniform Light {
-    vec3 pos;
-} light;
+layout(set = 2, binding = 0) uniform sampler2D normal_tex;
 
-layout(set = 3, binding = 0) uniform sampler2D normal_tex;
+layout(set = 3, binding = 0) uniform Model {
+    mat4 model;
+} model;
 
 void main() {
      v_pos = vec3(model.model * vec4(position, 1.0));
@@ -4,47 +4,28 @@ layout(location = 0) in vec2 v_tex_coord;
 layout(location = 1) in vec3 tan_light_dir;
 layout(location = 2) in vec3 tan_cam_pos;
 layout(location = 3) in vec3 tan_frag_pos;
+layout(location = 4) flat in uint v_object_index;
 
 layout(location = 0) out vec4 f_color;
 
-layout(set = 0, binding = 0) uniform Material {
-  vec4 ambient;
-  vec4 diffuse;
-  vec4 specular;
-  vec4 shininess;
-  vec4 use_texture;
-} material;
-
-layout(set = 0, binding = 1) uniform Model {
-  mat4 model;
-} model;
-
-layout(set = 1, binding = 0) uniform sampler2D diffuse_map;
-layout(set = 1, binding = 1) uniform sampler2D specular_map;
-layout(set = 1, binding = 2) uniform sampler2D normal_map;
-
-layout(set = 2, binding = 0) uniform Camera {
-  mat4 view;
-  mat4 proj;
-  vec3 pos;
-} camera;
-
-layout(set = 2, binding = 1) uniform Light {
-  vec3 direction;
-  vec3 strength; // vec3 really means float, idk why it doesn't work
-} light;
+#include "globals.glsl"
+
+// every texture of every material, the size is defined by obj-viewer.rs
+layout(set = 2, binding = 0) uniform sampler2D textures[TEXTURE_COUNT];
 
 void main() {
+  ObjectData material = objects[v_object_index];
+
   // only use the texture if we should
-  vec4 tex_diffuse = material.use_texture.r > 0.5 ? texture(diffuse_map, v_tex_coord) : vec4(material.diffuse.rgb, 1.0);
+  vec4 tex_diffuse = material.use_texture != 0 ? texture(textures[material.textures], v_tex_coord) : vec4(material.diffuse, 1.0);
 
   if (tex_diffuse.a < 0.5) {
     discard;
   }
 
-  vec3 tex_specular = texture(specular_map, v_tex_coord).rgb;
+  vec3 tex_specular = texture(textures[material.textures + 1], v_tex_coord).rgb;
 
-  vec3 normal = texture(normal_map, v_tex_coord).rgb * 2.0 - 1.0;
+  vec3 normal = texture(textures[material.textures + 2], v_tex_coord).rgb * 2.0 - 1.0;
 
   // ambient
   vec3 ambient = tex_diffuse.rgb * 0.01;
@@ -58,11 +39,11 @@ void main() {
   // specular
   vec3 view_dir = normalize(tan_cam_pos - tan_frag_pos);
   vec3 halfway_dir = normalize(light_dir + view_dir);
-  float spec = pow(max(dot(normal, halfway_dir), 0.0), material.shininess.r);
-  vec3 specular = material.specular.rgb * spec;
+  float spec = pow(max(dot(normal, halfway_dir), 0.0), material.shininess);
+  vec3 specular = material.specular * spec;
 
   // result
-  vec3 result = ambient + (diffuse + specular) * light.strength.r;
+  vec3 result = ambient + (diffuse + specular) * light.power;
 
   // gamma correction
   float gamma = 2.2;
@@ -0,0 +1,30 @@
+// bound once per frame with System::set_globals
+
+layout(set = 0, binding = 0) uniform Camera {
+  mat4 view;
+  mat4 proj;
+  vec3 pos;
+} camera;
+
+layout(set = 0, binding = 1) uniform Light {
+  vec3 direction;
+  float power;
+} light;
+
+// every object's model matrix and material, in one buffer shared by all of
+// them. each object finds its own with the index in its push constants.
+struct ObjectData {
+  mat4 model;
+  vec3 ambient;
+  float shininess;
+  vec3 diffuse;
+  uint use_texture;
+  vec3 specular;
+  // the object's diffuse texture in the texture array, its specular and
+  // normal textures are the next two
+  uint textures;
+};
+
+layout(set = 0, binding = 2) readonly buffer Objects {
+  ObjectData objects[];
+};
@@ -9,37 +9,19 @@ layout(location = 0) out vec2 v_tex_coord;
 layout(location = 1) out vec3 tan_light_dir;
 layout(location = 2) out vec3 tan_cam_pos;
 layout(location = 3) out vec3 tan_frag_pos;
+layout(location = 4) flat out uint v_object_index;
 
-layout(set = 0, binding = 0) uniform Material {
-  vec4 ambient;
-  vec4 diffuse;
-  vec4 specular;
-  vec4 shininess;
-  vec4 use_texture;
-} material;
+#include "globals.glsl"
 
-layout(set = 0, binding = 1) uniform Model {
-  mat4 model;
-} model;
-
-layout(set = 1, binding = 0) uniform sampler2D diffuse_map;
-layout(set = 1, binding = 1) uniform sampler2D specular_map;
-layout(set = 1, binding = 2) uniform sampler2D normal_map;
-
-layout(set = 2, binding = 0) uniform Camera {
-  mat4 view;
-  mat4 proj;
-  vec3 pos;
-} camera;
-
-layout(set = 2, binding = 1) uniform Light {
-  vec3 direction;
-  vec3 strength; // vec3 really means float, idk why it doesn't work
-} light;
+// which of the objects this is
+layout(push_constant) uniform Object {
+  uint index;
+} object;
 
 void main() {
   v_tex_coord = tex_coord;
-  vec3 pos = vec3(model.model * vec4(position, 1.0));
+  v_object_index = object.index;
+  vec3 pos = vec3(objects[object.index].model * vec4(position, 1.0));
   gl_Position = camera.proj * camera.view * vec4(pos, 1.0);
 
   vec3 bitangent = cross(tangent, normal);
@@ -3,9 +3,28 @@
 layout(location = 0) in vec2 v_pos;
 layout(location = 0) out vec4 f_color;
 
-layout(set = 0, binding = 0) uniform sampler2D depth_map;
+layout(set = 1, binding = 0) uniform samplerCube shadow_map;
 
+// shows the faces side by side, in the order +X, -X, +Y, -Y, +Z, -Z
 void main() {
-  float depth = texture(depth_map, v_pos.xy).r;
+  float face = floor(v_pos.x * 6.0);
+  vec2 uv = vec2(fract(v_pos.x * 6.0), v_pos.y) * 2.0 - 1.0;
+
+  vec3 dir;
+  if (face == 0.0) {
+    dir = vec3(1.0, -uv.y, -uv.x);
+  } else if (face == 1.0) {
+    dir = vec3(-1.0, -uv.y, uv.x);
+  } else if (face == 2.0) {
+    dir = vec3(uv.x, 1.0, uv.y);
+  } else if (face == 3.0) {
+    dir = vec3(uv.x, -1.0, -uv.y);
+  } else if (face == 4.0) {
+    dir = vec3(uv.x, -uv.y, 1.0);
+  } else {
+    dir = vec3(-uv.x, -uv.y, -1.0);
+  }
+
+  float depth = texture(shadow_map, dir).r;
   f_color = vec4(vec3(pow(depth, 20.0)), 1.0);
 }
@@ -7,17 +7,15 @@ layout(location = 2) in vec3 normal;
 layout(location = 0) out vec3 v_pos;
 layout(location = 1) out vec3 v_normal;
 
-layout(set = 0, binding = 0) uniform sampler2D shadow_map;
-
-layout(set = 1, binding = 0) uniform Model {
-  mat4 model;
-} model;
-
-layout(set = 2, binding = 0) uniform Camera {
+layout(set = 0, binding = 0) uniform Camera {
   mat4 view;
   mat4 proj;
 } camera;
 
+layout(set = 3, binding = 0) uniform Model {
+  mat4 model;
+} model;
+
 void main() {
   v_pos = vec3(model.model * vec4(position, 1.0));
   v_normal = normal;
@@ -2,15 +2,42 @@
 
 layout(location = 0) in vec2 v_pos;
 
-layout(set = 0, binding = 0) uniform sampler2D depth_map;
+// the pass's image
+layout(set = 1, binding = 0) uniform samplerCube depth_map;
+
+// which face of the blurred cubemap is drawn, in the order +X, -X, +Y, -Y,
+// +Z, -Z
+layout(push_constant) uniform Face {
+  uint index;
+} face;
+
+// the direction a point on the face looks in, with uv from -1 to 1. points
+// off the edge of the face end up on its neighbour, so the blur is seamless.
+vec3 face_dir(vec2 uv) {
+  if (face.index == 0) {
+    return vec3(1.0, -uv.y, -uv.x);
+  } else if (face.index == 1) {
+    return vec3(-1.0, -uv.y, uv.x);
+  } else if (face.index == 2) {
+    return vec3(uv.x, 1.0, uv.y);
+  } else if (face.index == 3) {
+    return vec3(uv.x, -1.0, -uv.y);
+  } else if (face.index == 4) {
+    return vec3(uv.x, -uv.y, 1.0);
+  } else {
+    return vec3(-uv.x, -uv.y, -1.0);
+  }
+}
 
 void main() {
+  vec2 uv = v_pos * 2.0 - 1.0;
+
   float depth = 0.0;
-  float radius = 0.0002;
+  float radius = 0.002;
   for (int x = -2; x <= 2; x++) {
     for (int y = -2; y <= 2; y++) {
-      vec2 tex_coords = v_pos.xy + vec2(x * radius, y * radius);
-      float sample_depth = texture(depth_map, tex_coords).r;
+      vec2 offset_uv = uv + vec2(x * radius, y * radius);
+      float sample_depth = texture(depth_map, face_dir(offset_uv)).r;
       depth += sample_depth;
     }
   }
@@ -2,15 +2,12 @@
 
 layout(location = 0) in vec3 position;
 
-layout(set = 0, binding = 0) uniform Model {
+#include "globals.glsl"
+
+layout(set = 3, binding = 0) uniform Model {
     mat4 model;
 } model;
 
-layout(set = 1, binding = 0) uniform Camera {
-    mat4 view;
-    mat4 proj;
-} camera;
-
 void main() {
      gl_Position = camera.proj * camera.view * model.model * vec4(position, 1.0);
      gl_Position.z += 0.0001;
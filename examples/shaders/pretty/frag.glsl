@@ -0,0 +1,141 @@
+#version 450
+
+// the geometry pass's fragment shader. which parts of the lighting it uses are
+// switched on with defines in ShaderOptions, each one building on the last:
+// USE_LIGHTING: diffuse light from the light's direction
+// USE_FALLOFF: ambient light, and the light getting weaker with distance
+// USE_SPECULAR: specular highlights
+// USE_NORMAL_MAP: normals from the normal map rather than the surface's
+// USE_SHADOWS: shadows from the blurred shadow cubemap
+// USE_TONEMAPPING: uncharted 2 tone mapping before gamma correction
+// without any of them only the diffuse texture is shown. for debugging, one of
+// SHOW_WHITE, SHOW_SHADOWS, SHOW_SPECULAR, SHOW_SPECULAR_THRESHOLD or
+// SHOW_NORMALS shows just that instead.
+
+layout(location = 0) in vec2 v_tex_coord;
+layout(location = 1) in vec3 tan_light_pos;
+layout(location = 2) in vec3 tan_cam_pos;
+layout(location = 3) in vec3 tan_frag_pos;
+layout(location = 4) in vec3 v_pos;
+
+layout(location = 0) out vec4 f_color;
+
+#include "globals.glsl"
+
+// the blurred shadow cubemap, the geometry pass's only image
+layout(set = 1, binding = 0) uniform samplerCube shadow_map;
+
+layout(set = 2, binding = 0) uniform Material {
+  vec3 ambient;
+  vec3 diffuse;
+  vec3 specular;
+  vec3 shininess;
+  vec3 use_texture;
+} material;
+
+layout(set = 2, binding = 1) uniform sampler2D diffuse_map;
+layout(set = 2, binding = 2) uniform sampler2D specular_map;
+layout(set = 2, binding = 3) uniform sampler2D normal_map;
+
+#ifdef USE_TONEMAPPING
+float A = 0.15;
+float B = 0.50;
+float C = 0.10;
+float D = 0.20;
+float E = 0.02;
+float F = 0.30;
+
+// taken from: http://filmicworlds.com/blog/filmic-tonemapping-operators/
+vec3 Uncharted2Tonemap(vec3 x)
+{
+  return ((x*(A*x+C*B)+D*E)/(x*(A*x+B)+D*F))-E/F;
+}
+#endif
+
+#if defined(USE_SHADOWS) || defined(SHOW_SHADOWS)
+float shadowedness() {
+  // the cubemap was drawn from the light's position
+  vec3 light_dir = v_pos - light.position;
+  float sample_dist = texture(shadow_map, light_dir).r * 250.0;
+
+  float frag_dist = length(v_pos - light.position);
+
+  // idk why i have to invert it
+  float difference = abs(sample_dist - frag_dist);
+
+  return clamp(difference, 0.0, 1.0);
+}
+#endif
+
+vec3 surface_normal() {
+#if defined(USE_NORMAL_MAP) || defined(SHOW_SPECULAR) || defined(SHOW_SPECULAR_THRESHOLD)
+  return texture(normal_map, v_tex_coord).rgb * 2.0 - 1.0;
+#else
+  return vec3(0.0, 0.0, 1.0);
+#endif
+}
+
+vec3 specular_light(vec3 normal) {
+  vec3 light_dir = normalize(tan_light_pos - tan_frag_pos);
+  vec3 view_dir = normalize(tan_cam_pos - tan_frag_pos);
+  vec3 halfway_dir = normalize(light_dir + view_dir);
+  float spec = pow(max(dot(normal, halfway_dir), 0.0), 32.0);
+  return vec3(clamp(0.2 * spec, 0.0, 0.5));
+}
+
+vec3 lit_color() {
+  // only use the texture if we should
+  vec4 tex_diffuse = material.use_texture.r > 0.5 ? texture(diffuse_map, v_tex_coord) : vec4(material.diffuse, 1.0);
+
+#ifndef USE_LIGHTING
+  return tex_diffuse.rgb;
+#else
+  vec3 normal = surface_normal();
+
+  // diffuse
+  vec3 light_dir = normalize(tan_light_pos - tan_frag_pos);
+  float diff = max(dot(normal, light_dir), 0.0);
+  vec3 lit = diff * tex_diffuse.rgb;
+
+#ifdef USE_SPECULAR
+  lit += specular_light(normal);
+#endif
+
+#ifdef USE_SHADOWS
+  lit *= 1.0 - shadowedness();
+#endif
+
+#ifdef USE_FALLOFF
+  vec3 ambient = tex_diffuse.rgb * 0.01;
+  float dist = length(tan_light_pos - tan_frag_pos);
+  return ambient + lit * light.strength / (dist * dist / 2000.0);
+#else
+  return lit;
+#endif
+#endif
+}
+
+void main() {
+#if defined(SHOW_WHITE)
+  f_color = vec4(1.0);
+#elif defined(SHOW_SHADOWS)
+  f_color = vec4(vec3(1.0 - shadowedness()), 1.0);
+#elif defined(SHOW_SPECULAR)
+  f_color = vec4(specular_light(surface_normal()) * 4.0, 1.0);
+#elif defined(SHOW_SPECULAR_THRESHOLD)
+  vec3 specular = specular_light(surface_normal());
+  f_color = vec4(specular.x > 0.1 ? vec3(1.0) : vec3(0.0), 1.0);
+#elif defined(SHOW_NORMALS)
+  f_color = vec4(texture(normal_map, v_tex_coord).rgb, 1.0);
+#else
+  vec3 result = lit_color();
+
+#ifdef USE_TONEMAPPING
+  result *= 16;
+  float exposure_bias = 2.0;
+  result = Uncharted2Tonemap(exposure_bias * result);
+#endif
+
+  f_color = vec4(pow(result, vec3(1/2.2)), 1.0);
+#endif
+}
@@ -2,10 +2,7 @@
 
 layout(location = 0) in vec3 v_pos;
 
-layout(set = 3, binding = 0) uniform Light {
-  vec3 position;
-  vec3 strength;
-} light;
+#include "globals.glsl"
 
 void main() {
   float light_dist = length(v_pos - light.position);
@@ -1,9 +1,12 @@
+use render_engine::collection::SetLayers;
+use render_engine::input::VirtualKeyCode;
 use render_engine::mesh::PrimitiveTopology;
 use render_engine::render_passes;
 use render_engine::system::{Pass, System};
 use render_engine::window::Window;
 use render_engine::object::ObjectPrototype;
-use render_engine::utils::Timer;
+use render_engine::shaders::ShaderOptions;
+use render_engine::utils::{as_bytes, Timer};
 
 use nalgebra_glm::*;
 
@@ -35,15 +38,15 @@ fn main() {
         HashMap::new(),
         "resolve_color",
     );
+    let resources = system.resources();
 
     window.set_render_pass(render_pass.clone());
 
-    // create buffer and set for model matrix
+    // the model matrix is small enough for push constants
     let model_data: Matrix4 = Mat4::identity().into();
 
     // initialize camera
     let mut camera = OrbitCamera::default();
-    let camera_data = camera.get_data();
 
     // load, create pipeline spec and set for model matrix
     // only load 1st object
@@ -51,20 +54,43 @@ fn main() {
     let mesh = convert_meshes(&[models.remove(0)]).remove(0);
     dbg!["done loading meshes"];
 
-    let mut object = ObjectPrototype {
+    let object = ObjectPrototype {
         vs_path: relative_path("shaders/base/vert.glsl"),
         fs_path: relative_path("shaders/base/frag.glsl"),
         fill_type: PrimitiveTopology::TriangleList,
         read_depth: true,
         write_depth: true,
+        shader_options: ShaderOptions::default(),
+        mesh: mesh.clone(),
+        material: (),
+        collection: (),
+        set_layers: SetLayers::default(),
+        push_constants: Some(as_bytes(&model_data)),
+        custom_dynamic_state: None,
+    }
+    .build(&resources, render_pass.clone())
+    .unwrap();
+
+    // the same mesh again, with a geometry shader that draws each vertex's
+    // normal as a line. toggled with N.
+    let normals = ObjectPrototype {
+        vs_path: relative_path("shaders/base/normals_vert.glsl"),
+        fs_path: relative_path("shaders/base/normals_frag.glsl"),
+        fill_type: PrimitiveTopology::TriangleList,
+        read_depth: true,
+        write_depth: true,
+        shader_options: ShaderOptions::default()
+            .geometry_shader(relative_path("shaders/base/normals_geom.glsl")),
         mesh,
-        collection: (
-            (model_data,),
-            (camera_data,),
-        ),
+        material: (),
+        collection: (),
+        set_layers: SetLayers::default(),
+        push_constants: Some(as_bytes(&model_data)),
         custom_dynamic_state: None,
     }
-    .build(queue.clone(), render_pass.clone());
+    .build(&resources, render_pass.clone())
+    .unwrap();
+    let mut show_normals = false;
 
     let mut camera_timer = Timer::new("Camera uniform buffer");
 
@@ -73,16 +99,24 @@ fn main() {
         camera_timer.start();
         camera.update(window.get_frame_info());
 
-        let camera_data = camera.get_data();
-
-        object.collection.1.data.0 = camera_data;
-
-        object.collection.1.upload(device.clone());
+        // the camera is bound for every object
+        system.set_globals(((camera.get_data(),),));
         camera_timer.stop();
 
+        if window
+            .get_frame_info()
+            .keydowns
+            .contains(&VirtualKeyCode::N)
+        {
+            show_normals = !show_normals;
+        }
+
         // draw
         system.start_window(&mut window);
         system.add_object(&object);
+        if show_normals {
+            system.add_object(&normals);
+        }
         system.finish_to_window(&mut window);
     }
 
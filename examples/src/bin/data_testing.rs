@@ -1,5 +1,7 @@
+use render_engine::collection::SetLayers;
 use render_engine::mesh::{Mesh, PrimitiveTopology};
 use render_engine::object::ObjectPrototype;
+use render_engine::shaders::ShaderOptions;
 use render_engine::render_passes;
 use render_engine::system::{Pass, System};
 use render_engine::utils::load_texture;
@@ -32,6 +34,15 @@ fn main() {
         HashMap::new(),
         "color",
     );
+    let resources = system.resources();
+
+    // there are no globals, pass images or materials here, so the objects'
+    // sets start at 0
+    let set_layers = SetLayers {
+        object: 0,
+        ..SetLayers::default()
+    };
+    system.set_layers = set_layers;
 
     window.set_render_pass(render_pass.clone());
 
@@ -45,6 +56,7 @@ fn main() {
         fill_type: PrimitiveTopology::TriangleList,
         read_depth: false,
         write_depth: false,
+        shader_options: ShaderOptions::default(),
         mesh: Mesh {
             vertices: vec![
                 VPosColor2D {
@@ -62,10 +74,14 @@ fn main() {
             ],
             indices: vec![0, 1, 2],
         },
+        material: (),
         collection: ((model_data,),),
+        set_layers,
+        push_constants: None,
         custom_dynamic_state: None,
     }
-    .build(queue.clone(), render_pass.clone());
+    .build(&resources, render_pass.clone())
+    .unwrap();
 
     let texture = load_texture(
         queue.clone(),
@@ -79,6 +95,7 @@ fn main() {
         fill_type: PrimitiveTopology::TriangleList,
         read_depth: false,
         write_depth: false,
+        shader_options: ShaderOptions::default(),
         mesh: Mesh {
             vertices: vec![
                 VPos2D {
@@ -93,10 +110,14 @@ fn main() {
             ],
             indices: vec![0, 1, 2],
         },
+        material: (),
         collection: ((texture,),),
+        set_layers,
+        push_constants: None,
         custom_dynamic_state: None,
     }
-    .build(queue.clone(), render_pass.clone());
+    .build(&resources, render_pass.clone())
+    .unwrap();
 
     // used in main loop
     while !window.update() {
@@ -1,11 +1,12 @@
 use render_engine::input::get_elapsed;
 use render_engine::mesh::PrimitiveTopology;
 use render_engine::object::ObjectPrototype;
+use render_engine::shaders::ShaderOptions;
 use render_engine::render_passes;
 use render_engine::system::{Pass, System};
 use render_engine::utils::load_texture;
 use render_engine::window::Window;
-use render_engine::collection::Data;
+use render_engine::collection::{Data, SetLayers};
 
 // TODO: reeeeee i shouldn't have to do this
 use nalgebra_glm::*;
@@ -39,6 +40,7 @@ fn main() {
         HashMap::new(),
         "resolve_color",
     );
+    let resources = system.resources();
 
     window.set_render_pass(render_pass.clone());
 
@@ -76,7 +78,6 @@ fn main() {
 
     // initialize camera
     let mut camera = OrbitCamera::default();
-    let camera_data = camera.get_data();
 
     // load mesh and create object
     let (mut models, _materials) =
@@ -84,24 +85,29 @@ fn main() {
     let basic_mesh = convert_meshes(&[models.remove(0)]).remove(0);
     let mesh = add_tangents(&basic_mesh);
 
-    let mut object = ObjectPrototype {
+    let object = ObjectPrototype {
         vs_path: relative_path("shaders/lighting/object_vert.glsl"),
         fs_path: relative_path("shaders/lighting/object_frag.glsl"),
         fill_type: PrimitiveTopology::TriangleList,
         read_depth: true,
         write_depth: true,
+        shader_options: ShaderOptions::default(),
         mesh,
 
-        // 00 model 01 material 10 camera 20 light 30 diff 31 spec 32 norm
-        collection: (
-            (model_data, material_data),
-            (camera_data,),
-            (light.clone(),),
-            (diffuse_texture, specular_texture, normal_texture),
-        ),
+        // the camera and light are globals, at set 0
+        material: ((
+            material_data,
+            diffuse_texture,
+            specular_texture,
+            normal_texture,
+        ),),
+        collection: ((model_data,),),
+        set_layers: SetLayers::default(),
+        push_constants: None,
         custom_dynamic_state: None,
     }
-    .build(queue.clone(), render_pass.clone());
+    .build(&resources, render_pass.clone())
+    .unwrap();
 
     // used in main loop
     let start_time = std::time::Instant::now();
@@ -117,11 +123,8 @@ fn main() {
         let light_z = (time / 4.0).cos() * 20.0;
         light.position = [light_x, 0.0, light_z, 0.0];
 
-        object.collection.1.data.0 = camera_data;
-        object.collection.2.data.0 = light.clone();
-
-        object.collection.1.upload(device.clone());
-        object.collection.2.upload(device.clone());
+        // the camera and light are bound for every object
+        system.set_globals(((camera_data, light.clone()),));
 
         // draw
         system.start_window(&mut window);
@@ -1,6 +1,8 @@
 use render_engine as re;
 
+use re::collection::SetLayers;
 use re::object::ObjectPrototype;
+use re::shaders::ShaderOptions;
 use re::render_passes;
 use re::system::{Pass, System};
 use re::window::Window;
@@ -38,6 +40,7 @@ fn main() {
         HashMap::new(),
         "final",
     );
+    let resources = system.resources();
 
     window.set_render_pass(render_pass.clone());
 
@@ -49,6 +52,7 @@ fn main() {
         fill_type: PrimitiveTopology::TriangleList,
         read_depth: false,
         write_depth: false,
+        shader_options: ShaderOptions::default(),
         mesh: Mesh {
             vertices: vec![
                 VPosColor2D {
@@ -66,13 +70,17 @@ fn main() {
             ],
             indices: vec![0, 1, 2],
         },
+        material: (),
         collection: (),
+        set_layers: SetLayers::default(),
+        push_constants: None,
         custom_dynamic_state: None,
     }
-    .build(queue.clone(), render_pass.clone());
+    .build(&resources, render_pass.clone())
+    .unwrap();
 
     let quad = fullscreen_quad(
-        queue.clone(),
+        &resources,
         render_pass.clone(),
         relative_path("shaders/multipass/postpro_vert.glsl"),
         relative_path("shaders/multipass/postpro_frag.glsl"),
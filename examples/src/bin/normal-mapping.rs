@@ -8,11 +8,12 @@ Why do I have to manage queue and device? :(
 use re::input::get_elapsed;
 use re::mesh::{Mesh, PrimitiveTopology};
 use re::object::ObjectPrototype;
+use re::shaders::ShaderOptions;
 use re::render_passes;
 use re::system::{Pass, System};
 use re::utils::load_texture;
 use re::window::Window;
-use re::collection::Data;
+use re::collection::{Data, SetLayers};
 
 use vulkano::format::Format;
 
@@ -48,6 +49,7 @@ fn main() {
         HashMap::new(),
         "resolve_color",
     );
+    let resources = system.resources();
 
     window.set_render_pass(render_pass.clone());
 
@@ -60,7 +62,6 @@ fn main() {
 
     // initialize camera
     let mut camera = OrbitCamera::default();
-    let camera_data = camera.get_data();
 
     // textures
     let normal_texture = load_texture(
@@ -86,32 +87,35 @@ fn main() {
         fs_path: relative_path("shaders/normal-mapping/object_frag.glsl"),
         read_depth: true,
         write_depth: true,
+        shader_options: ShaderOptions::default(),
         fill_type: PrimitiveTopology::TriangleList,
         mesh: raptor_mesh,
-        collection: (
-            (model_data,),
-            (camera_data.clone(),),
-            (light.clone(),),
-            (normal_texture.clone(),),
-        ),
+        // the camera and light are globals, at set 0
+        material: ((normal_texture.clone(),),),
+        collection: ((model_data,),),
+        set_layers: SetLayers::default(),
+        push_constants: None,
         custom_dynamic_state: None,
     }
-    .build(queue.clone(), render_pass.clone());
+    .build(&resources, render_pass.clone())
+    .unwrap();
 
-    let mut normals = ObjectPrototype {
+    let normals = ObjectPrototype {
         vs_path: relative_path("shaders/normal-mapping/debug_vert.glsl"),
         fs_path: relative_path("shaders/normal-mapping/debug_frag.glsl"),
         read_depth: true,
         write_depth: true,
+        shader_options: ShaderOptions::default(),
         fill_type: PrimitiveTopology::LineList,
         mesh: normals_mesh,
-        collection: (
-            (model_data,),
-            (camera_data,),
-        ),
+        material: (),
+        collection: ((model_data,),),
+        set_layers: SetLayers::default(),
+        push_constants: None,
         custom_dynamic_state: None,
     }
-    .build(queue.clone(), render_pass.clone());
+    .build(&resources, render_pass.clone())
+    .unwrap();
 
     // used to calculate light's position
     let start_time = std::time::Instant::now();
@@ -127,16 +131,8 @@ fn main() {
         let light_z = (time / 4.0).cos() * 20.0;
         light.position = [light_x, 0.0, light_z];
 
-        // update raptor collection
-        raptor.collection.1.data.0 = camera_data.clone();
-        raptor.collection.2.data.0 = light.clone();
-
-        raptor.collection.1.upload(device.clone());
-        raptor.collection.2.upload(device.clone());
-
-        // update normal vis collection
-        normals.collection.1.data.0 = camera_data;
-        normals.collection.1.upload(device.clone());
+        // the camera and light are bound for every object
+        system.set_globals(((camera_data, light.clone()),));
 
         // if C is pressed, switch to the debugging fragment shader which
         // renders the raptor's surface showing normals instead of as white
@@ -165,7 +161,7 @@ fn main() {
 
 fn normals_vis(mesh: &Mesh<VPosTexNormTan>) -> Mesh<VPosColor> {
     // produces a mesh of type VPos, we need VPosColor
-    let wireframe_pos_only = wireframe(&only_pos_from_ptnt(&mesh));
+    let wireframe_pos_only = wireframe(&only_pos_from_ptnt(mesh));
     let wireframe_verts: Vec<VPosColor> = wireframe_pos_only
         .vertices
         .iter()
@@ -1,20 +1,21 @@
-use render_engine::collection::{Data, Set};
+use render_engine::collection::{Data, Set, SetLayers, StorageSlice, TextureArray};
 use render_engine::input::get_elapsed;
-use render_engine::mesh::PrimitiveTopology;
+use render_engine::mesh::{MeshAbstract, PrimitiveTopology};
 use render_engine::object::{Object, ObjectPrototype};
+use render_engine::shaders::ShaderOptions;
 use render_engine::render_passes;
 use render_engine::system::{Pass, System};
+use render_engine::utils::as_bytes;
 use render_engine::window::Window;
-use render_engine::Image;
 
 use std::collections::HashMap;
 use std::env;
 use std::path::Path;
 
-use nalgebra_glm::{scale, vec3, Mat4};
+use nalgebra_glm::Mat4;
 
 use tests_render_engine::mesh::{add_tangents_multi, convert_meshes, load_obj, load_textures};
-use tests_render_engine::{relative_path, CameraData, FlyCamera, Matrix4};
+use tests_render_engine::{relative_path, FlyCamera};
 
 fn main() {
     // get path to load_obj
@@ -48,6 +49,7 @@ fn main() {
         HashMap::new(),
         "resolve_color",
     );
+    let resources = system.resources();
 
     window.set_render_pass(render_pass.clone());
 
@@ -56,44 +58,40 @@ fn main() {
 
     // light
     let moving_light = MovingLight::new();
-    let light_data = moving_light.get_data();
 
     // load meshes and materials
-    let (models, materials) = load_obj(&path).expect("Couldn't open OBJ file");
+    let (models, materials) = load_obj(path).expect("Couldn't open OBJ file");
     let meshes = add_tangents_multi(&convert_meshes(&models));
     let textures_path = path.parent().expect("Given path has no parent!");
     println!("Searching for textures in {:?}", textures_path);
     let texture_sets = load_textures(queue.clone(), textures_path, &materials);
 
     let default_material = Material {
-        ambient: [1.0, 1.0, 1.0, 0.0],
-        diffuse: [0.0, 0.0, 1.0, 0.0],
-        specular: [1.0, 1.0, 1.0, 0.0],
-        shininess: [32.0, 0.0, 0.0, 0.0],
-        use_texture: [0.0, 0.0, 0.0, 0.0],
+        ambient: [1.0, 1.0, 1.0],
+        shininess: 32.0,
+        diffuse: [0.0, 0.0, 1.0],
+        use_texture: 0,
+        specular: [1.0, 1.0, 1.0],
     };
-    let model_mat: Matrix4 = Mat4::identity().into();
-
-    // combine the meshes and textures to create a list of renderable objects
-
-    // i don't think the type annotation is necessary is here, but i included it
-    // anyway to show how information about the object's uniforms is stored in
-    // its type.
-    let mut objects: Vec<
-        Object<(
-            // material and model matrix
-            Set<(Material, Matrix4)>,
-            // textures (diffuse, specular, normal)
-            Set<(Image, Image, Image)>,
-            // camera matrices and light position
-            Set<(CameraData, Light)>,
-        )>,
-    > = meshes
-        .into_iter()
-        .enumerate()
-        .map(|(idx, mesh)| {
-            let model = &models[idx];
+    let model_mat: [[f32; 4]; 4] = Mat4::identity().into();
+
+    // every texture goes in one array, three per material: diffuse, specular
+    // and normal
+    let texture_array = TextureArray::new(
+        texture_sets
+            .iter()
+            .flat_map(|(diffuse, specular, normal)| {
+                vec![diffuse.clone(), specular.clone(), normal.clone()]
+            })
+            .collect(),
+    );
 
+    // every object's model matrix and material go in one buffer, which is part
+    // of the globals. objects only push their index into it, so a scene with
+    // hundreds of objects doesn't need hundreds of sets for them.
+    let object_data: Vec<ObjectData> = models
+        .iter()
+        .map(|model| {
             let mat_idx = if let Some(idx) = model.mesh.material_id {
                 idx
             } else {
@@ -107,24 +105,51 @@ fn main() {
                 default_material.clone()
             };
 
-            let textures = texture_sets[mat_idx].clone();
-
-            let object = ObjectPrototype {
-                vs_path: relative_path("shaders/obj-viewer/vert.glsl"),
-                fs_path: relative_path("shaders/obj-viewer/frag.glsl"),
-                fill_type: PrimitiveTopology::TriangleList,
-                read_depth: true,
-                write_depth: true,
-                mesh,
-                collection: (
-                    (material.clone(), model_mat),
-                    textures,
-                    (camera.get_data(), light_data.clone()),
-                ),
-                custom_dynamic_state: None,
-            }
-            .build(queue.clone(), render_pass.clone());
+            ObjectData::new(model_mat, &material, mat_idx as u32 * 3)
+        })
+        .collect();
+    let object_data = StorageSlice::new(device.clone(), &object_data);
+
+    // the shader's array has to have a fixed size
+    let texture_count = texture_array.images.len().to_string();
+
+    // the objects only differ in their meshes and indices, so one is built and
+    // the rest are copies of it. that way they all share its pipeline and the
+    // set with the texture array.
+
+    // i don't think the type annotation is necessary is here, but i included it
+    // anyway to show how information about the object's uniforms is stored in
+    // its type. the camera, light, model matrix and material aren't part of it,
+    // they're globals.
+    let first_object: Object<
+        // nothing of the object's own
+        (),
+        // every texture
+        (Set<TextureArray>,),
+    > = ObjectPrototype {
+        vs_path: relative_path("shaders/obj-viewer/vert.glsl"),
+        fs_path: relative_path("shaders/obj-viewer/frag.glsl"),
+        fill_type: PrimitiveTopology::TriangleList,
+        read_depth: true,
+        write_depth: true,
+        shader_options: ShaderOptions::default().define("TEXTURE_COUNT", Some(&texture_count)),
+        mesh: meshes.first().expect("OBJ file has no meshes").clone(),
+        material: (texture_array,),
+        collection: (),
+        set_layers: SetLayers::default(),
+        push_constants: Some(as_bytes(&0u32)),
+        custom_dynamic_state: None,
+    }
+    .build(&resources, render_pass.clone())
+    .unwrap();
 
+    let objects: Vec<_> = meshes
+        .iter()
+        .enumerate()
+        .map(|(idx, mesh)| {
+            let mut object = first_object.with_index(idx as u32).unwrap();
+            object.vbuf = mesh.get_vbuf(queue.clone());
+            object.ibuf = mesh.get_ibuf(queue.clone());
             object
         })
         .collect();
@@ -138,12 +163,8 @@ fn main() {
         let camera_data = camera.get_data();
         let light_data = moving_light.get_data();
 
-        // update collections
-        objects.iter_mut().for_each(|obj| {
-            obj.collection.2.data.0 = camera_data.clone();
-            obj.collection.2.data.1 = light_data.clone();
-            obj.collection.2.upload(device.clone());
-        });
+        // the camera and light are bound for every object
+        system.set_globals(((camera_data, light_data, object_data.clone()),));
 
         // draw
         system.start_window(&mut window);
@@ -158,14 +179,13 @@ fn main() {
     println!("FPS: {}", window.get_fps());
 }
 
-#[derive(Clone)]
+#[derive(Clone, Data)]
+#[repr(C)]
 struct Light {
-    direction: [f32; 4],
+    direction: [f32; 3],
     power: f32,
 }
 
-impl Data for Light {}
-
 struct MovingLight {
     start_time: std::time::Instant,
 }
@@ -180,7 +200,7 @@ impl MovingLight {
     fn get_data(&self) -> Light {
         let time = get_elapsed(self.start_time) / 4.0;
         Light {
-            direction: [time.sin(), 2.0, time.cos(), 0.0],
+            direction: [time.sin(), 2.0, time.cos()],
             power: 1.0,
         }
     }
@@ -188,36 +208,63 @@ impl MovingLight {
 
 #[derive(Clone)]
 struct Material {
-    ambient: [f32; 4],
-    diffuse: [f32; 4],
-    specular: [f32; 4],
-    shininess: [f32; 4],
-    use_texture: [f32; 4],
+    ambient: [f32; 3],
+    shininess: f32,
+    diffuse: [f32; 3],
+    use_texture: u32,
+    specular: [f32; 3],
 }
 
-impl Data for Material {}
-
 impl Material {
     fn from_tobj(material: &tobj::Material) -> Self {
         let (amb, diff, spec) = (material.ambient, material.diffuse, material.specular);
         let shine = material.shininess;
 
-        let use_tex = if contains_textures(&material) {
-            1.0
-        } else {
-            0.0
-        };
+        let use_tex = if contains_textures(material) { 1 } else { 0 };
 
         Material {
-            ambient: [amb[0], amb[1], amb[2], 1.0],
-            diffuse: [diff[0], diff[1], diff[2], 1.0],
-            specular: [spec[0], spec[1], spec[2], 1.0],
-            shininess: [shine, 0.0, 0.0, 0.0],
-            use_texture: [use_tex, 0.0, 0.0, 0.0],
+            ambient: amb,
+            shininess: shine,
+            diffuse: diff,
+            use_texture: use_tex,
+            specular: spec,
+        }
+    }
+}
+
+// one element of the objects buffer. the scalars fill the space after each
+// vec3, which would be padding otherwise.
+#[derive(Clone, Data)]
+#[repr(C)]
+#[data(std430)]
+struct ObjectData {
+    model: [[f32; 4]; 4],
+    ambient: [f32; 3],
+    shininess: f32,
+    diffuse: [f32; 3],
+    use_texture: u32,
+    specular: [f32; 3],
+    // the object's diffuse texture in the texture array, its specular and
+    // normal textures are the next two
+    textures: u32,
+}
+
+impl ObjectData {
+    fn new(model: [[f32; 4]; 4], material: &Material, textures: u32) -> Self {
+        ObjectData {
+            model,
+            ambient: material.ambient,
+            shininess: material.shininess,
+            diffuse: material.diffuse,
+            use_texture: material.use_texture,
+            specular: material.specular,
+            textures,
         }
     }
 }
 
 fn contains_textures(material: &tobj::Material) -> bool {
-    material.normal_texture != "" || material.specular_texture != "" || material.diffuse_texture != ""
+    !material.normal_texture.is_empty()
+        || !material.specular_texture.is_empty()
+        || !material.diffuse_texture.is_empty()
 }
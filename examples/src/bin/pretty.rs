@@ -1,29 +1,57 @@
-use render_engine::collection::Data;
+use render_engine::collection::{Collection, Data, Set, SetLayers};
+use render_engine::cube_target::CubeTarget;
 use render_engine::input::{get_elapsed, VirtualKeyCode};
 use render_engine::mesh::PrimitiveTopology;
-use render_engine::object::{Drawcall, Object, ObjectPrototype};
+use render_engine::object::{Object, ObjectPrototype};
+use render_engine::shaders::ShaderOptions;
 use render_engine::render_passes;
+use render_engine::resources::Resources;
 use render_engine::system::{Pass, System};
-use render_engine::utils::Timer;
+use render_engine::utils::{as_bytes, Timer};
 use render_engine::window::Window;
-use render_engine::{Format, Image};
-
-use vulkano::command_buffer::DynamicState;
-use vulkano::pipeline::viewport::Viewport;
+use render_engine::Format;
 
 use std::collections::HashMap;
-use std::sync::Arc;
 
 use nalgebra_glm::*;
 
 use tests_render_engine::mesh::{
-    add_tangents_multi, convert_meshes, fullscreen_quad, load_obj, load_textures, merge, only_pos,
-    only_pos_from_ptnt, wireframe,
+    add_tangents_multi, convert_meshes, fullscreen_quad, fullscreen_quad_mesh, load_obj,
+    load_textures, merge, only_pos, only_pos_from_ptnt, wireframe,
 };
 use tests_render_engine::{relative_path, FlyCamera, Matrix4};
 
-const SHADOW_MAP_DIMS: [u32; 2] = [6_144, 1024];
-const PATCH_DIMS: [f32; 2] = [1024.0, 1024.0];
+const SHADOW_MAP_SIZE: u32 = 1024;
+
+// the parts of the geometry pass's lighting, see shaders/pretty/frag.glsl.
+// the view modes switch them on one at a time, in this order.
+const ALL_FEATURES: [&str; 6] = [
+    "USE_LIGHTING",
+    "USE_FALLOFF",
+    "USE_SPECULAR",
+    "USE_NORMAL_MAP",
+    "USE_SHADOWS",
+    "USE_TONEMAPPING",
+];
+
+// one pass per face of the shadow cubemap and one per face of the blurred
+// one, in the order +X, -X, +Y, -Y, +Z, -Z
+const SHADOW_FACE_TAGS: [&str; 6] = [
+    "shadow_pos_x",
+    "shadow_neg_x",
+    "shadow_pos_y",
+    "shadow_neg_y",
+    "shadow_pos_z",
+    "shadow_neg_z",
+];
+const BLUR_FACE_TAGS: [&str; 6] = [
+    "shadow_blur_pos_x",
+    "shadow_blur_neg_x",
+    "shadow_blur_pos_y",
+    "shadow_blur_neg_y",
+    "shadow_blur_pos_z",
+    "shadow_blur_neg_z",
+];
 
 fn main() {
     // initialize window
@@ -31,71 +59,75 @@ fn main() {
     let device = queue.device().clone();
 
     // create system
-    let patched_shadow: Image = vulkano::image::AttachmentImage::sampled(
-        device.clone(),
-        SHADOW_MAP_DIMS,
-        Format::D32Sfloat,
-    )
-    .unwrap();
-    let shadow_blur: Image = vulkano::image::AttachmentImage::sampled(
-        device.clone(),
-        // SHADOW_MAP_DIMS,
-        SHADOW_MAP_DIMS,
-        Format::D32Sfloat,
-    )
-    .unwrap();
-    let mut custom_images = HashMap::new();
-    custom_images.insert("shadow_map", patched_shadow);
-    custom_images.insert("shadow_map_blur", shadow_blur);
-
     let render_pass = render_passes::read_depth(device.clone());
     let rpass_shadow = render_passes::only_depth(device.clone());
     let rpass_shadow_blur = render_passes::only_depth(device.clone());
     let rpass_cubeview = render_passes::basic(device.clone());
     let rpass_prepass = render_passes::only_depth(device.clone());
 
-    let mut system = System::new(
-        queue.clone(),
-        vec![
-            // renders to shadow cubemap
-            Pass {
-                name: "shadow",
-                images_created_tags: vec!["shadow_map"],
-                images_needed_tags: vec![],
-                render_pass: rpass_shadow.clone(),
-            },
-            // blurs shadow cubemap
-            Pass {
-                name: "shadow_blur",
-                images_created_tags: vec!["shadow_map_blur"],
-                images_needed_tags: vec!["shadow_map"],
-                render_pass: rpass_shadow_blur.clone(),
-            },
-            // depth prepass
-            Pass {
-                name: "depth_prepass",
-                images_created_tags: vec!["depth_prepass"],
-                images_needed_tags: vec![],
-                render_pass: rpass_prepass.clone(),
-            },
-            // displays any depth buffer for debugging
-            Pass {
-                name: "depth_viewer",
-                images_created_tags: vec!["depth_view"],
-                images_needed_tags: vec!["depth_prepass"],
-                render_pass: rpass_cubeview.clone(),
-            },
-            // final pass
-            Pass {
-                name: "geometry",
-                images_created_tags: vec!["color", "depth_prepass"],
-                images_needed_tags: vec!["shadow_map_blur"],
-                render_pass: render_pass.clone(),
-            },
-        ],
-        custom_images,
-        "color",
-    );
+    // renders each face of the shadow cubemap
+    let mut passes: Vec<Pass> = SHADOW_FACE_TAGS
+        .iter()
+        .map(|&tag| Pass {
+            name: tag,
+            images_created_tags: vec![tag],
+            images_needed_tags: vec![],
+            render_pass: rpass_shadow.clone(),
+        })
+        .collect();
+    // blurs each face of the shadow cubemap
+    passes.extend(BLUR_FACE_TAGS.iter().map(|&tag| Pass {
+        name: tag,
+        images_created_tags: vec![tag],
+        images_needed_tags: vec!["shadow_map"],
+        render_pass: rpass_shadow_blur.clone(),
+    }));
+    // depth prepass
+    passes.push(Pass {
+        name: "depth_prepass",
+        images_created_tags: vec!["depth_prepass"],
+        images_needed_tags: vec![],
+        render_pass: rpass_prepass.clone(),
+    });
+    // displays any depth buffer for debugging
+    passes.push(Pass {
+        name: "depth_viewer",
+        images_created_tags: vec!["depth_view"],
+        images_needed_tags: vec!["depth_prepass"],
+        render_pass: rpass_cubeview.clone(),
+    });
+    // final pass
+    passes.push(Pass {
+        name: "geometry",
+        images_created_tags: vec!["color", "depth_prepass"],
+        images_needed_tags: vec!["shadow_map_blur"],
+        render_pass: render_pass.clone(),
+    });
+
+    let mut system = System::new(queue.clone(), passes, HashMap::new(), "color");
+    let resources = system.resources();
+
+    // each cube's faces are copied into it once the last of them is drawn, so
+    // the blur passes sample the whole shadow cubemap and the geometry pass
+    // the whole blurred one
+    system.cube_targets.push(CubeTarget {
+        tag: "shadow_map",
+        face_tags: SHADOW_FACE_TAGS,
+        size: SHADOW_MAP_SIZE,
+        format: Format::D32Sfloat,
+    });
+    system.cube_targets.push(CubeTarget {
+        tag: "shadow_map_blur",
+        face_tags: BLUR_FACE_TAGS,
+        size: SHADOW_MAP_SIZE,
+        format: Format::D32Sfloat,
+    });
+
+    // the camera and light are globals at set 0 and each pass's images are at
+    // set 1, then come the materials and the objects' own sets. these are the
+    // default layers, which the shaders are written for.
+    let set_layers = SetLayers::default();
+    system.set_layers = set_layers;
 
     window.set_render_pass(render_pass.clone());
 
@@ -103,11 +135,9 @@ fn main() {
     let mut camera = FlyCamera::default();
     camera.yaw = 0.0;
     camera.position = vec3(0.0, 10.0, 0.0);
-    let camera_data = camera.get_data();
 
     // light
     let light = MovingLight::new();
-    let light_data = light.get_data();
 
     // a model buffer with .1 scale, used for a couple different objects
     let model_data: Matrix4 = scale(&Mat4::identity(), &vec3(0.1, 0.1, 0.1)).into();
@@ -131,7 +161,7 @@ fn main() {
     let textures = load_textures(queue.clone(), &relative_path("meshes/sponza/"), &materials);
 
     // create objects for the geometry pass
-    let mut geo_objects: Vec<Object<_>> = meshes
+    let mut geo_objects: Vec<Object<_, _>> = meshes
         .iter()
         .enumerate()
         .map(|(idx, mesh)| {
@@ -143,23 +173,24 @@ fn main() {
                 println!("Model {} has no material id! Using 0.", model.name);
                 0
             };
-            let textures = textures[mat_idx].clone();
+            let (diffuse, specular, normal) = textures[mat_idx].clone();
 
             ObjectPrototype {
                 vs_path: relative_path("shaders/pretty/vert.glsl"),
-                fs_path: relative_path("shaders/pretty/all_frag.glsl"),
+                fs_path: relative_path("shaders/pretty/frag.glsl"),
                 fill_type: PrimitiveTopology::TriangleList,
                 read_depth: true,
                 write_depth: true,
+                shader_options: shader_options(&ALL_FEATURES),
                 mesh: mesh.clone(),
-                collection: (
-                    (material_data.clone(), model_data),
-                    textures,
-                    (camera_data.clone(), light_data.clone()),
-                ),
+                material: ((material_data.clone(), diffuse, specular, normal),),
+                collection: ((model_data,),),
+                set_layers,
+                push_constants: None,
                 custom_dynamic_state: None,
             }
-            .build(queue.clone())
+            .build(&resources, render_pass.clone())
+            .unwrap()
         })
         .collect();
 
@@ -168,18 +199,38 @@ fn main() {
     // shadow stuff
     // create fullscreen quad to debug cubemap
     let quad_display = fullscreen_quad(
-        queue.clone(),
+        &resources,
+        rpass_cubeview.clone(),
         relative_path("shaders/pretty/fullscreen_vert.glsl"),
         relative_path("shaders/pretty/display_cubemap_frag.glsl"),
     );
 
-    // and to blur shadow map
-    let mut quad_blur = fullscreen_quad(
-        queue.clone(),
-        relative_path("shaders/pretty/fullscreen_vert.glsl"),
-        relative_path("shaders/pretty/blur_frag.glsl"),
-    );
-    quad_blur.pipeline_spec.write_depth = true;
+    // and to blur shadow map, once for each face
+    let quad_blur = ObjectPrototype {
+        vs_path: relative_path("shaders/pretty/fullscreen_vert.glsl"),
+        fs_path: relative_path("shaders/pretty/blur_frag.glsl"),
+        fill_type: PrimitiveTopology::TriangleStrip,
+        read_depth: false,
+        write_depth: true,
+        shader_options: ShaderOptions::default(),
+        mesh: fullscreen_quad_mesh(),
+        material: (),
+        collection: (),
+        set_layers,
+        push_constants: Some(as_bytes(&Face { index: 0 })),
+        custom_dynamic_state: None,
+    }
+    .build(&resources, rpass_shadow_blur.clone())
+    .unwrap();
+
+    let blur_quads: Vec<_> = (0..6)
+        .map(|index| {
+            let mut quad = quad_blur.clone();
+            quad.set_push_constants(Some(as_bytes(&Face { index })))
+                .unwrap();
+            quad
+        })
+        .collect();
 
     // merge meshes for use in depth prepass and shadow casting
     let merged_mesh = merge(&meshes);
@@ -191,24 +242,35 @@ fn main() {
         fill_type: PrimitiveTopology::TriangleList,
         read_depth: true,
         write_depth: true,
+        shader_options: ShaderOptions::default(),
         mesh: merged_mesh_pos_only.clone(),
-        // convert_to_shadow_casters adds proper collections
-        collection: (),
+        material: (),
+        collection: ((model_data,),),
+        set_layers,
+        // only a placeholder of the right size, cube_face_casters gives each
+        // face its own view and projection
+        push_constants: Some(as_bytes(&Matrix4::from(Mat4::identity()))),
         custom_dynamic_state: None,
     }
-    .build(queue.clone());
+    .build(&resources, rpass_shadow.clone())
+    .unwrap();
 
-    let mut depth_prepass_object = ObjectPrototype {
+    let depth_prepass_object = ObjectPrototype {
         vs_path: relative_path("shaders/pretty/depth_prepass_vert.glsl"),
         fs_path: relative_path("shaders/pretty/depth_prepass_frag.glsl"),
         fill_type: PrimitiveTopology::TriangleList,
         read_depth: true,
         write_depth: true,
+        shader_options: ShaderOptions::default(),
         mesh: merged_mesh_pos_only,
-        collection: ((model_data,), (camera_data.clone(),)),
+        material: (),
+        collection: ((model_data,),),
+        set_layers,
+        push_constants: None,
         custom_dynamic_state: None,
     }
-    .build(queue.clone());
+    .build(&resources, rpass_prepass.clone())
+    .unwrap();
 
     // create mesh for light (just a sphere)
     // we need 2 objects: one for the depth prepass and one for the geometry stage
@@ -224,11 +286,16 @@ fn main() {
         fill_type: PrimitiveTopology::TriangleList,
         read_depth: true,
         write_depth: true,
+        shader_options: ShaderOptions::default(),
         mesh: only_pos(&light_mesh),
-        collection: ((model_data,), (camera_data.clone(),)),
+        material: (),
+        collection: ((model_data,),),
+        set_layers,
+        push_constants: None,
         custom_dynamic_state: None,
     }
-    .build(queue.clone());
+    .build(&resources, rpass_prepass.clone())
+    .unwrap();
 
     let mut light_object_geo = ObjectPrototype {
         vs_path: relative_path("shaders/pretty/vert.glsl"),
@@ -236,21 +303,20 @@ fn main() {
         fill_type: PrimitiveTopology::TriangleList,
         read_depth: true,
         write_depth: true,
+        shader_options: ShaderOptions::default(),
         mesh: light_mesh,
-        collection: (
-            (material_data.clone(), model_data),
-            // take the textures of the first object just to fill the space
-            // maybe eventually give the light its own vertex shader
-            textures[0].clone(),
-            (camera_data.clone(), light_data.clone()),
-        ),
+        material: (),
+        collection: ((model_data,),),
+        set_layers,
+        push_constants: None,
         custom_dynamic_state: None,
     }
-    .build(queue.clone());
+    .build(&resources, render_pass.clone())
+    .unwrap();
 
     // create wireframe mesh
     let wireframe_mesh = wireframe(&only_pos_from_ptnt(&merged_mesh));
-    let mut wireframe_object = ObjectPrototype {
+    let wireframe_object = ObjectPrototype {
         // the light vertex shader does exactly the same we need to do, just
         // converts the position to screen space and nothing else, so we re-use
         // it
@@ -259,21 +325,21 @@ fn main() {
         fill_type: PrimitiveTopology::LineList,
         read_depth: true,
         write_depth: true,
+        shader_options: ShaderOptions::default(),
         mesh: wireframe_mesh,
-        collection: ((model_data,), (camera_data,)),
+        material: (),
+        collection: ((model_data,),),
+        set_layers,
+        push_constants: None,
         custom_dynamic_state: None,
     }
-    .build(queue.clone());
-
-    let mut all_objects: HashMap<&str, Vec<Arc<dyn Drawcall>>> = HashMap::new();
+    .build(&resources, render_pass.clone())
+    .unwrap();
 
     // used in main loop
     let mut timer_setup = Timer::new("Setup time");
     let mut timer_draw = Timer::new("Overall draw time");
 
-    all_objects.insert("depth_viewer", vec![Arc::new(quad_display)]);
-    all_objects.insert("shadow_blur", vec![Arc::new(quad_blur)]);
-
     let mut view_mode: i32 = 0;
     let mut update_view = false;
     let mut draw_wireframe = false;
@@ -282,8 +348,6 @@ fn main() {
     while !window.update() {
         timer_setup.start();
 
-        // convert merged mesh into 6 casters, one for each cubemap face
-        let shadow_casters = convert_to_shadow_casters(shadow_cast_base.clone(), light.get_data());
         // update camera, but only if we're grabbing the cursor
         if cursor_grabbed {
             camera.update(window.get_frame_info());
@@ -293,9 +357,11 @@ fn main() {
         // update light
         let light_data = light.get_data();
 
-        // update depth prepass objects' collections
-        (depth_prepass_object.collection.1).0 = camera_data.clone();
-        (light_object_prepass.collection.1).0 = camera_data.clone();
+        // the merged mesh once for each cubemap face
+        let shadow_casters = cube_face_casters(&shadow_cast_base, &light_data);
+
+        // the camera and light are bound for every object
+        system.set_globals(((camera_data, light_data.clone()),));
 
         // the light has moved, we need to update its model matrix
         let light_model_data: Matrix4 = scale(
@@ -303,15 +369,10 @@ fn main() {
             &vec3(0.03, 0.03, 0.03),
         )
         .into();
-        (light_object_prepass.collection.0).0 = light_model_data;
-
-        all_objects.insert(
-            "depth_prepass",
-            vec![
-                Arc::new(depth_prepass_object.clone()),
-                Arc::new(light_object_prepass.clone()),
-            ],
-        );
+        light_object_prepass.collection.0.data.0 = light_model_data;
+        light_object_prepass.collection.0.upload(&resources);
+        light_object_geo.collection.0.data.0 = light_model_data;
+        light_object_geo.collection.0.upload(&resources);
 
         if window
             .get_frame_info()
@@ -329,9 +390,7 @@ fn main() {
                 }
                 1 => {
                     // pure white
-                    geo_objects.iter_mut().for_each(|obj| {
-                        obj.pipeline_spec.fs_path = relative_path("shaders/pretty/white_frag.glsl");
-                    });
+                    set_defines(&resources, &mut geo_objects, &["SHOW_WHITE"]);
                     system.output_tag = "color";
                 }
                 2 => {
@@ -340,95 +399,61 @@ fn main() {
                 }
                 3 => {
                     // diffuse_only
-                    geo_objects.iter_mut().for_each(|obj| {
-                        obj.pipeline_spec.fs_path =
-                            relative_path("shaders/pretty/diffuse_only_frag.glsl");
-                    });
+                    set_defines(&resources, &mut geo_objects, &[]);
                     system.output_tag = "color";
                 }
                 4 => {
                     // diffuse and light direction
-                    geo_objects.iter_mut().for_each(|obj| {
-                        obj.pipeline_spec.fs_path =
-                            relative_path("shaders/pretty/diffuse_and_light_frag.glsl");
-                    });
+                    set_defines(&resources, &mut geo_objects, &ALL_FEATURES[..1]);
                     system.output_tag = "color";
                 }
                 5 => {
                     // diffuse and light distance + direction
-                    geo_objects.iter_mut().for_each(|obj| {
-                        obj.pipeline_spec.fs_path =
-                            relative_path("shaders/pretty/diffuse_light_distance_frag.glsl");
-                    });
+                    set_defines(&resources, &mut geo_objects, &ALL_FEATURES[..2]);
                     system.output_tag = "color";
                 }
                 6 => {
                     // diffuse and specular
-                    geo_objects.iter_mut().for_each(|obj| {
-                        obj.pipeline_spec.fs_path =
-                            relative_path("shaders/pretty/diffuse_and_spec.glsl");
-                    });
+                    set_defines(&resources, &mut geo_objects, &ALL_FEATURES[..3]);
                     system.output_tag = "color";
                 }
                 7 => {
                     // diffuse, specular, normal mapping
-                    geo_objects.iter_mut().for_each(|obj| {
-                        obj.pipeline_spec.fs_path =
-                            relative_path("shaders/pretty/diffuse_spec_normal.glsl");
-                    });
+                    set_defines(&resources, &mut geo_objects, &ALL_FEATURES[..4]);
                     system.output_tag = "color";
                 }
                 8 => {
                     // shadows only
-                    geo_objects.iter_mut().for_each(|obj| {
-                        obj.pipeline_spec.fs_path =
-                            relative_path("shaders/pretty/shadows_only.glsl");
-                    });
+                    set_defines(&resources, &mut geo_objects, &["SHOW_SHADOWS"]);
                     system.output_tag = "color";
                 }
                 9 => {
                     // diffuse + spec + normal mapping + shadows
-                    geo_objects.iter_mut().for_each(|obj| {
-                        obj.pipeline_spec.fs_path =
-                            relative_path("shaders/pretty/shadows_and_color.glsl");
-                    });
+                    set_defines(&resources, &mut geo_objects, &ALL_FEATURES[..5]);
                     system.output_tag = "color";
                 }
                 10 => {
                     // diffuse + spec + normal mapping + shadows + tonemapping
-                    geo_objects.iter_mut().for_each(|obj| {
-                        obj.pipeline_spec.fs_path = relative_path("shaders/pretty/all_frag.glsl");
-                    });
+                    set_defines(&resources, &mut geo_objects, &ALL_FEATURES);
                     system.output_tag = "color";
                 }
                 11 => {
                     // specular only
-                    geo_objects.iter_mut().for_each(|obj| {
-                        obj.pipeline_spec.fs_path =
-                            relative_path("shaders/pretty/specular_only.glsl");
-                    });
+                    set_defines(&resources, &mut geo_objects, &["SHOW_SPECULAR"]);
                     system.output_tag = "color";
                 }
                 12 => {
-                    // specular only, low shininess
-                    geo_objects.iter_mut().for_each(|obj| {
-                        obj.pipeline_spec.fs_path =
-                            relative_path("shaders/pretty/specular_only_2.glsl");
-                    });
+                    // specular only, thresholded
+                    set_defines(&resources, &mut geo_objects, &["SHOW_SPECULAR_THRESHOLD"]);
                     system.output_tag = "color";
                 }
                 13 => {
                     // normals
-                    geo_objects.iter_mut().for_each(|obj| {
-                        obj.pipeline_spec.fs_path =
-                            relative_path("shaders/pretty/normals_only.glsl");
-                    });
+                    set_defines(&resources, &mut geo_objects, &["SHOW_NORMALS"]);
                     system.output_tag = "color";
                 }
                 _ => {
-                    geo_objects.iter_mut().for_each(|obj| {
-                        obj.pipeline_spec.fs_path = relative_path("shaders/pretty/all_frag.glsl");
-                    });
+                    set_defines(&resources, &mut geo_objects, &ALL_FEATURES);
                     view_mode = 0;
                     system.output_tag = "color";
                 }
@@ -459,17 +484,6 @@ fn main() {
             }
         }
 
-        (light_object_geo.collection.0).1 = light_model_data;
-
-        geo_objects
-            .iter_mut()
-            .for_each(|obj| obj.collection.2 = (camera_data.clone(), light_data.clone()));
-
-        if draw_wireframe {
-            (wireframe_object.collection.1).0 = camera_data.clone();
-            // geometry_object_list.push(cur_wireframe_object.clone());
-        }
-
         if window
             .get_frame_info()
             .keydowns
@@ -478,37 +492,43 @@ fn main() {
             draw_wireframe = !draw_wireframe;
         }
 
-        all_objects.insert(
-            "geometry",
-            geo_objects
-                .iter()
-                .map(|obj| {
-                    let dc: Arc<dyn Drawcall> = Arc::new(obj.clone());
-                    dc
-                })
-                .collect(),
-        );
-        all_objects.insert(
-            "shadow",
-            shadow_casters
-                .iter()
-                .map(|obj| {
-                    let dc: Arc<dyn Drawcall> = Arc::new(obj.clone());
-                    dc
-                })
-                .collect(),
-        );
-
-        /*
-        let mut cur_wireframe_object = wireframe_object.clone();
-        cur_wireframe_object.custom_sets.push(camera_set.clone());
-        */
-
         timer_setup.stop();
 
         // draw
         timer_draw.start();
-        system.render_to_window(&mut window, all_objects.clone());
+        system.start_window(&mut window);
+
+        // shadow cubemap faces
+        for caster in shadow_casters.iter() {
+            system.add_object(caster);
+            system.next_pass();
+        }
+
+        // blurred shadow cubemap faces
+        for quad in blur_quads.iter() {
+            system.add_object(quad);
+            system.next_pass();
+        }
+
+        // depth_prepass
+        system.add_object(&depth_prepass_object);
+        system.add_object(&light_object_prepass);
+        system.next_pass();
+
+        // depth_viewer
+        system.add_object(&quad_display);
+        system.next_pass();
+
+        // geometry
+        for object in geo_objects.iter() {
+            system.add_object(object);
+        }
+        system.add_object(&light_object_geo);
+        if draw_wireframe {
+            system.add_object(&wireframe_object);
+        }
+
+        system.finish_to_window(&mut window);
         timer_draw.stop();
     }
 
@@ -519,15 +539,13 @@ fn main() {
     timer_draw.print();
 }
 
-#[allow(dead_code)]
-#[derive(Clone)]
+#[derive(Clone, Data)]
+#[repr(C)]
 struct Light {
-    position: [f32; 4],
+    position: [f32; 3],
     strength: f32,
 }
 
-impl Data for Light {}
-
 struct MovingLight {
     start_time: std::time::Instant,
 }
@@ -542,21 +560,19 @@ impl MovingLight {
     fn get_data(&self) -> Light {
         let time = get_elapsed(self.start_time) / 16.0;
         Light {
-            position: [time.sin() * 100.0, 10.0, 0.0, 0.0],
+            position: [time.sin() * 100.0, 10.0, 0.0],
             strength: 1.0,
         }
     }
 }
 
-fn convert_to_shadow_casters(
-    base_object: Object<()>,
-    light_data: Light,
-) -> Vec<Object<((Matrix4,), (Matrix4,), (Matrix4,), (Light,))>> {
-    // if you want to make point lamps cast shadows, you need shadow cubemaps
-    // render-engine doesn't support geometry shaders, so the easiest way to do
-    // this is to convert one object into 6 different ones, one for each face of
-    // the cubemap, that each render to a different part of a 2D texture.
-    // for now this function assumes a 6x1 patch layout
+// the same object once for each face of the shadow cubemap, each with that
+// face's projection and view in its push constants. the up directions are the
+// usual ones for rendering cubemaps.
+fn cube_face_casters(
+    base_object: &Object<(Set<(Matrix4,)>,)>,
+    light_data: &Light,
+) -> Vec<Object<(Set<(Matrix4,)>,)>> {
     let view_directions = [
         vec3(1.0, 0.0, 0.0),
         vec3(-1.0, 0.0, 0.0),
@@ -575,71 +591,59 @@ fn convert_to_shadow_casters(
         vec3(0.0, -1.0, 0.0),
     ];
 
-    let patch_positions = [
-        [0.0, 0.0],
-        [1.0, 0.0],
-        [2.0, 0.0],
-        [3.0, 0.0],
-        [4.0, 0.0],
-        [5.0, 0.0],
-    ];
-
-    let (near, far) = (1.0, 250.0);
     // pi / 2 = 90 deg., 1.0 = aspect ratio
-    // we use a fov 1% too big to make sure sampling doesn't go between patches
-    let proj_data: Matrix4 = perspective(1.0, std::f32::consts::PI / 2.0 * 1.01, near, far).into();
-
-    let model_data: Matrix4 = scale(&Mat4::identity(), &vec3(0.1, 0.1, 0.1)).into();
+    let (near, far) = (1.0, 250.0);
+    let proj = perspective(1.0, std::f32::consts::PI / 2.0, near, far);
 
     let light_pos = make_vec3(&light_data.position);
 
     view_directions
         .iter()
         .zip(&up_directions)
-        .zip(&patch_positions)
-        .map(|((dir, up), patch_pos): ((&Vec3, &Vec3), &[f32; 2])| {
-            let view_data: Matrix4 = look_at(&light_pos, &(light_pos + dir), up).into();
-
-            // dynamic state for the current cubemap face, represents which part
-            // of the patched texture we draw to
-            let margin = 0.0;
-            let origin = [
-                patch_pos[0] * PATCH_DIMS[0] + margin,
-                patch_pos[1] * PATCH_DIMS[1] + margin,
-            ];
-            let dynamic_state = dynamic_state_for_bounds(
-                origin,
-                [PATCH_DIMS[0] - margin * 2.0, PATCH_DIMS[1] - margin * 2.0],
-            );
-
-            Object {
-                pipeline_spec: base_object.pipeline_spec.clone(),
-                vbuf: base_object.vbuf.clone(),
-                ibuf: base_object.ibuf.clone(),
-                collection: (
-                    (model_data,),
-                    (proj_data,),
-                    (view_data,),
-                    (light_data.clone(),),
-                ),
-                custom_dynamic_state: Some(dynamic_state),
-            }
+        .map(|(dir, up)| {
+            let view = look_at(&light_pos, &(light_pos + dir), up);
+            let view_proj: Matrix4 = (proj * view).into();
+
+            let mut object = base_object.clone();
+            object
+                .set_push_constants(Some(as_bytes(&view_proj)))
+                .unwrap();
+            object
         })
         .collect()
 }
 
-fn dynamic_state_for_bounds(origin: [f32; 2], dimensions: [f32; 2]) -> DynamicState {
-    DynamicState {
-        line_width: None,
-        viewports: Some(vec![Viewport {
-            origin,
-            dimensions,
-            depth_range: 0.0..1.0,
-        }]),
-        scissors: None,
+// the options for a variant of the geometry pass's fragment shader
+fn shader_options(defines: &[&str]) -> ShaderOptions {
+    defines
+        .iter()
+        .fold(ShaderOptions::default(), |options, name| {
+            options.define(name, None)
+        })
+}
+
+// switches the geometry objects to another variant. each variant gets its own
+// pipeline, which is only compiled the first time it's used.
+fn set_defines<C: Collection, M: Collection>(
+    resources: &Resources,
+    objects: &mut [Object<C, M>],
+    defines: &[&str],
+) {
+    let options = shader_options(defines);
+    for object in objects.iter_mut() {
+        object
+            .set_shader_options(resources, options.clone())
+            .unwrap_or_else(|e| panic!("couldn't switch to {:?}: {}", defines, e));
     }
 }
 
+// which face of the cubemap a blur pass draws
+#[derive(Clone, Data)]
+#[repr(C)]
+struct Face {
+    index: u32,
+}
+
 #[allow(dead_code)]
 #[derive(Clone)]
 struct Material {
@@ -1,4 +1,6 @@
+use render_engine::collection::SetLayers;
 use render_engine::object::ObjectPrototype;
+use render_engine::shaders::ShaderOptions;
 use render_engine::render_passes;
 use render_engine::system::{Pass, System};
 use render_engine::window::Window;
@@ -29,6 +31,7 @@ fn main() {
         HashMap::new(),
         "color",
     );
+    let resources = system.resources();
 
     window.set_render_pass(render_pass.clone());
 
@@ -39,6 +42,7 @@ fn main() {
         fill_type: PrimitiveTopology::TriangleList,
         read_depth: false,
         write_depth: false,
+        shader_options: ShaderOptions::default(),
         mesh: Mesh {
             vertices: vec![
                 VPosColor2D {
@@ -56,10 +60,14 @@ fn main() {
             ],
             indices: vec![0, 1, 2],
         },
+        material: (),
         collection: (),
+        set_layers: SetLayers::default(),
+        push_constants: None,
         custom_dynamic_state: None,
     }
-    .build(queue.clone(), render_pass.clone());
+    .build(&resources, render_pass.clone())
+    .unwrap();
 
     let mut start_window_timer = Timer::new("Start window");
     let mut add_object_timer = Timer::new("Add object");
@@ -2,6 +2,7 @@ use render_engine::input::{FrameInfo, get_elapsed};
 use render_engine::utils::upload_data;
 use render_engine::{Buffer, Device};
 use render_engine::collection::Data;
+use render_engine::layout::Pod;
 
 use nalgebra_glm::*;
 
@@ -15,8 +16,11 @@ pub fn relative_path(local_path: &str) -> PathBuf {
 }
 
 #[derive(Clone, Copy)]
+#[repr(C)]
 pub struct Matrix4([[f32; 4]; 4]);
 impl Data for Matrix4 {}
+// nothing but the array
+unsafe impl Pod for Matrix4 {}
 
 impl From<[[f32; 4]; 4]> for Matrix4 {
     fn from(item: [[f32; 4]; 4]) -> Self {
@@ -47,9 +51,8 @@ pub struct OrbitCamera {
     proj_mat: CameraMatrix,
 }
 
-// TODO: builders for changing fov, perspective, orbit dist, etc.
-impl OrbitCamera {
-    pub fn default() -> Self {
+impl Default for OrbitCamera {
+    fn default() -> Self {
         let center_position = vec3(0.0, 0.0, 0.0);
         let pitch: f32 = 0.0;
         let yaw: f32 = std::f32::consts::PI / 2.0;
@@ -81,7 +84,10 @@ impl OrbitCamera {
             proj_mat,
         }
     }
+}
 
+// TODO: builders for changing fov, perspective, orbit dist, etc.
+impl OrbitCamera {
     pub fn update(&mut self, frame_info: FrameInfo) {
         // check for scroll wheel
         let scroll: f32 = frame_info
@@ -179,8 +185,8 @@ pub struct FlyCamera {
     proj_mat: CameraMatrix,
 }
 
-impl FlyCamera {
-    pub fn default() -> Self {
+impl Default for FlyCamera {
+    fn default() -> Self {
         let position = vec3(0.0, 0.0, 0.0);
         let pitch: f32 = 0.0;
         let yaw: f32 = std::f32::consts::PI / 2.0;
@@ -209,7 +215,9 @@ impl FlyCamera {
             proj_mat: Mat4::identity().into(),
         }
     }
+}
 
+impl FlyCamera {
     pub fn move_forward(&mut self, delta: f32) {
         self.position += self.front * self.movement_speed * delta;
     }
@@ -320,6 +328,12 @@ pub struct MovingLight {
     start_time: std::time::Instant,
 }
 
+impl Default for MovingLight {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl MovingLight {
     pub fn new() -> Self {
         Self {
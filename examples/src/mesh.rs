@@ -4,10 +4,13 @@ Mesh: vertices and indices, nothing else
 Object: mesh + other stuff.
  */
 
+use render_engine::collection::SetLayers;
 use render_engine::mesh::{Mesh, PrimitiveTopology, Vertex};
-use render_engine::utils::load_texture;
+use render_engine::texture_loader::TextureLoader;
 use render_engine::{Format, Queue, Image, RenderPass};
 use render_engine::object::{ObjectPrototype, Object};
+use render_engine::resources::Resources;
+use render_engine::shaders::ShaderOptions;
 use render_engine::impl_vertex;
 
 use crate::relative_path;
@@ -35,8 +38,10 @@ pub fn load_textures(
 ) -> Vec<(Image, Image, Image)> {
     // loads all textures for all materials provided by returning 3 images for
     // each material: a diffuse texture, a specular texture, and a normal
-    // texture, in that order
-    materials
+    // texture, in that order. they're all decoded in parallel and uploaded
+    // in batches, then waited for at the end.
+    let loader = TextureLoader::new(queue);
+    let handles: Vec<_> = materials
         .iter()
         .map(|mat| {
             // diffuse
@@ -45,7 +50,7 @@ pub fn load_textures(
 
                 // if the diffuse texture path is empty or the file doesn't
                 // exist, use a placeholder
-                if mat.diffuse_texture == "" {
+                if mat.diffuse_texture.is_empty() {
                     println!("{} has no diffuse texture", mat.name);
                     relative_path("textures/missing.png")
                 } else if !maybe_path.exists() {
@@ -63,7 +68,7 @@ pub fn load_textures(
             let spec_path = {
                 let maybe_path = root_path.join(Path::new(&mat.specular_texture));
 
-                if mat.specular_texture == "" {
+                if mat.specular_texture.is_empty() {
                     println!("{} has no specular texture", mat.name);
                     relative_path("textures/missing-spec.png")
                 } else if !maybe_path.exists() {
@@ -81,7 +86,7 @@ pub fn load_textures(
             let normal_path = {
                 let maybe_path = root_path.join(Path::new(&mat.normal_texture));
 
-                if mat.normal_texture == "" {
+                if mat.normal_texture.is_empty() {
                     println!("{} has no normal texture", mat.name);
                     relative_path("textures/missing-normal.png")
                 } else if !maybe_path.exists() {
@@ -95,15 +100,40 @@ pub fn load_textures(
                 }
             };
 
-            let diff_tex = load_texture(queue.clone(), &diff_path, Format::R8G8B8A8Srgb);
-            let spec_tex = load_texture(queue.clone(), &spec_path, Format::R8G8B8A8Unorm);
-            let norm_tex = load_texture(queue.clone(), &normal_path, Format::R8G8B8A8Unorm);
+            // textures converted with re-texture-convert are used instead if
+            // they're there. it picks sRGB or linear from each file's name
+            // (_ddn normal maps are linear), and loading one that disagrees
+            // with the format below panics. the shaders read all 3 channels
+            // of normal maps, so those have to be converted without
+            // --normal-map.
+            let diff_path = prefer_ktx2(diff_path);
+            let spec_path = prefer_ktx2(spec_path);
+            let normal_path = prefer_ktx2(normal_path);
+
+            let diff_tex = loader.load(&diff_path, Format::R8G8B8A8Srgb);
+            let spec_tex = loader.load(&spec_path, Format::R8G8B8A8Unorm);
+            let norm_tex = loader.load(&normal_path, Format::R8G8B8A8Unorm);
 
             (diff_tex, spec_tex, norm_tex)
         })
+        .collect();
+
+    handles
+        .iter()
+        .map(|(diff, spec, norm)| (diff.wait(), spec.wait(), norm.wait()))
         .collect()
 }
 
+fn prefer_ktx2(path: PathBuf) -> PathBuf {
+    let ktx2_path = path.with_extension("ktx2");
+
+    if ktx2_path.exists() {
+        ktx2_path
+    } else {
+        path
+    }
+}
+
 pub fn convert_mesh(mesh: &tobj::Mesh) -> Mesh<VPosTexNorm> {
     // converts a tobj mesh to one of vertices render-engine will be able to use
     let mut vertices: Vec<VPosTexNorm> = vec![];
@@ -124,7 +154,7 @@ pub fn convert_mesh(mesh: &tobj::Mesh) -> Mesh<VPosTexNorm> {
         let tex_coord = if mesh.texcoords.len() <= i * 2 + 1 {
             [0.0, 0.0]
         } else {
-            [mesh.texcoords[i * 2], mesh.texcoords[i * 2 + 1] * -1.0]
+            [mesh.texcoords[i * 2], -mesh.texcoords[i * 2 + 1]]
         };
 
         let vertex = VPosTexNorm {
@@ -143,7 +173,7 @@ pub fn convert_mesh(mesh: &tobj::Mesh) -> Mesh<VPosTexNorm> {
 }
 
 pub fn add_tangents_multi(meshes: &[Mesh<VPosTexNorm>]) -> Vec<Mesh<VPosTexNormTan>> {
-    meshes.iter().map(|mesh| add_tangents(mesh)).collect()
+    meshes.iter().map(add_tangents).collect()
 }
 
 pub fn add_tangents(mesh: &Mesh<VPosTexNorm>) -> Mesh<VPosTexNormTan> {
@@ -185,34 +215,46 @@ pub fn add_tangents(mesh: &Mesh<VPosTexNorm>) -> Mesh<VPosTexNormTan> {
     }
 }
 
-pub fn fullscreen_quad(queue: Queue, render_pass: RenderPass, vs_path: PathBuf, fs_path: PathBuf) -> Object<()> {
+pub fn fullscreen_quad(resources: &Resources, render_pass: RenderPass, vs_path: PathBuf, fs_path: PathBuf) -> Object<()> {
     ObjectPrototype {
         vs_path,
         fs_path,
         fill_type: PrimitiveTopology::TriangleStrip,
         read_depth: false,
         write_depth: false,
-        mesh: Mesh {
-            vertices: vec![
-                VPos2D {
-                    position: [-1.0, -1.0],
-                },
-                VPos2D {
-                    position: [-1.0, 1.0],
-                },
-                VPos2D {
-                    position: [1.0, -1.0],
-                },
-                VPos2D {
-                    position: [1.0, 1.0],
-                },
-            ],
-            indices: vec![0, 1, 2, 3],
-        },
+        shader_options: ShaderOptions::default(),
+        mesh: fullscreen_quad_mesh(),
+        material: (),
         collection: (),
+        set_layers: SetLayers::default(),
+        push_constants: None,
         custom_dynamic_state: None,
     }
-    .build(queue, render_pass)
+    .build(resources, render_pass)
+    .unwrap()
+}
+
+pub fn fullscreen_quad_mesh() -> Mesh<VPos2D> {
+    // a square covering the whole screen, drawn as a triangle strip. for
+    // fullscreen quads that need more than fullscreen_quad gives them, like
+    // push constants.
+    Mesh {
+        vertices: vec![
+            VPos2D {
+                position: [-1.0, -1.0],
+            },
+            VPos2D {
+                position: [-1.0, 1.0],
+            },
+            VPos2D {
+                position: [1.0, -1.0],
+            },
+            VPos2D {
+                position: [1.0, 1.0],
+            },
+        ],
+        indices: vec![0, 1, 2, 3],
+    }
 }
 
 pub fn wireframe(mesh: &Mesh<VPos>) -> Mesh<VPos> {
@@ -0,0 +1,553 @@
+// #[derive(Data)] for structs that get uploaded as uniform or storage buffers.
+//
+// Besides implementing render_engine::collection::Data, it checks at compile
+// time that the struct's fields are where GLSL expects them to be under std140
+// (the default, used by uniform buffers) or std430 (with #[data(std430)], for
+// storage buffers). Fields whose names start with an underscore are treated as
+// explicit padding and left out of the GLSL side.
+//
+// It also implements render_engine::layout::Pod, so the struct can be used as
+// push constants with utils::as_bytes. For that Rust mustn't add any padding
+// of its own, which only happens with f64s: put explicit padding fields
+// wherever GLSL has a gap.
+//
+// Arrays of 2 to 4 scalars are vectors and arrays of 2 to 4 f32 vectors are
+// matrices, with each inner array being a column. Any other type that isn't a
+// scalar or an array has to derive Data itself.
+//
+// The common mistakes (vec3 arrays, mat3, arrays of floats in std140) are
+// reported with an explanation. Anything else that doesn't line up fails to
+// compile with an error pointing at the field, for example
+// `[(); (rust_offset == glsl_offset) as usize]` not having 1 element.
+
+extern crate proc_macro;
+
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, DeriveInput, Expr, Fields, Ident, Lit, Meta, NestedMeta, Type};
+
+#[proc_macro_derive(Data, attributes(data))]
+pub fn derive_data(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Std {
+    Std140,
+    Std430,
+}
+
+#[derive(Clone, Copy)]
+struct Scalar {
+    size: usize,
+    // "float" and "vec" for f32, "int" and "ivec" for i32...
+    glsl: &'static str,
+    vector_prefix: &'static str,
+}
+
+enum Ty<'a> {
+    Scalar(Scalar),
+    Vector(Scalar, usize),
+    Matrix { columns: usize, rows: usize },
+    Array(Box<Ty<'a>>, &'a Type, &'a Expr),
+    Struct(&'a Type),
+}
+
+struct Field<'a> {
+    ident: &'a Ident,
+    rust_ty: &'a Type,
+    ty: Ty<'a>,
+    padding: bool,
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "Data can't be derived for generic structs",
+        ));
+    }
+    if !has_repr_c(input)? {
+        return Err(syn::Error::new_spanned(
+            name,
+            "Data structs need #[repr(C)], otherwise Rust is free to reorder the fields",
+        ));
+    }
+    let std = std_attribute(input)?;
+
+    let named = match &input.data {
+        syn::Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    name,
+                    "Data can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                name,
+                "Data can only be derived for structs",
+            ))
+        }
+    };
+
+    let fields = named
+        .iter()
+        .map(|field| {
+            let ident = field.ident.as_ref().unwrap();
+            Ok(Field {
+                ident,
+                rust_ty: &field.ty,
+                ty: classify(&field.ty)?,
+                padding: ident.to_string().starts_with('_'),
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    if fields.iter().all(|field| field.padding) {
+        return Err(syn::Error::new_spanned(
+            name,
+            "Data structs need at least one field that isn't padding",
+        ));
+    }
+
+    for field in fields.iter().filter(|field| !field.padding) {
+        check_known_mistakes(&field.ty, field.rust_ty, std)?;
+    }
+
+    let layout = quote!(::render_engine::layout);
+    let checks = checks(&fields, std);
+    let pod_checks = pod_checks(name, &fields);
+    let std430 = std == Std::Std430;
+
+    let (align140, size140) = struct_layout(&fields, Std::Std140);
+    let (align430, size430) = struct_layout(&fields, Std::Std430);
+
+    let glsl_fields = fields
+        .iter()
+        .filter(|field| !field.padding)
+        .map(|field| glsl_declaration(&field.ty, &field.ident.to_string()));
+
+    let name_str = name.to_string();
+
+    Ok(quote! {
+        impl ::render_engine::collection::Data for #name {}
+
+        unsafe impl #layout::Pod for #name {}
+
+        impl #layout::GlslLayout for #name {
+            const GLSL_NAME: &'static str = #name_str;
+            const STD430: bool = #std430;
+            const STD140_ALIGN: usize = #align140;
+            const STD140_SIZE: usize = #layout::align_up(#size140, Self::STD140_ALIGN);
+            const STD430_ALIGN: usize = #align430;
+            const STD430_SIZE: usize = #layout::align_up(#size430, Self::STD430_ALIGN);
+
+            fn glsl_fields() -> String {
+                let mut fields = String::new();
+                #(fields.push_str(&#glsl_fields);)*
+                fields
+            }
+        }
+
+        const _: () = {
+            #(#checks)*
+            #(#pod_checks)*
+        };
+    })
+}
+
+fn has_repr_c(input: &DeriveInput) -> syn::Result<bool> {
+    for attr in input.attrs.iter().filter(|attr| attr.path.is_ident("repr")) {
+        if let Meta::List(list) = attr.parse_meta()? {
+            let is_c = list.nested.iter().any(|nested| match nested {
+                NestedMeta::Meta(Meta::Path(path)) => path.is_ident("C"),
+                _ => false,
+            });
+            if is_c {
+                return Ok(true);
+            }
+        }
+    }
+
+    Ok(false)
+}
+
+// #[data(std140)] or #[data(std430)], std140 if there's neither
+fn std_attribute(input: &DeriveInput) -> syn::Result<Std> {
+    let mut std = Std::Std140;
+
+    for attr in input.attrs.iter().filter(|attr| attr.path.is_ident("data")) {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => {
+                return Err(syn::Error::new_spanned(
+                    meta,
+                    "expected #[data(std140)] or #[data(std430)]",
+                ))
+            }
+        };
+
+        for nested in list.nested.iter() {
+            std = match nested {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("std140") => Std::Std140,
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("std430") => Std::Std430,
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        nested,
+                        "expected std140 or std430",
+                    ))
+                }
+            };
+        }
+    }
+
+    Ok(std)
+}
+
+fn classify(ty: &Type) -> syn::Result<Ty<'_>> {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => {
+            if let Some(scalar) = scalar(&path.path) {
+                return Ok(Ty::Scalar(scalar));
+            }
+            if path.path.is_ident("bool") {
+                return Err(syn::Error::new_spanned(
+                    ty,
+                    "bools are 4 bytes in GLSL but 1 in Rust, use a u32 instead",
+                ));
+            }
+
+            Ok(Ty::Struct(ty))
+        }
+        Type::Paren(paren) => classify(&paren.elem),
+        Type::Group(group) => classify(&group.elem),
+        Type::Array(array) => {
+            let elem = classify(&array.elem)?;
+
+            Ok(match (elem, literal_len(&array.len)) {
+                (Ty::Scalar(scalar), Some(len)) if (2..=4).contains(&len) => {
+                    Ty::Vector(scalar, len)
+                }
+                (Ty::Vector(scalar, rows), Some(columns))
+                    if scalar.glsl == "float" && (2..=4).contains(&columns) =>
+                {
+                    Ty::Matrix { columns, rows }
+                }
+                (elem, _) => Ty::Array(Box::new(elem), &array.elem, &array.len),
+            })
+        }
+        _ => Err(syn::Error::new_spanned(
+            ty,
+            "unsupported type for a Data struct, use scalars, arrays or other Data structs",
+        )),
+    }
+}
+
+fn scalar(path: &syn::Path) -> Option<Scalar> {
+    let (size, glsl, vector_prefix) = if path.is_ident("f32") {
+        (4, "float", "vec")
+    } else if path.is_ident("i32") {
+        (4, "int", "ivec")
+    } else if path.is_ident("u32") {
+        (4, "uint", "uvec")
+    } else if path.is_ident("f64") {
+        (8, "double", "dvec")
+    } else {
+        return None;
+    };
+
+    Some(Scalar {
+        size,
+        glsl,
+        vector_prefix,
+    })
+}
+
+fn literal_len(len: &Expr) -> Option<usize> {
+    match len {
+        Expr::Lit(lit) => match &lit.lit {
+            Lit::Int(int) => int.base10_parse().ok(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// errors with an explanation for the layouts people usually get wrong. the
+// generated checks would catch these too, just with a less helpful message.
+fn check_known_mistakes(ty: &Ty, rust_ty: &Type, std: Std) -> syn::Result<()> {
+    let message = match ty {
+        Ty::Matrix { columns, rows } if std == Std::Std140 && *rows != 4 => format!(
+            "each column of a matrix is aligned to 16 bytes in std140, use [[f32; 4]; {}] and ignore the last row",
+            columns
+        ),
+        Ty::Matrix { columns, rows: 3 } => format!(
+            "each column of a {} is aligned to 16 bytes, use [[f32; 4]; {}] and ignore the last row",
+            if *columns == 3 {
+                "mat3".to_string()
+            } else {
+                format!("mat{}x3", columns)
+            },
+            columns
+        ),
+        Ty::Array(elem, _, _) => {
+            match **elem {
+                Ty::Scalar(scalar) if std == Std::Std140 => format!(
+                    "every element of a {} array is aligned to 16 bytes in std140, use [[{}; 4]; N] and only the first component, or #[data(std430)]",
+                    scalar.glsl,
+                    rust_name(scalar)
+                ),
+                Ty::Vector(scalar, 3) => format!(
+                    "every element of a {}3 array is aligned to 16 bytes, use [[{}; 4]; N]",
+                    scalar.vector_prefix,
+                    rust_name(scalar)
+                ),
+                Ty::Vector(scalar, 2) if std == Std::Std140 => format!(
+                    "every element of a {}2 array is aligned to 16 bytes in std140, use [[{}; 4]; N]",
+                    scalar.vector_prefix,
+                    rust_name(scalar)
+                ),
+                _ => return check_known_mistakes(elem, rust_ty, std),
+            }
+        }
+        _ => return Ok(()),
+    };
+
+    Err(syn::Error::new_spanned(rust_ty, message))
+}
+
+fn rust_name(scalar: Scalar) -> &'static str {
+    match scalar.glsl {
+        "float" => "f32",
+        "int" => "i32",
+        "uint" => "u32",
+        _ => "f64",
+    }
+}
+
+// the alignment and size of a type in GLSL, as constant expressions
+fn ty_layout(ty: &Ty, std: Std) -> (TokenStream, TokenStream) {
+    let layout = quote!(::render_engine::layout);
+
+    match ty {
+        Ty::Scalar(scalar) => {
+            let size = scalar.size;
+            (quote!(#size), quote!(#size))
+        }
+        Ty::Vector(scalar, count) => {
+            // vec3s are aligned like vec4s
+            let align = if *count == 2 { 2 } else { 4 } * scalar.size;
+            let size = count * scalar.size;
+            (quote!(#align), quote!(#size))
+        }
+        Ty::Matrix { columns, rows } => {
+            // an array of column vectors
+            let column_align = if *rows == 2 { 8 } else { 16 };
+            let stride = match std {
+                Std::Std140 => 16,
+                Std::Std430 => column_align,
+            };
+            let size = stride * columns;
+            (quote!(#stride), quote!(#size))
+        }
+        Ty::Array(elem, _, len) => {
+            let (align, stride) = array_layout(elem, std);
+            let size = quote!(#layout::align_up(#stride, #align) * (#len));
+            (align, size)
+        }
+        Ty::Struct(rust_ty) => match std {
+            Std::Std140 => (
+                quote!(<#rust_ty as #layout::GlslLayout>::STD140_ALIGN),
+                quote!(<#rust_ty as #layout::GlslLayout>::STD140_SIZE),
+            ),
+            Std::Std430 => (
+                quote!(<#rust_ty as #layout::GlslLayout>::STD430_ALIGN),
+                quote!(<#rust_ty as #layout::GlslLayout>::STD430_SIZE),
+            ),
+        },
+    }
+}
+
+// the alignment of an array and the distance between its elements
+fn array_layout(elem: &Ty, std: Std) -> (TokenStream, TokenStream) {
+    let layout = quote!(::render_engine::layout);
+    let (elem_align, elem_size) = ty_layout(elem, std);
+
+    let align = match std {
+        Std::Std140 => quote!(#layout::align_up(#elem_align, 16)),
+        Std::Std430 => elem_align,
+    };
+    let stride = quote!(#layout::align_up(#elem_size, #align));
+
+    (align, stride)
+}
+
+// the alignment and the end of the last field of the struct
+fn struct_layout(fields: &[Field], std: Std) -> (TokenStream, TokenStream) {
+    let layout = quote!(::render_engine::layout);
+
+    let mut align = quote!(1);
+    let mut end = quote!(0);
+    for field in fields.iter().filter(|field| !field.padding) {
+        let (field_align, field_size) = ty_layout(&field.ty, std);
+        align = quote!(#layout::max(#align, #field_align));
+        end = quote!(#layout::align_up(#end, #field_align) + #field_size);
+    }
+
+    if std == Std::Std140 {
+        align = quote!(#layout::align_up(#align, 16));
+    }
+
+    (align, end)
+}
+
+// constant items that fail to compile if the Rust layout doesn't match the
+// GLSL one
+fn checks(fields: &[Field], std: Std) -> Vec<TokenStream> {
+    let layout = quote!(::render_engine::layout);
+    let std_name = match std {
+        Std::Std140 => "std140",
+        Std::Std430 => "std430",
+    };
+
+    let mut checks = vec![];
+    let mut glsl_end = quote!(0);
+    let mut rust_end = quote!(0);
+
+    for field in fields {
+        let rust_ty = field.rust_ty;
+        let rust_offset =
+            quote!(#layout::align_up(#rust_end, ::core::mem::align_of::<#rust_ty>()));
+        rust_end = quote!(#rust_offset + ::core::mem::size_of::<#rust_ty>());
+
+        if field.padding {
+            continue;
+        }
+
+        let (align, size) = ty_layout(&field.ty, std);
+        let glsl_offset = quote!(#layout::align_up(#glsl_end, #align));
+        glsl_end = quote!(#glsl_offset + #size);
+
+        let span = field.rust_ty.span();
+        checks.push(assertion(
+            &format!("{}_offset_doesnt_match_{}", field.ident, std_name),
+            span,
+            quote!(#rust_offset == #glsl_offset),
+        ));
+
+        let (_, glsl_size) = ty_layout(&field.ty, std);
+        checks.push(assertion(
+            &format!("{}_size_doesnt_match_{}", field.ident, std_name),
+            span,
+            quote!(::core::mem::size_of::<#rust_ty>() == #glsl_size),
+        ));
+
+        checks.extend(nested_checks(&field.ty, std, std_name, &field.ident.to_string(), span));
+    }
+
+    checks
+}
+
+// every field has to be Pod, and together they have to cover the whole struct
+fn pod_checks(name: &Ident, fields: &[Field]) -> Vec<TokenStream> {
+    let layout = quote!(::render_engine::layout);
+
+    let mut checks: Vec<TokenStream> = fields
+        .iter()
+        .map(|field| {
+            let rust_ty = field.rust_ty;
+            quote_spanned! {rust_ty.span()=>
+                const _: () = #layout::assert_pod::<#rust_ty>();
+            }
+        })
+        .collect();
+
+    let rust_tys = fields.iter().map(|field| field.rust_ty);
+    checks.push(assertion(
+        "struct_has_padding_use_explicit_padding_fields",
+        name.span(),
+        quote!(::core::mem::size_of::<#name>() == 0 #(+ ::core::mem::size_of::<#rust_tys>())*),
+    ));
+
+    checks
+}
+
+// arrays need the same distance between elements in Rust and GLSL, and
+// nested structs need to have been checked against the same layout
+fn nested_checks(ty: &Ty, std: Std, std_name: &str, name: &str, span: Span) -> Vec<TokenStream> {
+    let layout = quote!(::render_engine::layout);
+    let std430 = std == Std::Std430;
+
+    match ty {
+        Ty::Array(elem, elem_ty, _) => {
+            let (_, stride) = array_layout(elem, std);
+            let mut checks = vec![assertion(
+                &format!("{}_array_stride_doesnt_match_{}", name, std_name),
+                span,
+                quote!(::core::mem::size_of::<#elem_ty>() == #stride),
+            )];
+            checks.extend(nested_checks(elem, std, std_name, name, span));
+            checks
+        }
+        Ty::Struct(rust_ty) => vec![assertion(
+            &format!("{}_is_not_checked_against_{}", name, std_name),
+            span,
+            quote!(<#rust_ty as #layout::GlslLayout>::STD430 == #std430),
+        )],
+        _ => vec![],
+    }
+}
+
+fn assertion(name: &str, span: Span, condition: TokenStream) -> TokenStream {
+    let ident = Ident::new(name, span);
+    quote_spanned! {span=>
+        #[allow(non_upper_case_globals, dead_code)]
+        const #ident: [(); 1] = [(); (#condition) as usize];
+    }
+}
+
+// an expression making a String like "    vec3 position;\n"
+fn glsl_declaration(ty: &Ty, name: &str) -> TokenStream {
+    // arrays of arrays are written with the outermost size first
+    let mut lens = vec![];
+    let mut base = ty;
+    while let Ty::Array(elem, _, len) = base {
+        lens.push(len);
+        base = &**elem;
+    }
+
+    let base_name = match base {
+        Ty::Scalar(scalar) => {
+            let glsl = scalar.glsl;
+            quote!(#glsl)
+        }
+        Ty::Vector(scalar, count) => {
+            let glsl = format!("{}{}", scalar.vector_prefix, count);
+            quote!(#glsl)
+        }
+        Ty::Matrix { columns, rows } => {
+            let glsl = if columns == rows {
+                format!("mat{}", columns)
+            } else {
+                format!("mat{}x{}", columns, rows)
+            };
+            quote!(#glsl)
+        }
+        Ty::Struct(rust_ty) => quote!(<#rust_ty as ::render_engine::layout::GlslLayout>::GLSL_NAME),
+        Ty::Array(..) => unreachable!(),
+    };
+
+    let format = format!("    {{}} {}{};\n", name, "[{}]".repeat(lens.len()));
+    quote!(format!(#format, #base_name #(, #lens)*))
+}
@@ -0,0 +1,79 @@
+// Prints what a set of shaders expects as input: vertex attributes, the
+// varyings between stages, descriptor sets and push constants.
+//
+// usage: re-shader-info <vertex shader> <fragment shader> [options]
+//     --gs <path>               geometry shader
+//     --tcs <path> --tes <path> tessellation shaders
+//     -D NAME[=VALUE]           define a macro
+//     -I <dir>                  add an include directory
+//     --json                    print JSON instead of text
+
+use render_engine::shader_report::ShaderReport;
+use render_engine::shaders::ShaderOptions;
+
+use std::path::PathBuf;
+
+const USAGE: &str = "usage: re-shader-info <vertex shader> <fragment shader> [--gs <path>] \
+[--tcs <path> --tes <path>] [-D NAME[=VALUE]] [-I <dir>] [--json]";
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let mut paths = vec![];
+    let mut options = ShaderOptions::default();
+    let mut json = false;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--gs" => options.gs_path = Some(PathBuf::from(next_value(&mut args, &arg))),
+            "--tcs" => options.tcs_path = Some(PathBuf::from(next_value(&mut args, &arg))),
+            "--tes" => options.tes_path = Some(PathBuf::from(next_value(&mut args, &arg))),
+            "-D" => options = define(options, &next_value(&mut args, &arg)),
+            "-I" => options = options.include_dir(PathBuf::from(next_value(&mut args, &arg))),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if arg.starts_with("-D") => options = define(options, &arg[2..]),
+            _ if arg.starts_with("-I") => options = options.include_dir(PathBuf::from(&arg[2..])),
+            _ if arg.starts_with('-') => usage_error(&format!("unknown option {}", arg)),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    if paths.len() != 2 {
+        usage_error("expected a vertex and a fragment shader");
+    }
+    if options.tcs_path.is_some() != options.tes_path.is_some() {
+        usage_error("--tcs and --tes have to be given together");
+    }
+
+    match ShaderReport::from_files(&paths[0], &paths[1], &options) {
+        Ok(report) if json => println!("{}", report.to_json()),
+        Ok(report) => print!("{}", report),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn next_value(args: &mut impl Iterator<Item = String>, option: &str) -> String {
+    args.next()
+        .unwrap_or_else(|| usage_error(&format!("{} needs a value", option)))
+}
+
+fn define(options: ShaderOptions, define: &str) -> ShaderOptions {
+    let mut parts = define.splitn(2, '=');
+    let name = parts.next().unwrap_or_default();
+    if name.is_empty() {
+        usage_error("-D needs a macro name");
+    }
+
+    options.define(name, parts.next())
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    std::process::exit(2);
+}
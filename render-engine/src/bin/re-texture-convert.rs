@@ -0,0 +1,161 @@
+// Converts PNG, TGA and other images into BC-compressed KTX2 files with full
+// mip chains. load_texture loads the results like any other image.
+//
+// usage: re-texture-convert <image or directory>... [options]
+//     -o, --out <dir>           where to write the .ktx2 files, next to the
+//                               inputs by default
+//     --format bc1|bc3|bc4|bc5  picked from each image by default
+//     --srgb                    mark every texture as sRGB
+//     --linear                  mark every texture as linear
+//     --linear-suffix <suffix>  also treat images ending in this as linear
+//     --normal-map              store normal maps as X and Y only, in BC5
+//
+// without --srgb or --linear each image is sRGB unless its name (without the
+// extension) ends in one of LINEAR_SUFFIXES, so a model's texture directory
+// can be converted in one go: diffuse maps come out sRGB, normal, specular
+// and other data maps linear.
+
+use render_engine::texture_convert::{convert, BlockFormat, ConvertOptions};
+use render_engine::texture_data::TextureData;
+
+use std::path::{Path, PathBuf};
+
+const USAGE: &str = "usage: re-texture-convert <image or directory>... [-o <dir>] \
+[--format bc1|bc3|bc4|bc5] [--srgb | --linear] [--linear-suffix <suffix>]... [--normal-map]";
+
+// only files with these extensions are converted when given a directory
+const EXTENSIONS: &[&str] = &["png", "tga", "jpg", "jpeg", "bmp"];
+
+// the usual endings of textures that hold data rather than colours, matched
+// case-insensitively
+const LINEAR_SUFFIXES: &[&str] = &[
+    "_n",
+    "_nrm",
+    "_normal",
+    "_ddn",
+    "_bump",
+    "_height",
+    "_disp",
+    "_s",
+    "_spec",
+    "_specular",
+    "_rough",
+    "_roughness",
+    "_metal",
+    "_metallic",
+    "_ao",
+    "_mask",
+];
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let mut inputs = vec![];
+    let mut out_dir = None;
+    let mut options = ConvertOptions::default();
+    // None to decide for each image from its name
+    let mut srgb = None;
+    let mut linear_suffixes: Vec<String> = LINEAR_SUFFIXES
+        .iter()
+        .map(|suffix| suffix.to_string())
+        .collect();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--out" => out_dir = Some(PathBuf::from(next_value(&mut args, &arg))),
+            "--format" => {
+                let name = next_value(&mut args, &arg);
+                options.format = Some(
+                    BlockFormat::from_name(&name)
+                        .unwrap_or_else(|| usage_error(&format!("unknown format {}", name))),
+                );
+            }
+            "--srgb" => srgb = Some(true),
+            "--linear" => srgb = Some(false),
+            "--linear-suffix" => linear_suffixes.push(next_value(&mut args, &arg).to_lowercase()),
+            "--normal-map" => options.normal_map = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if arg.starts_with('-') => usage_error(&format!("unknown option {}", arg)),
+            _ => inputs.push(PathBuf::from(arg)),
+        }
+    }
+
+    if inputs.is_empty() {
+        usage_error("expected at least one image or directory");
+    }
+
+    for path in images(&inputs) {
+        let out_path = out_dir
+            .as_ref()
+            .map(|dir| dir.join(path.file_name().unwrap()))
+            .unwrap_or_else(|| path.clone())
+            .with_extension("ktx2");
+
+        let options = ConvertOptions {
+            srgb: srgb.unwrap_or_else(|| !is_linear(&path, &linear_suffixes)),
+            ..options.clone()
+        };
+        let texture = convert(TextureData::open(&path), &options);
+        std::fs::write(&out_path, texture.to_ktx2())
+            .unwrap_or_else(|e| panic!("couldn't write {:?}: {}", out_path, e));
+
+        println!(
+            "{} -> {} ({:?}, {} levels)",
+            path.display(),
+            out_path.display(),
+            texture.format,
+            texture.levels.len()
+        );
+    }
+}
+
+// the files given, and the images in the directories given, sorted so the
+// output is always in the same order
+fn images(inputs: &[PathBuf]) -> Vec<PathBuf> {
+    let mut paths = vec![];
+
+    for input in inputs {
+        if input.is_dir() {
+            let entries = std::fs::read_dir(input)
+                .unwrap_or_else(|e| panic!("couldn't read {:?}: {}", input, e));
+            let mut images: Vec<_> = entries
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| is_image(path))
+                .collect();
+            images.sort();
+            paths.extend(images);
+        } else {
+            paths.push(input.clone());
+        }
+    }
+
+    paths
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+fn is_linear(path: &Path, linear_suffixes: &[String]) -> bool {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    linear_suffixes.iter().any(|suffix| stem.ends_with(suffix))
+}
+
+fn next_value(args: &mut impl Iterator<Item = String>, option: &str) -> String {
+    args.next()
+        .unwrap_or_else(|| usage_error(&format!("{} needs a value", option)))
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    std::process::exit(2);
+}
@@ -0,0 +1,82 @@
+// Uniform buffers for data that's uploaded every frame. Creating a new
+// CpuAccessibleBuffer for every upload means a new allocation every time, so
+// instead every type of data gets a CpuBufferPool that hands out pieces of one
+// bigger buffer. A piece goes back to the pool once the descriptor set using it
+// is dropped and the GPU is done with it, so after the first few frames the
+// pool stops growing, no matter how many frames are in flight.
+
+// Every System has one UniformPool, in its Resources, shared by every Set
+// uploaded with them.
+
+use vulkano::buffer::cpu_pool::CpuBufferPoolSubbuffer;
+use vulkano::buffer::{BufferUsage, CpuBufferPool};
+use vulkano::device::Device;
+use vulkano::memory::pool::StdMemoryPool;
+
+use crate::collection::Data;
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+pub type PooledBuffer<T> = CpuBufferPoolSubbuffer<T, Arc<StdMemoryPool>>;
+
+pub struct UniformPool {
+    device: Arc<Device>,
+    // a CpuBufferPool<T> for every T uploaded so far
+    pools: Mutex<HashMap<TypeId, Box<dyn Any + Send + Sync>>>,
+    stats: Mutex<PoolStats>,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct PoolStats {
+    // pieces handed out
+    pub uploads: u32,
+    // times a pool had to allocate a bigger buffer because every piece was in
+    // use
+    pub allocations: u32,
+}
+
+impl UniformPool {
+    pub fn new(device: Arc<Device>) -> Self {
+        Self {
+            device,
+            pools: Mutex::new(HashMap::new()),
+            stats: Mutex::new(PoolStats::default()),
+        }
+    }
+
+    pub fn upload<T: Data>(&self, data: T) -> PooledBuffer<T> {
+        let pool = self
+            .pools
+            .lock()
+            .unwrap()
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| {
+                Box::new(CpuBufferPool::<T>::new(
+                    self.device.clone(),
+                    BufferUsage::uniform_buffer(),
+                ))
+            })
+            .downcast_ref::<CpuBufferPool<T>>()
+            .expect("uniform pool stored under the wrong type")
+            // cloning only clones an Arc, and means the lock isn't held while
+            // allocating
+            .clone();
+
+        let capacity = pool.capacity();
+        let buffer = pool.next(data).unwrap();
+
+        let mut stats = self.stats.lock().unwrap();
+        stats.uploads += 1;
+        if pool.capacity() > capacity {
+            stats.allocations += 1;
+        }
+
+        buffer
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        self.stats.lock().unwrap().clone()
+    }
+}
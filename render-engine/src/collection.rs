@@ -2,13 +2,43 @@
 There are two types of data that can be used in a collection (data passed to
 shaders): images and structs. Structs must implement the Data trait to be
 uploaded to the GPU, which just means implementing Send, Sync, Clone and being
-'static.
+'static. Use #[derive(Data)] rather than implementing it by hand: it also
+checks at compile time that the struct's fields line up with GLSL's std140
+layout (or std430, with #[data(std430)]), and can print the matching GLSL.
 
-The SetUpload trait is implemented for any* tuple of images and structs that
-implement Data. For example, it is implemented for (Image, Data, Image) and
-(Data, Data, Data) and (Image,) and (Data, Image,) and so on.
+Structs are bound as uniform buffers, which are small (often only 16KB
+guaranteed) and fixed-size. For things like a list of hundreds of lights or
+a particle buffer, wrap a slice in a StorageSlice instead, which is bound as a
+storage buffer holding a runtime-sized array:
 
-*: any tuple up to size 3. Sorry.
+layout(set = 0, binding = 0) readonly buffer Lights { Light lights[]; };
+
+StorageSlice<T> is read-only, StorageSlice<T, ReadWrite> can also be written
+by the shader. Similarly, wrapping an image in StorageImage binds it as a
+storage image (image2D in GLSL) rather than with a sampler.
+
+A StorageSlice is also how lots of objects can share one buffer rather than
+each uploading their own set: put every object's model matrix and material in
+one slice in the globals, and give each object its index in the slice as push
+constants.
+
+Images are sampled with SamplerDesc::default() (linear, repeating). To use a
+different sampler, pair the image with a SamplerDesc in a Sampled, for example
+Sampled(texture, SamplerDesc::nearest()) for pixel art. Equal SamplerDescs
+share the same sampler, so there's no cost to creating them on the fly.
+
+The SetUpload trait is implemented for any* tuple of elements that implement
+SetElement, which are images, Sampleds, structs that implement Data,
+StorageSlices and StorageImages. For example, it is implemented for (Image, Data, Image) and
+(Data, Data, Data) and (Image,) and (Data, StorageSlice<Light>) and so on.
+
+*: any tuple up to size 8, in any mix of elements. The impls are generated by
+the macros at the bottom of this file.
+
+A set can also be a TextureArray instead of a tuple, which binds a whole list
+of images to one array in the shader. Put every texture of a scene in one and
+objects can share a single material set, picking their textures by index. The
+array has to be the only binding in its set, see TextureArray.
 
 These tuples should represent a set within a collection that will be used in a
 shader. SetUpload requires implementing upload, which uploads the data to the
@@ -20,14 +50,15 @@ because it means you can initialize the set once with all the annoying data
 necessary to upload it (the pipeline) and easily re-upload it and change the
 underlying data.
 
+let resources = system.resources();
 let mut set = Set::new(
     (some_struct,),
-    device,
+    &resources,
     pipeline,
     0      // set idx
 );
 set.data.0 = updated_struct;
-set.upload(device);
+set.upload(&resources);
 
 Ta-da! Now to collections. Collection is a trait implemented for all* tuples of
 sets that allows converting them into Vec<Arc<DescriptorSet>>, which is most
@@ -35,21 +66,48 @@ concrete form of a collection: it is the type taken by draw and draw_indexed
 when creating the command buffer. Collection requires the get() function, which
 returns a Vec<Arc<DescriptorSet>>.
 
-*: any tuple up to size 4.
+*: any tuple up to size 8.
 
 So that's it: how you can go from tuples of images and arbitrary structs to a
 type that can be used in draw and draw_indexed. How magnificently mediocre.
+
+Objects don't have to supply every set themselves though. Sets are layered
+from the ones that change least often to the ones that change most often, and
+SetLayers says which set index each layer starts at:
+  - globals, like the camera and lights, given once per frame to
+    System::set_globals and bound for every object
+  - the images the current pass reads (images_needed_tags)
+  - the object's material
+  - the object's own sets
  */
 
-use vulkano::descriptor::descriptor_set::{DescriptorSet, PersistentDescriptorSet};
-use vulkano::device::Device;
-use vulkano::image::ImageViewAccess;
+use vulkano::buffer::{BufferAccess, BufferUsage, CpuAccessibleBuffer, TypedBufferAccess};
+use vulkano::descriptor::descriptor::DescriptorDesc;
+use vulkano::descriptor::descriptor_set::{
+    DescriptorPool, DescriptorPoolAlloc, DescriptorSet, DescriptorSetDesc, DescriptorWrite,
+    PersistentDescriptorSet, PersistentDescriptorSetBuf, PersistentDescriptorSetBuilder,
+    PersistentDescriptorSetError, PersistentDescriptorSetImg, PersistentDescriptorSetSampler,
+    StdDescriptorPoolAlloc, UnsafeDescriptorSet,
+};
+use vulkano::descriptor::pipeline_layout::{PipelineLayoutAbstract, PipelineLayoutDesc};
+use vulkano::device::{Device, DeviceOwned};
+use vulkano::format::Format;
+use vulkano::image::{Dimensions, ImageViewAccess};
+use vulkano::memory::Content;
 use vulkano::pipeline::GraphicsPipelineAbstract;
+use vulkano::sampler::Sampler;
 
-use crate::utils::{upload_data, default_sampler};
+use crate::buffer_pool::PooledBuffer;
+use crate::resources::Resources;
+use crate::sampler::SamplerDesc;
 
+use std::fmt;
+use std::marker::PhantomData;
 use std::sync::Arc;
 
+// #[derive(Data)], which also checks the struct's layout against GLSL's
+pub use render_engine_derive::Data;
+
 pub trait Collection {
     fn get(&self) -> Vec<Arc<dyn DescriptorSet + Send + Sync>>;
 }
@@ -60,32 +118,6 @@ impl Collection for () {
     }
 }
 
-impl<T: SetUpload> Collection for (Set<T>,) {
-    fn get(&self) -> Vec<Arc<dyn DescriptorSet + Send + Sync>> {
-        vec![self.0.get()]
-    }
-}
-
-impl<T1: SetUpload, T2: SetUpload> Collection for (Set<T1>, Set<T2>) {
-    fn get(&self) -> Vec<Arc<dyn DescriptorSet + Send + Sync>> {
-        vec![self.0.get(), self.1.get()]
-    }
-}
-
-impl<T1: SetUpload, T2: SetUpload, T3: SetUpload> Collection for (Set<T1>, Set<T2>, Set<T3>) {
-    fn get(&self) -> Vec<Arc<dyn DescriptorSet + Send + Sync>> {
-        vec![self.0.get(), self.1.get(), self.2.get()]
-    }
-}
-
-impl<T1: SetUpload, T2: SetUpload, T3: SetUpload, T4: SetUpload> Collection
-    for (Set<T1>, Set<T2>, Set<T3>, Set<T4>)
-{
-    fn get(&self) -> Vec<Arc<dyn DescriptorSet + Send + Sync>> {
-        vec![self.0.get(), self.1.get(), self.2.get(), self.3.get()]
-    }
-}
-
 /*
 CollectionData
  */
@@ -95,10 +127,13 @@ pub trait CollectionData {
 
     fn create_sets(
         &self,
-        device: Arc<Device>,
+        resources: &Resources,
         pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
         set_idx_offset: usize,
     ) -> Self::Sets;
+
+    // the descriptors of every set, in order
+    fn descriptors(&self) -> Vec<Vec<ProvidedDescriptor>>;
 }
 
 impl CollectionData for () {
@@ -106,129 +141,49 @@ impl CollectionData for () {
 
     fn create_sets(
         &self,
-        _device: Arc<Device>,
+        _resources: &Resources,
         _pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
         _set_idx_offset: usize,
     ) -> Self::Sets {
     }
-}
-
-impl<T1: SetUpload> CollectionData for (T1,) {
-    type Sets = (Set<T1>,);
 
-    fn create_sets(
-        &self,
-        device: Arc<Device>,
-        pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
-        set_idx_offset: usize,
-    ) -> Self::Sets {
-        let set1 = Set::new(self.0.clone(), device, pipeline, set_idx_offset);
-
-        (set1,)
-    }
-}
-
-impl<T1: SetUpload, T2: SetUpload> CollectionData for (T1, T2) {
-    type Sets = (Set<T1>, Set<T2>);
-
-    fn create_sets(
-        &self,
-        device: Arc<Device>,
-        pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
-        set_idx_offset: usize,
-    ) -> Self::Sets {
-        let set1 = Set::new(
-            self.0.clone(),
-            device.clone(),
-            pipeline.clone(),
-            set_idx_offset,
-        );
-        let set2 = Set::new(
-            self.1.clone(),
-            device.clone(),
-            pipeline.clone(),
-            set_idx_offset + 1,
-        );
-
-        (set1, set2)
+    fn descriptors(&self) -> Vec<Vec<ProvidedDescriptor>> {
+        vec![]
     }
 }
 
-impl<T1: SetUpload, T2: SetUpload, T3: SetUpload> CollectionData for (T1, T2, T3) {
-    type Sets = (Set<T1>, Set<T2>, Set<T3>);
-
-    fn create_sets(
-        &self,
-        device: Arc<Device>,
-        pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
-        set_idx_offset: usize,
-    ) -> Self::Sets {
-        let set1 = Set::new(
-            self.0.clone(),
-            device.clone(),
-            pipeline.clone(),
-            set_idx_offset,
-        );
-        let set2 = Set::new(
-            self.1.clone(),
-            device.clone(),
-            pipeline.clone(),
-            set_idx_offset + 1,
-        );
-        let set3 = Set::new(
-            self.2.clone(),
-            device.clone(),
-            pipeline.clone(),
-            set_idx_offset + 2,
-        );
+/*
+SetLayers
+ */
 
-        (set1, set2, set3)
-    }
+// the set index each layer starts at. a layer with more than one set takes up
+// the indices after it too, so leave space for them. shaders that use a
+// layer's set have to declare all of it: putting the globals in an #include
+// file is the easiest way.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SetLayers {
+    pub globals: usize,
+    pub pass_images: usize,
+    pub material: usize,
+    pub object: usize,
 }
 
-impl<T1: SetUpload, T2: SetUpload, T3: SetUpload, T4: SetUpload> CollectionData
-    for (T1, T2, T3, T4)
-{
-    type Sets = (Set<T1>, Set<T2>, Set<T3>, Set<T4>);
-
-    fn create_sets(
-        &self,
-        device: Arc<Device>,
-        pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
-        set_idx_offset: usize,
-    ) -> Self::Sets {
-        let set1 = Set::new(
-            self.0.clone(),
-            device.clone(),
-            pipeline.clone(),
-            set_idx_offset,
-        );
-        let set2 = Set::new(
-            self.1.clone(),
-            device.clone(),
-            pipeline.clone(),
-            set_idx_offset + 1,
-        );
-        let set3 = Set::new(
-            self.2.clone(),
-            device.clone(),
-            pipeline.clone(),
-            set_idx_offset + 2,
-        );
-        let set4 = Set::new(
-            self.3.clone(),
-            device.clone(),
-            pipeline.clone(),
-            set_idx_offset + 3,
-        );
-
-        (set1, set2, set3, set4)
+impl Default for SetLayers {
+    fn default() -> Self {
+        Self {
+            globals: 0,
+            pass_images: 1,
+            material: 2,
+            object: 3,
+        }
     }
 }
 
 /*
 Set
  */
+// clones share the uploaded descriptor set until one of them is uploaded again
+#[derive(Clone)]
 pub struct Set<T: SetUpload> {
     pub data: T,
     cached: Arc<dyn DescriptorSet + Send + Sync>,
@@ -239,12 +194,12 @@ pub struct Set<T: SetUpload> {
 impl<T: SetUpload> Set<T> {
     pub fn new(
         data: T,
-        device: Arc<Device>,
+        resources: &Resources,
         pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
         set_idx: usize,
     ) -> Self {
         // creates a new set and immediately uploads the data to the GPU
-        let gpu_data = data.upload(device, pipeline.clone(), set_idx);
+        let gpu_data = data.upload(resources, pipeline.clone(), set_idx);
         Self {
             data,
             cached: gpu_data,
@@ -257,344 +212,609 @@ impl<T: SetUpload> Set<T> {
         self.cached.clone()
     }
 
-    pub fn upload(&mut self, device: Arc<Device>) {
-        self.cached = self.data.upload(device, self.pipeline.clone(), self.set_idx);
+    pub fn upload(&mut self, resources: &Resources) {
+        self.cached = self
+            .data
+            .upload(resources, self.pipeline.clone(), self.set_idx);
     }
 }
 
 pub trait SetUpload: Clone {
     fn upload(
         &self,
-        device: Arc<Device>,
+        resources: &Resources,
         pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
         set_idx: usize,
     ) -> Arc<dyn DescriptorSet + Send + Sync>;
+
+    // what gets bound to each binding of the set, in order. used to check the
+    // set against the shader before uploading anything.
+    fn descriptors(&self) -> Vec<ProvidedDescriptor>;
 }
 
-// length 1
-impl<T: Data> SetUpload for (T,) {
-    fn upload(
+pub type Image = Arc<dyn ImageViewAccess + Send + Sync>;
+
+pub trait Data: Send + Sync + Clone + 'static {}
+
+/*
+Elements
+ */
+
+type Layout = Arc<dyn GraphicsPipelineAbstract + Send + Sync>;
+
+// anything that can be part of a set
+pub trait SetElement: Clone {
+    fn descriptor(&self) -> ProvidedDescriptor;
+}
+
+// adds the element to a descriptor set builder that already has R in it. the
+// builder's type changes with every element added, which is why this is
+// generic over R instead of taking a builder of any type.
+pub trait AddToSet<R>: SetElement {
+    type Resources;
+
+    fn add_to_set(
         &self,
-        device: Arc<Device>,
-        pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
-        set_idx: usize,
-    ) -> Arc<dyn DescriptorSet + Send + Sync> {
-        let buffer = upload_data(device.clone(), self.0.clone());
-
-        Arc::new(
-            PersistentDescriptorSet::start(pipeline, set_idx)
-                .add_buffer(buffer)
-                .expect(&format!("Panic adding 1st buffer at set idx {}", set_idx))
-                .build()
-                .expect(&format!("Panic finalizing set at set idx {}", set_idx)),
-        )
+        builder: PersistentDescriptorSetBuilder<Layout, R>,
+        resources: &Resources,
+    ) -> Result<PersistentDescriptorSetBuilder<Layout, Self::Resources>, PersistentDescriptorSetError>;
+}
+
+impl<T: Data> SetElement for T {
+    fn descriptor(&self) -> ProvidedDescriptor {
+        ProvidedDescriptor::buffer::<T>()
     }
 }
 
-impl SetUpload for (Image,) {
-    fn upload(
+// structs are uploaded to the system's UniformPool, since sets are usually
+// re-uploaded every frame
+impl<T: Data, R> AddToSet<R> for T {
+    type Resources = (R, PersistentDescriptorSetBuf<PooledBuffer<T>>);
+
+    fn add_to_set(
         &self,
-        device: Arc<Device>,
-        pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
-        set_idx: usize,
-    ) -> Arc<dyn DescriptorSet + Send + Sync> {
-        let sampler = default_sampler(device.clone());
-        Arc::new(
-            PersistentDescriptorSet::start(pipeline, set_idx)
-                .add_sampled_image(self.0.clone(), sampler)
-                .expect(&format!("Panic adding 1st image at set idx {}", set_idx))
-                .build()
-                .expect(&format!("Panic finalizing set at set idx {}", set_idx)),
-        )
+        builder: PersistentDescriptorSetBuilder<Layout, R>,
+        resources: &Resources,
+    ) -> Result<PersistentDescriptorSetBuilder<Layout, Self::Resources>, PersistentDescriptorSetError>
+    {
+        builder.add_buffer(resources.uniform_pool.upload(self.clone()))
     }
 }
 
-// length 2
-impl<T1: Data, T2: Data> SetUpload for (T1, T2) {
-    fn upload(
-        &self,
-        device: Arc<Device>,
-        pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
-        set_idx: usize,
-    ) -> Arc<dyn DescriptorSet + Send + Sync> {
-        let buffer1 = upload_data(device.clone(), self.0.clone());
-        let buffer2 = upload_data(device.clone(), self.1.clone());
-
-        Arc::new(
-            PersistentDescriptorSet::start(pipeline, set_idx)
-                .add_buffer(buffer1)
-                .expect(&format!("Panic adding 1st buffer at set idx {}", set_idx))
-                .add_buffer(buffer2)
-                .expect(&format!("Panic adding 2nd buffer at set idx {}", set_idx))
-                .build()
-                .expect(&format!("Panic finalizing set at set idx {}", set_idx)),
-        )
+impl SetElement for Image {
+    fn descriptor(&self) -> ProvidedDescriptor {
+        ProvidedDescriptor::Image { compare: false }
     }
 }
 
-impl<T: Data> SetUpload for (Image, T) {
-    fn upload(
+impl<R> AddToSet<R> for Image {
+    type Resources = (
+        (R, PersistentDescriptorSetImg<Image>),
+        PersistentDescriptorSetSampler,
+    );
+
+    fn add_to_set(
         &self,
-        device: Arc<Device>,
-        pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
-        set_idx: usize,
-    ) -> Arc<dyn DescriptorSet + Send + Sync> {
-        let sampler = default_sampler(device.clone());
-        let buffer2 = upload_data(device.clone(), self.1.clone());
-
-        Arc::new(
-            PersistentDescriptorSet::start(pipeline, set_idx)
-                .add_sampled_image(self.0.clone(), sampler)
-                .expect(&format!("Panic adding 1st image at set idx {}", set_idx))
-                .add_buffer(buffer2)
-                .expect(&format!("Panic adding 2nd buffer at set idx {}", set_idx))
-                .build()
-                .expect(&format!("Panic finalizing set at set idx {}", set_idx)),
+        builder: PersistentDescriptorSetBuilder<Layout, R>,
+        resources: &Resources,
+    ) -> Result<PersistentDescriptorSetBuilder<Layout, Self::Resources>, PersistentDescriptorSetError>
+    {
+        builder.add_sampled_image(
+            self.clone(),
+            resources.samplers.get(&SamplerDesc::default()),
         )
     }
 }
 
-impl<T: Data> SetUpload for (T, Image) {
-    fn upload(
-        &self,
-        device: Arc<Device>,
-        pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
-        set_idx: usize,
-    ) -> Arc<dyn DescriptorSet + Send + Sync> {
-        let sampler = default_sampler(device.clone());
-        let buffer1 = upload_data(device.clone(), self.0.clone());
-
-        Arc::new(
-            PersistentDescriptorSet::start(pipeline, set_idx)
-                .add_sampled_image(self.1.clone(), sampler)
-                .expect(&format!("Panic adding 1st image at set idx {}", set_idx))
-                .add_buffer(buffer1)
-                .expect(&format!("Panic adding 2nd buffer at set idx {}", set_idx))
-                .build()
-                .expect(&format!("Panic finalizing set at set idx {}", set_idx)),
-        )
+// an image paired with its own sampler instead of the default one
+#[derive(Clone)]
+pub struct Sampled(pub Image, pub SamplerDesc);
+
+impl SetElement for Sampled {
+    fn descriptor(&self) -> ProvidedDescriptor {
+        ProvidedDescriptor::Image {
+            compare: self.1.compare.is_some(),
+        }
     }
 }
 
-impl SetUpload for (Image, Image) {
-    fn upload(
+impl<R> AddToSet<R> for Sampled {
+    type Resources = (
+        (R, PersistentDescriptorSetImg<Image>),
+        PersistentDescriptorSetSampler,
+    );
+
+    fn add_to_set(
         &self,
-        device: Arc<Device>,
-        pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
-        set_idx: usize,
-    ) -> Arc<dyn DescriptorSet + Send + Sync> {
-        let sampler = default_sampler(device.clone());
-
-        Arc::new(
-            PersistentDescriptorSet::start(pipeline, set_idx)
-                .add_sampled_image(self.0.clone(), sampler.clone())
-                .expect(&format!("Panic adding 1st image at set idx {}", set_idx))
-                .add_sampled_image(self.1.clone(), sampler)
-                .expect(&format!("Panic adding 2nd image at set idx {}", set_idx))
-                .build()
-                .expect(&format!("Panic finalizing set at set idx {}", set_idx)),
-        )
+        builder: PersistentDescriptorSetBuilder<Layout, R>,
+        resources: &Resources,
+    ) -> Result<PersistentDescriptorSetBuilder<Layout, Self::Resources>, PersistentDescriptorSetError>
+    {
+        builder.add_sampled_image(self.0.clone(), resources.samplers.get(&self.1))
     }
 }
 
-// length 3
-impl<T1: Data, T2: Data, T3: Data> SetUpload for (T1, T2, T3) {
-    fn upload(
-        &self,
-        device: Arc<Device>,
-        pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
-        set_idx: usize,
-    ) -> Arc<dyn DescriptorSet + Send + Sync> {
-        let buffer1 = upload_data(device.clone(), self.0.clone());
-        let buffer2 = upload_data(device.clone(), self.1.clone());
-        let buffer3 = upload_data(device.clone(), self.2.clone());
-
-        Arc::new(
-            PersistentDescriptorSet::start(pipeline, set_idx)
-                .add_buffer(buffer1)
-                .expect(&format!("Panic adding 1st buffer at set idx {}", set_idx))
-                .add_buffer(buffer2)
-                .expect(&format!("Panic adding 2nd buffer at set idx {}", set_idx))
-                .add_buffer(buffer3)
-                .expect(&format!("Panic adding 3rd buffer at set idx {}", set_idx))
-                .build()
-                .expect(&format!("Panic finalizing set at set idx {}", set_idx)),
-        )
+// whether the shader is allowed to write to a StorageSlice
+pub trait Access: Send + Sync + 'static {
+    const WRITABLE: bool;
+}
+
+pub struct ReadOnly;
+pub struct ReadWrite;
+
+impl Access for ReadOnly {
+    const WRITABLE: bool = false;
+}
+
+impl Access for ReadWrite {
+    const WRITABLE: bool = true;
+}
+
+// a runtime-sized array of T, bound as a storage buffer. the buffer is shared
+// between clones, so a compute shader can write to it through one set and a
+// graphics shader read from it through another.
+pub struct StorageSlice<T: Send + Sync + 'static, A: Access = ReadOnly> {
+    pub buffer: Arc<dyn TypedBufferAccess<Content = [T]> + Send + Sync>,
+    access: PhantomData<A>,
+}
+
+impl<T: Content + Clone + Send + Sync + 'static> StorageSlice<T, ReadOnly> {
+    pub fn new(device: Arc<Device>, data: &[T]) -> Self {
+        Self::from_buffer(upload_slice(device, data))
     }
 }
 
-impl<T1: Data, T2: Data> SetUpload for (Image, T1, T2) {
-    fn upload(
-        &self,
-        device: Arc<Device>,
-        pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
-        set_idx: usize,
-    ) -> Arc<dyn DescriptorSet + Send + Sync> {
-        let sampler = default_sampler(device.clone());
-        let buffer2 = upload_data(device.clone(), self.1.clone());
-        let buffer3 = upload_data(device.clone(), self.2.clone());
-
-        Arc::new(
-            PersistentDescriptorSet::start(pipeline, set_idx)
-                .add_sampled_image(self.0.clone(), sampler)
-                .expect(&format!("Panic adding 1st image at set idx {}", set_idx))
-                .add_buffer(buffer2)
-                .expect(&format!("Panic adding 2nd buffer at set idx {}", set_idx))
-                .add_buffer(buffer3)
-                .expect(&format!("Panic adding 3rd buffer at set idx {}", set_idx))
-                .build()
-                .expect(&format!("Panic finalizing set at set idx {}", set_idx)),
-        )
+impl<T: Content + Clone + Send + Sync + 'static> StorageSlice<T, ReadWrite> {
+    pub fn read_write(device: Arc<Device>, data: &[T]) -> Self {
+        Self::from_buffer(upload_slice(device, data))
     }
 }
 
-impl<T1: Data, T2: Data> SetUpload for (T1, Image, T2) {
-    fn upload(
-        &self,
-        device: Arc<Device>,
-        pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
-        set_idx: usize,
-    ) -> Arc<dyn DescriptorSet + Send + Sync> {
-        let sampler = default_sampler(device.clone());
-        let buffer1 = upload_data(device.clone(), self.0.clone());
-        let buffer3 = upload_data(device.clone(), self.2.clone());
-
-        Arc::new(
-            PersistentDescriptorSet::start(pipeline, set_idx)
-                .add_buffer(buffer1)
-                .expect(&format!("Panic adding 1st buffer at set idx {}", set_idx))
-                .add_sampled_image(self.1.clone(), sampler)
-                .expect(&format!("Panic adding 2nd image at set idx {}", set_idx))
-                .add_buffer(buffer3)
-                .expect(&format!("Panic adding 3rd buffer at set idx {}", set_idx))
-                .build()
-                .expect(&format!("Panic finalizing set at set idx {}", set_idx)),
-        )
+impl<T: Send + Sync + 'static, A: Access> StorageSlice<T, A> {
+    // for buffers created some other way, like device-local ones that only
+    // the GPU ever touches
+    pub fn from_buffer(buffer: Arc<dyn TypedBufferAccess<Content = [T]> + Send + Sync>) -> Self {
+        Self {
+            buffer,
+            access: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 }
 
-impl<T1: Data, T2: Data> SetUpload for (T1, T2, Image) {
-    fn upload(
-        &self,
-        device: Arc<Device>,
-        pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
-        set_idx: usize,
-    ) -> Arc<dyn DescriptorSet + Send + Sync> {
-        let sampler = default_sampler(device.clone());
-        let buffer1 = upload_data(device.clone(), self.0.clone());
-        let buffer2 = upload_data(device.clone(), self.1.clone());
-
-        Arc::new(
-            PersistentDescriptorSet::start(pipeline, set_idx)
-                .add_buffer(buffer1)
-                .expect(&format!("Panic adding 1st buffer at set idx {}", set_idx))
-                .add_buffer(buffer2)
-                .expect(&format!("Panic adding 2nd buffer at set idx {}", set_idx))
-                .add_sampled_image(self.2.clone(), sampler)
-                .expect(&format!("Panic adding 3rd image at set idx {}", set_idx))
-                .build()
-                .expect(&format!("Panic finalizing set at set idx {}", set_idx)),
-        )
+fn upload_slice<T: Content + Clone + Send + Sync + 'static>(
+    device: Arc<Device>,
+    data: &[T],
+) -> Arc<CpuAccessibleBuffer<[T]>> {
+    CpuAccessibleBuffer::from_iter(device, BufferUsage::all(), data.iter().cloned()).unwrap()
+}
+
+// derive(Clone) would require T: Clone, which isn't needed to clone an Arc
+impl<T: Send + Sync + 'static, A: Access> Clone for StorageSlice<T, A> {
+    fn clone(&self) -> Self {
+        Self::from_buffer(self.buffer.clone())
     }
 }
 
-impl<T: Data> SetUpload for (T, Image, Image) {
-    fn upload(
+impl<T: Send + Sync + 'static, A: Access> SetElement for StorageSlice<T, A> {
+    fn descriptor(&self) -> ProvidedDescriptor {
+        ProvidedDescriptor::StorageBuffer {
+            type_name: std::any::type_name::<T>(),
+            writable: A::WRITABLE,
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static, A: Access, R> AddToSet<R> for StorageSlice<T, A> {
+    type Resources = (
+        R,
+        PersistentDescriptorSetBuf<Arc<dyn TypedBufferAccess<Content = [T]> + Send + Sync>>,
+    );
+
+    fn add_to_set(
         &self,
-        device: Arc<Device>,
-        pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
-        set_idx: usize,
-    ) -> Arc<dyn DescriptorSet + Send + Sync> {
-        let sampler = default_sampler(device.clone());
-        let buffer1 = upload_data(device.clone(), self.0.clone());
-
-        Arc::new(
-            PersistentDescriptorSet::start(pipeline, set_idx)
-                .add_buffer(buffer1)
-                .expect(&format!("Panic adding 1st buffer at set idx {}", set_idx))
-                .add_sampled_image(self.1.clone(), sampler.clone())
-                .expect(&format!("Panic adding 2nd image at set idx {}", set_idx))
-                .add_sampled_image(self.2.clone(), sampler)
-                .expect(&format!("Panic adding 3rd image at set idx {}", set_idx))
-                .build()
-                .expect(&format!("Panic finalizing set at set idx {}", set_idx)),
+        builder: PersistentDescriptorSetBuilder<Layout, R>,
+        _resources: &Resources,
+    ) -> Result<PersistentDescriptorSetBuilder<Layout, Self::Resources>, PersistentDescriptorSetError>
+    {
+        builder.add_buffer(self.buffer.clone())
+    }
+}
+
+// an image bound without a sampler, which the shader can read and write with
+// imageLoad and imageStore
+#[derive(Clone)]
+pub struct StorageImage(pub Image);
+
+impl StorageImage {
+    // a new 2D image usable as a storage image. not every format supports
+    // storage, R8G8B8A8Unorm and R32G32B32A32Sfloat are safe bets.
+    pub fn new(device: Arc<Device>, width: u32, height: u32, format: Format) -> Self {
+        let image = vulkano::image::StorageImage::new(
+            device.clone(),
+            Dimensions::Dim2d { width, height },
+            format,
+            device.active_queue_families(),
         )
+        .unwrap();
+
+        StorageImage(image)
     }
 }
 
-impl<T: Data> SetUpload for (Image, T, Image) {
-    fn upload(
+impl SetElement for StorageImage {
+    fn descriptor(&self) -> ProvidedDescriptor {
+        ProvidedDescriptor::StorageImage
+    }
+}
+
+impl<R> AddToSet<R> for StorageImage {
+    type Resources = (R, PersistentDescriptorSetImg<Image>);
+
+    fn add_to_set(
         &self,
-        device: Arc<Device>,
-        pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
-        set_idx: usize,
-    ) -> Arc<dyn DescriptorSet + Send + Sync> {
-        let sampler = default_sampler(device.clone());
-        let buffer2 = upload_data(device.clone(), self.1.clone());
-
-        Arc::new(
-            PersistentDescriptorSet::start(pipeline, set_idx)
-                .add_sampled_image(self.0.clone(), sampler.clone())
-                .expect(&format!("Panic adding 1st image at set idx {}", set_idx))
-                .add_buffer(buffer2)
-                .expect(&format!("Panic adding 2nd buffer at set idx {}", set_idx))
-                .add_sampled_image(self.2.clone(), sampler)
-                .expect(&format!("Panic adding 3rd image at set idx {}", set_idx))
-                .build()
-                .expect(&format!("Panic finalizing set at set idx {}", set_idx)),
-        )
+        builder: PersistentDescriptorSetBuilder<Layout, R>,
+        _resources: &Resources,
+    ) -> Result<PersistentDescriptorSetBuilder<Layout, Self::Resources>, PersistentDescriptorSetError>
+    {
+        builder.add_image(self.0.clone())
+    }
+}
+
+/*
+TextureArray
+ */
+
+// every texture of a scene in a single binding, so objects can share one set
+// and pick their texture by index (usually from their push constants, see
+// obj-viewer):
+//
+// layout(set = 2, binding = 0) uniform sampler2D textures[64];
+// ... texture(textures[push.texture_idx], uv) ...
+//
+// if the shader's array is longer than the list of images, the remaining
+// slots are filled with the first image. a define in the pipeline's
+// ShaderOptions is an easy way to make it exactly as long.
+//
+// indexing with anything but a constant needs the
+// shaderSampledImageArrayDynamicIndexing feature, which the device is created
+// with if it's supported. in practice every desktop GPU supports it.
+//
+// known limitations, both because of vulkano 0.14:
+// - a TextureArray is a whole set by itself and the array has to be the set's
+//   only binding, anything else in the set fails validation. it can't be an
+//   element of a tuple like Image is, because the PersistentDescriptorSet
+//   builder's type changes with every image added, so the number of images
+//   would have to be known at compile time. instead the descriptors are
+//   written by hand.
+// - the array's size has to be fixed in the shader, and the index has to be
+//   the same for a whole draw call. runtime-sized arrays (sampler2D
+//   textures[];) and indexing that differs between invocations need
+//   VK_EXT_descriptor_indexing, which vulkano 0.14 doesn't know about.
+#[derive(Clone)]
+pub struct TextureArray {
+    pub images: Vec<Image>,
+    // used for every image in the array
+    pub sampler: SamplerDesc,
+}
+
+impl TextureArray {
+    pub fn new(images: Vec<Image>) -> Self {
+        Self {
+            images,
+            sampler: SamplerDesc::default(),
+        }
     }
 }
 
-impl<T: Data> SetUpload for (Image, Image, T) {
+impl SetUpload for TextureArray {
     fn upload(
         &self,
-        device: Arc<Device>,
+        resources: &Resources,
         pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
         set_idx: usize,
     ) -> Arc<dyn DescriptorSet + Send + Sync> {
-        let sampler = default_sampler(device.clone());
-        let buffer3 = upload_data(device.clone(), self.2.clone());
-
-        Arc::new(
-            PersistentDescriptorSet::start(pipeline, set_idx)
-                .add_sampled_image(self.0.clone(), sampler.clone())
-                .expect(&format!("Panic adding 1st image at set idx {}", set_idx))
-                .add_sampled_image(self.1.clone(), sampler)
-                .expect(&format!("Panic adding 2nd image at set idx {}", set_idx))
-                .add_buffer(buffer3)
-                .expect(&format!("Panic adding 1st buffer at set idx {}", set_idx))
-                .build()
-                .expect(&format!("Panic finalizing set at set idx {}", set_idx)),
-        )
+        Arc::new(ImageArraySet::new(
+            resources.device().clone(),
+            pipeline,
+            set_idx,
+            &self.images,
+            resources.samplers.get(&self.sampler),
+        ))
+    }
+
+    fn descriptors(&self) -> Vec<ProvidedDescriptor> {
+        vec![ProvidedDescriptor::ImageArray {
+            compare: self.sampler.compare.is_some(),
+        }]
     }
 }
 
-impl SetUpload for (Image, Image, Image) {
-    fn upload(
-        &self,
+// a descriptor set with an array of combined image samplers at binding 0. it
+// keeps the images and sampler alive for as long as the set is in use, which
+// PersistentDescriptorSet would otherwise do.
+struct ImageArraySet {
+    alloc: StdDescriptorPoolAlloc,
+    device: Arc<Device>,
+    pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
+    set_idx: usize,
+    // one per element of the array
+    images: Vec<Image>,
+    _sampler: Arc<Sampler>,
+}
+
+impl ImageArraySet {
+    fn new(
         device: Arc<Device>,
         pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
         set_idx: usize,
-    ) -> Arc<dyn DescriptorSet + Send + Sync> {
-        let sampler = default_sampler(device.clone());
-
-        Arc::new(
-            PersistentDescriptorSet::start(pipeline, set_idx)
-                .add_sampled_image(self.0.clone(), sampler.clone())
-                .expect(&format!("Panic adding 1st image at set idx {}", set_idx))
-                .add_sampled_image(self.1.clone(), sampler.clone())
-                .expect(&format!("Panic adding 2nd image at set idx {}", set_idx))
-                .add_sampled_image(self.2.clone(), sampler)
-                .expect(&format!("Panic adding 3rd image at set idx {}", set_idx))
-                .build()
-                .expect(&format!("Panic finalizing set at set idx {}", set_idx)),
-        )
+        images: &[Image],
+        sampler: Arc<Sampler>,
+    ) -> Self {
+        let array_count = pipeline
+            .descriptor(set_idx, 0)
+            .map(|desc| desc.array_count as usize)
+            .unwrap_or_else(|| panic!("set {} has no binding 0 for the texture array", set_idx));
+
+        assert!(!images.is_empty(), "texture array is empty");
+        assert!(
+            images.len() <= array_count,
+            "{} textures given, but the shader's array at set {} only has room for {}",
+            images.len(),
+            set_idx,
+            array_count
+        );
+
+        let mut images = images.to_vec();
+        images.resize(array_count, images[0].clone());
+
+        let layout = pipeline
+            .descriptor_set_layout(set_idx)
+            .unwrap_or_else(|| panic!("pipeline has no set {}", set_idx))
+            .clone();
+        let mut pool = Device::standard_descriptor_pool(&device);
+        let mut alloc = pool.alloc(&layout).unwrap();
+
+        let writes = images.iter().enumerate().map(|(idx, image)| {
+            DescriptorWrite::combined_image_sampler(0, idx as u32, &sampler, image)
+        });
+        unsafe {
+            alloc.inner_mut().write(&device, writes);
+        }
+
+        Self {
+            alloc,
+            device,
+            pipeline,
+            set_idx,
+            images,
+            _sampler: sampler,
+        }
     }
 }
 
-// length 4 will be FUN!
+unsafe impl DescriptorSet for ImageArraySet {
+    fn inner(&self) -> &UnsafeDescriptorSet {
+        self.alloc.inner()
+    }
 
-pub type Image = Arc<dyn ImageViewAccess + Send + Sync>;
+    fn num_buffers(&self) -> usize {
+        0
+    }
 
-pub trait Data: Send + Sync + Clone + 'static {}
+    fn buffer(&self, _index: usize) -> Option<(&dyn BufferAccess, u32)> {
+        None
+    }
+
+    fn num_images(&self) -> usize {
+        self.images.len()
+    }
+
+    // the second field is the binding, which is always 0
+    fn image(&self, index: usize) -> Option<(&dyn ImageViewAccess, u32)> {
+        self.images
+            .get(index)
+            .map(|image| (&**image as &dyn ImageViewAccess, 0))
+    }
+}
+
+unsafe impl DescriptorSetDesc for ImageArraySet {
+    fn num_bindings(&self) -> usize {
+        self.pipeline.num_bindings_in_set(self.set_idx).unwrap_or(0)
+    }
+
+    fn descriptor(&self, binding: usize) -> Option<DescriptorDesc> {
+        self.pipeline.descriptor(self.set_idx, binding)
+    }
+}
+
+unsafe impl DeviceOwned for ImageArraySet {
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+// a single element of a set, as seen by the shader
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProvidedDescriptor {
+    // a struct uploaded as a buffer
+    Buffer {
+        type_name: &'static str,
+        size: usize,
+    },
+    // an image with a sampler, either the default or a Sampled's. compare is
+    // true if the sampler has a compare op, which only sampler2DShadow and
+    // the like can be used with, and the other way around.
+    Image {
+        compare: bool,
+    },
+    // a StorageSlice, type_name is the type of a single element
+    StorageBuffer {
+        type_name: &'static str,
+        writable: bool,
+    },
+    StorageImage,
+    // a TextureArray, an array of images sharing one sampler
+    ImageArray {
+        compare: bool,
+    },
+}
+
+impl ProvidedDescriptor {
+    pub fn buffer<T: Data>() -> Self {
+        ProvidedDescriptor::Buffer {
+            type_name: std::any::type_name::<T>(),
+            size: std::mem::size_of::<T>(),
+        }
+    }
+}
+
+impl fmt::Display for ProvidedDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProvidedDescriptor::Buffer { type_name, size } => {
+                write!(f, "buffer {} ({} bytes)", type_name, size)
+            }
+            ProvidedDescriptor::Image { compare: false } => write!(f, "image"),
+            ProvidedDescriptor::Image { compare: true } => {
+                write!(f, "image with a comparison sampler")
+            }
+            ProvidedDescriptor::StorageBuffer {
+                type_name,
+                writable: false,
+            } => write!(f, "read-only storage buffer of [{}]", type_name),
+            ProvidedDescriptor::StorageBuffer {
+                type_name,
+                writable: true,
+            } => write!(f, "read-write storage buffer of [{}]", type_name),
+            ProvidedDescriptor::StorageImage => write!(f, "storage image"),
+            ProvidedDescriptor::ImageArray { compare: false } => write!(f, "array of images"),
+            ProvidedDescriptor::ImageArray { compare: true } => {
+                write!(f, "array of images with a comparison sampler")
+            }
+        }
+    }
+}
+
+/*
+The impls for tuples. Every element knows how to add itself to the builder
+through AddToSet, so there's one SetUpload impl per tuple length. The only
+tricky part is the where clause, because the builder's resources type grows
+with every element: T2 is added to whatever T1 left behind, and so on.
+ */
+
+// takes (idx T) pairs and implements Collection and CollectionData for tuples
+// of that length
+macro_rules! impl_collection {
+    ($(($idx:tt $T:ident))+) => {
+        impl<$($T: SetUpload),+> Collection for ($(Set<$T>,)+) {
+            fn get(&self) -> Vec<Arc<dyn DescriptorSet + Send + Sync>> {
+                vec![$(self.$idx.get()),+]
+            }
+        }
+
+        impl<$($T: SetUpload),+> CollectionData for ($($T,)+) {
+            type Sets = ($(Set<$T>,)+);
+
+            fn create_sets(
+                &self,
+                resources: &Resources,
+                pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
+                set_idx_offset: usize,
+            ) -> Self::Sets {
+                ($(
+                    Set::new(
+                        self.$idx.clone(),
+                        resources,
+                        pipeline.clone(),
+                        set_idx_offset + $idx,
+                    ),
+                )+)
+            }
+
+            fn descriptors(&self) -> Vec<Vec<ProvidedDescriptor>> {
+                vec![$(self.$idx.descriptors()),+]
+            }
+        }
+    };
+}
+
+impl_collection!((0 T1));
+impl_collection!((0 T1) (1 T2));
+impl_collection!((0 T1) (1 T2) (2 T3));
+impl_collection!((0 T1) (1 T2) (2 T3) (3 T4));
+impl_collection!((0 T1) (1 T2) (2 T3) (3 T4) (4 T5));
+impl_collection!((0 T1) (1 T2) (2 T3) (3 T4) (4 T5) (5 T6));
+impl_collection!((0 T1) (1 T2) (2 T3) (3 T4) (4 T5) (5 T6) (6 T7));
+impl_collection!((0 T1) (1 T2) (2 T3) (3 T4) (4 T5) (5 T6) (6 T7) (7 T8));
+
+// takes (idx T) pairs and implements SetUpload for tuples of that length.
+// the @bounds arms build up the where clause one element at a time, keeping
+// track of the builder's resources type so far.
+macro_rules! impl_set_upload {
+    ($(($idx:tt $T:ident))+) => {
+        impl_set_upload!(@bounds [] [()] [$(($idx $T))+] [$(($idx $T))+]);
+    };
+    (@bounds [$($bounds:tt)*] [$resources:ty] [($idx:tt $T:ident) $($rest:tt)*] [$($all:tt)+]) => {
+        impl_set_upload!(
+            @bounds
+            [$($bounds)* $T: AddToSet<$resources>,]
+            [<$T as AddToSet<$resources>>::Resources]
+            [$($rest)*]
+            [$($all)+]
+        );
+    };
+    (@bounds [$($bounds:tt)*] [$resources:ty] [] [$(($idx:tt $T:ident))+]) => {
+        impl<$($T),+> SetUpload for ($($T,)+)
+        where
+            $($bounds)*
+            PersistentDescriptorSet<Layout, $resources>: DescriptorSet + Send + Sync + 'static,
+        {
+            fn upload(
+                &self,
+                resources: &Resources,
+                pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
+                set_idx: usize,
+            ) -> Arc<dyn DescriptorSet + Send + Sync> {
+                let builder = PersistentDescriptorSet::start(pipeline, set_idx);
+                $(
+                    let builder = self
+                        .$idx
+                        .add_to_set(builder, resources)
+                        .expect(&format!(
+                            "Panic adding {} at binding {} of set idx {}",
+                            self.$idx.descriptor(),
+                            $idx,
+                            set_idx
+                        ));
+                )+
+
+                Arc::new(
+                    builder
+                        .build()
+                        .expect(&format!("Panic finalizing set at set idx {}", set_idx)),
+                )
+            }
+
+            fn descriptors(&self) -> Vec<ProvidedDescriptor> {
+                vec![$(self.$idx.descriptor()),+]
+            }
+        }
+    };
+}
+
+impl_set_upload!((0 T1));
+impl_set_upload!((0 T1) (1 T2));
+impl_set_upload!((0 T1) (1 T2) (2 T3));
+impl_set_upload!((0 T1) (1 T2) (2 T3) (3 T4));
+impl_set_upload!((0 T1) (1 T2) (2 T3) (3 T4) (4 T5));
+impl_set_upload!((0 T1) (1 T2) (2 T3) (3 T4) (4 T5) (5 T6));
+impl_set_upload!((0 T1) (1 T2) (2 T3) (3 T4) (4 T5) (5 T6) (6 T7));
+impl_set_upload!((0 T1) (1 T2) (2 T3) (3 T4) (4 T5) (5 T6) (6 T7) (7 T8));
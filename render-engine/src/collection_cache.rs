@@ -1,29 +1,59 @@
 use vulkano::buffer::BufferAccess;
 use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::descriptor::pipeline_layout::PipelineLayoutDesc;
 use vulkano::descriptor::DescriptorSet;
-use vulkano::device::Device;
 use vulkano::image::ImageViewAccess;
 use vulkano::pipeline::GraphicsPipelineAbstract;
-use vulkano::sampler::{Filter, MipmapMode, Sampler, SamplerAddressMode};
+use vulkano::sampler::Sampler;
 
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use crate::collection::{Collection as _, CollectionData, SetLayers};
 use crate::input::get_elapsed;
 use crate::pipeline_cache::PipelineSpec;
+use crate::resources::Resources;
+use crate::sampler::SamplerDesc;
 use crate::system::Pass;
 
 pub struct CollectionCache {
-    c_collections: Vec<CachedCollection>,
-    sampler: Arc<Sampler>,
+    c_collections: Vec<CachedPassSets>,
+    c_globals: Vec<CachedCollection>,
+    globals: Option<Globals>,
+    resources: Arc<Resources>,
+    // samplers for pass images, by tag. images without one use
+    // SamplerDesc::clamped.
+    samplers: HashMap<String, SamplerDesc>,
     stats: CacheStats,
 }
 
 struct CachedCollection {
     spec: PipelineSpec,
-    collection: Collection,
+    collection: Vec<Option<Arc<dyn DescriptorSet + Send + Sync>>>,
 }
 
+// the pass's images set points at specific images, so it can only be reused
+// while the pass reads the same ones. images are compared by address, which
+// can't be reused by a new image while the cached set keeps the old one alive.
+struct CachedPassSets {
+    spec: PipelineSpec,
+    pass: String,
+    images: Vec<usize>,
+    pass_images_set: usize,
+    collection: Vec<Option<Arc<dyn DescriptorSet + Send + Sync>>>,
+}
+
+// the data given to System::set_globals, which has to be uploaded separately
+// for every pipeline
+struct Globals {
+    create_sets: Box<CreateSets>,
+    set_count: usize,
+}
+
+// uploads the globals for a pipeline, with the first set at the given index
+type CreateSets =
+    dyn Fn(&Resources, Arc<dyn GraphicsPipelineAbstract + Send + Sync>, usize) -> Collection;
+
 #[derive(Default)]
 struct CacheStats {
     hits: u32,
@@ -32,83 +62,256 @@ struct CacheStats {
 }
 
 impl CollectionCache {
-    pub fn new(device: Arc<Device>) -> Self {
-        let sampler = Sampler::new(
-            device,
-            Filter::Linear,
-            Filter::Linear,
-            MipmapMode::Nearest,
-            SamplerAddressMode::ClampToEdge,
-            SamplerAddressMode::ClampToEdge,
-            SamplerAddressMode::ClampToEdge,
-            0.0,
-            1.0,
-            0.0,
-            0.0,
-        )
-        .unwrap();
-
+    pub fn new(resources: Arc<Resources>) -> Self {
         Self {
             c_collections: vec![],
-            sampler,
+            c_globals: vec![],
+            globals: None,
+            resources,
+            samplers: HashMap::new(),
             stats: CacheStats::default(),
         }
     }
 
-    // TODO: replace with a struct that defines a uniform buffer: what spec
-    // pipeline is belongs to, what resources it needs, etc.
+    // returns every set the pipeline uses, in order: the globals, the pass's
+    // images, and the object's own sets at the indices given by layers. set
+    // indices the pipeline doesn't use get an empty set, because vulkano
+    // binds sets starting from 0 without any gaps.
     pub fn get(
         &mut self,
         spec: &PipelineSpec,
         pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
         pass: &Pass,
         images: &HashMap<String, Arc<dyn ImageViewAccess + Send + Sync>>,
+        layers: SetLayers,
+        object_sets: Vec<(usize, Arc<dyn DescriptorSet + Send + Sync>)>,
     ) -> Collection {
-        let mut collection = None;
+        self.check_layers(spec, pass, layers, &object_sets);
 
-        for c_collection in self.c_collections.iter() {
-            if c_collection.spec == *spec {
-                collection = Some(c_collection.collection.clone());
-                self.stats.hits += 1;
+        let mut collection = self.pass_sets(spec, pipeline.clone(), pass, images, layers);
+        let globals = self
+            .global_sets(spec, pipeline, layers)
+            .into_iter()
+            .enumerate()
+            .map(|(idx, set)| (layers.globals + idx, set));
+
+        // nothing else can be at the same index as the pass's images, but the
+        // pipeline might not use every set of the globals, which stay empty
+        for (idx, set) in globals.chain(object_sets) {
+            if let Some(slot) = collection.get_mut(idx) {
+                if slot.is_none() {
+                    *slot = Some(set);
+                }
             }
         }
 
-        match collection {
-            Some(collection) => collection,
-            None => {
-                self.stats.misses += 1;
+        collection
+            .into_iter()
+            .enumerate()
+            .map(|(idx, set)| {
+                set.unwrap_or_else(|| {
+                    panic!(
+                        "set {} of the pipeline using {:?} isn't provided by the globals, the pass or the object",
+                        idx, spec.vs_path
+                    )
+                })
+            })
+            .collect()
+    }
 
-                let start_time = std::time::Instant::now();
+    // panics if more than one layer has a set at the same index, because one
+    // of them would silently replace the other
+    fn check_layers(
+        &self,
+        spec: &PipelineSpec,
+        pass: &Pass,
+        layers: SetLayers,
+        object_sets: &[(usize, Arc<dyn DescriptorSet + Send + Sync>)],
+    ) {
+        let global_count = self.globals.as_ref().map_or(0, |globals| globals.set_count);
+        let pass_images = if pass.images_needed_tags.is_empty() {
+            None
+        } else {
+            Some(layers.pass_images)
+        };
 
-                let images_needed: Vec<Arc<dyn ImageViewAccess + Send + Sync>> = pass
-                    .images_needed_tags
-                    .iter()
-                    .map(|tag| {
-                        images
-                            .get(&tag.to_string())
-                            .expect("missing key when getting image")
-                            .clone()
-                    })
-                    .collect();
+        let claims = (layers.globals..layers.globals + global_count)
+            .map(|idx| (idx, "globals"))
+            .chain(pass_images.map(|idx| (idx, "pass images")))
+            .chain(object_sets.iter().map(|(idx, _)| (*idx, "object's sets")));
 
-                let collection =
-                    collection_from_images(self.sampler.clone(), pipeline.clone(), &images_needed);
+        let mut claimed: HashMap<usize, &str> = HashMap::new();
+        for (idx, layer) in claims {
+            if let Some(other) = claimed.insert(idx, layer) {
+                panic!(
+                    "set {} of the pipeline using {:?} is taken by both the {} and the {}, the set layers overlap",
+                    idx, spec.vs_path, other, layer
+                );
+            }
+        }
+    }
 
-                let c_collection = CachedCollection {
-                    spec: spec.clone(),
-                    collection: collection.clone(),
-                };
-                self.c_collections.push(c_collection);
+    // the pass's images and the empty sets, which only depend on the pipeline
+    // and pass. everything else is None.
+    fn pass_sets(
+        &mut self,
+        spec: &PipelineSpec,
+        pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
+        pass: &Pass,
+        images: &HashMap<String, Arc<dyn ImageViewAccess + Send + Sync>>,
+        layers: SetLayers,
+    ) -> Vec<Option<Arc<dyn DescriptorSet + Send + Sync>>> {
+        let images_needed: Vec<Arc<dyn ImageViewAccess + Send + Sync>> = pass
+            .images_needed_tags
+            .iter()
+            .map(|tag| {
+                images
+                    .get(&tag.to_string())
+                    .expect("missing key when getting image")
+                    .clone()
+            })
+            .collect();
+        let image_ids: Vec<usize> = images_needed.iter().map(image_id).collect();
 
-                self.stats.gen_times.push(get_elapsed(start_time));
+        // there's only ever one entry per pipeline and pass: if the images
+        // changed, the old entry is replaced rather than kept around
+        let existing = self
+            .c_collections
+            .iter()
+            .position(|c_collection| c_collection.spec == *spec && c_collection.pass == pass.name);
 
-                collection
+        if let Some(idx) = existing {
+            let c_collection = &self.c_collections[idx];
+            if c_collection.images == image_ids
+                && c_collection.pass_images_set == layers.pass_images
+            {
+                self.stats.hits += 1;
+                return c_collection.collection.clone();
             }
         }
+
+        self.stats.misses += 1;
+        let start_time = std::time::Instant::now();
+
+        let samplers: Vec<Arc<Sampler>> = pass
+            .images_needed_tags
+            .iter()
+            .map(|tag| {
+                let desc = self
+                    .samplers
+                    .get(*tag)
+                    .copied()
+                    .unwrap_or_else(SamplerDesc::clamped);
+                self.resources.samplers.get(&desc)
+            })
+            .collect();
+
+        let collection: Vec<Option<Arc<dyn DescriptorSet + Send + Sync>>> = (0..pipeline
+            .num_sets())
+            .map(|idx| {
+                if !uses_set(&pipeline, idx) {
+                    Some(empty_set(pipeline.clone(), idx))
+                } else if idx == layers.pass_images {
+                    pds_for_images(&samplers, pipeline.clone(), &images_needed, idx)
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let c_collection = CachedPassSets {
+            spec: spec.clone(),
+            pass: pass.name.to_string(),
+            images: image_ids,
+            pass_images_set: layers.pass_images,
+            collection: collection.clone(),
+        };
+        match existing {
+            Some(idx) => self.c_collections[idx] = c_collection,
+            None => self.c_collections.push(c_collection),
+        }
+
+        self.stats.gen_times.push(get_elapsed(start_time));
+
+        collection
+    }
+
+    // the globals uploaded for this pipeline, or nothing if the pipeline
+    // doesn't use any of them
+    fn global_sets(
+        &mut self,
+        spec: &PipelineSpec,
+        pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
+        layers: SetLayers,
+    ) -> Collection {
+        let globals = match &self.globals {
+            Some(globals) => globals,
+            None => return vec![],
+        };
+
+        if let Some(c_globals) = self
+            .c_globals
+            .iter()
+            .find(|c_globals| c_globals.spec == *spec)
+        {
+            return c_globals.collection.iter().flatten().cloned().collect();
+        }
+
+        let used = (layers.globals..layers.globals + globals.set_count)
+            .any(|idx| uses_set(&pipeline, idx));
+        let collection = if used {
+            (globals.create_sets)(&self.resources, pipeline, layers.globals)
+        } else {
+            vec![]
+        };
+
+        self.c_globals.push(CachedCollection {
+            spec: spec.clone(),
+            collection: collection.iter().cloned().map(Some).collect(),
+        });
+
+        collection
+    }
+
+    // replaces the globals. they're re-uploaded for each pipeline the next
+    // time it's used.
+    pub fn set_globals<D: CollectionData + 'static>(&mut self, globals: D) {
+        let set_count = globals.descriptors().len();
+        self.globals = Some(Globals {
+            create_sets: Box::new(move |resources, pipeline, set_idx_offset| {
+                globals
+                    .create_sets(resources, pipeline, set_idx_offset)
+                    .get()
+            }),
+            set_count,
+        });
+        self.c_globals = vec![];
+    }
+
+    // sets the sampler used for the pass image with the given tag
+    pub fn set_sampler(&mut self, tag: &str, sampler: SamplerDesc) {
+        self.samplers.insert(tag.to_string(), sampler);
+        self.c_collections = vec![];
     }
 
     pub fn clear(&mut self) {
         self.c_collections = vec![];
+        self.c_globals = vec![];
+    }
+
+    // throws away every pass images set, for example because the images were
+    // recreated after the window was resized. the globals don't refer to any
+    // pass images, so they're kept.
+    pub fn clear_pass_images(&mut self) {
+        self.c_collections = vec![];
+    }
+
+    // removes any collections created for the pipeline with the given spec,
+    // for example because the pipeline was recreated after its shaders changed
+    pub fn invalidate(&mut self, spec: &PipelineSpec) {
+        self.c_collections
+            .retain(|c_collection| c_collection.spec != *spec);
+        self.c_globals.retain(|c_globals| c_globals.spec != *spec);
     }
 
     pub fn print_stats(&self) {
@@ -123,22 +326,27 @@ impl CollectionCache {
     }
 }
 
-// TODO: this is kinda obsolete now
-fn collection_from_images(
-    sampler: Arc<Sampler>,
+fn image_id(image: &Arc<dyn ImageViewAccess + Send + Sync>) -> usize {
+    &**image as *const (dyn ImageViewAccess + Send + Sync) as *const () as usize
+}
+
+fn uses_set(pipeline: &Arc<dyn GraphicsPipelineAbstract + Send + Sync>, idx: usize) -> bool {
+    pipeline.num_bindings_in_set(idx).unwrap_or(0) > 0
+}
+
+fn empty_set(
     pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
-    images: &[Arc<dyn ImageViewAccess + Send + Sync>],
-) -> Vec<Arc<dyn DescriptorSet + Send + Sync>> {
-    // assumes set idx should be 0
-    if let Some(image_set) = pds_for_images(sampler, pipeline.clone(), &images, 0) {
-        vec![image_set]
-    } else {
-        vec![]
-    }
+    set_idx: usize,
+) -> Arc<dyn DescriptorSet + Send + Sync> {
+    Arc::new(
+        PersistentDescriptorSet::start(pipeline, set_idx)
+            .build()
+            .unwrap(),
+    )
 }
 
 pub fn pds_for_images(
-    sampler: Arc<Sampler>,
+    samplers: &[Arc<Sampler>],
     pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
     images: &[Arc<dyn ImageViewAccess + Send + Sync>],
     set_idx: usize,
@@ -147,40 +355,40 @@ pub fn pds_for_images(
         0 => None,
         1 => Some(Arc::new(
             PersistentDescriptorSet::start(pipeline, set_idx)
-                .add_sampled_image(images[0].clone(), sampler)
+                .add_sampled_image(images[0].clone(), samplers[0].clone())
                 .unwrap()
                 .build()
                 .unwrap(),
         )),
         2 => Some(Arc::new(
             PersistentDescriptorSet::start(pipeline, set_idx)
-                .add_sampled_image(images[0].clone(), sampler.clone())
+                .add_sampled_image(images[0].clone(), samplers[0].clone())
                 .unwrap()
-                .add_sampled_image(images[1].clone(), sampler.clone())
+                .add_sampled_image(images[1].clone(), samplers[1].clone())
                 .unwrap()
                 .build()
                 .unwrap(),
         )),
         3 => Some(Arc::new(
             PersistentDescriptorSet::start(pipeline, set_idx)
-                .add_sampled_image(images[0].clone(), sampler.clone())
+                .add_sampled_image(images[0].clone(), samplers[0].clone())
                 .unwrap()
-                .add_sampled_image(images[1].clone(), sampler.clone())
+                .add_sampled_image(images[1].clone(), samplers[1].clone())
                 .unwrap()
-                .add_sampled_image(images[2].clone(), sampler.clone())
+                .add_sampled_image(images[2].clone(), samplers[2].clone())
                 .unwrap()
                 .build()
                 .unwrap(),
         )),
         4 => Some(Arc::new(
             PersistentDescriptorSet::start(pipeline, set_idx)
-                .add_sampled_image(images[0].clone(), sampler.clone())
+                .add_sampled_image(images[0].clone(), samplers[0].clone())
                 .unwrap()
-                .add_sampled_image(images[1].clone(), sampler.clone())
+                .add_sampled_image(images[1].clone(), samplers[1].clone())
                 .unwrap()
-                .add_sampled_image(images[2].clone(), sampler.clone())
+                .add_sampled_image(images[2].clone(), samplers[2].clone())
                 .unwrap()
-                .add_sampled_image(images[3].clone(), sampler.clone())
+                .add_sampled_image(images[3].clone(), samplers[3].clone())
                 .unwrap()
                 .build()
                 .unwrap(),
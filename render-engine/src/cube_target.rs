@@ -0,0 +1,111 @@
+// A cubemap that's rendered to, like a point light's shadow map.
+
+// vulkano 0.14 can't make a view of a single face of a cube image, so each
+// face is rendered by its own pass into a normal 2D image, with its own
+// framebuffer. Once the last of those passes is done, the system copies the
+// faces into the cube. Passes that need the cube's tag get the whole thing,
+// sampled as samplerCube in GLSL.
+
+// The face passes should each create one of face_tags, which are in the order
+// +X, -X, +Y, -Y, +Z, -Z. The face images are made by the CubeTarget, at
+// size x size rather than the size of the window.
+
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::device::Device;
+use vulkano::format::Format;
+use vulkano::image::{AttachmentImage, Dimensions, ImageUsage, ImageViewAccess, StorageImage};
+
+use std::sync::Arc;
+
+#[derive(Debug, Clone)]
+pub struct CubeTarget<'a> {
+    pub tag: &'a str,
+    pub face_tags: [&'a str; 6],
+    pub size: u32,
+    pub format: Format,
+}
+
+pub(crate) struct CubeImages {
+    cube: Arc<StorageImage<Format>>,
+    faces: Vec<Arc<AttachmentImage<Format>>>,
+    size: u32,
+}
+
+impl<'a> CubeTarget<'a> {
+    pub(crate) fn create_images(&self, device: Arc<Device>) -> CubeImages {
+        let face_usage = ImageUsage {
+            transfer_source: true,
+            sampled: true,
+            ..ImageUsage::none()
+        };
+        let faces = (0..6)
+            .map(|_| {
+                AttachmentImage::with_usage(
+                    device.clone(),
+                    [self.size, self.size],
+                    self.format,
+                    face_usage,
+                )
+                .unwrap()
+            })
+            .collect();
+
+        let cube_usage = ImageUsage {
+            transfer_destination: true,
+            sampled: true,
+            ..ImageUsage::none()
+        };
+        let cube = StorageImage::with_usage(
+            device.clone(),
+            Dimensions::Cubemap { size: self.size },
+            self.format,
+            cube_usage,
+            device.active_queue_families(),
+        )
+        .unwrap();
+
+        CubeImages {
+            cube,
+            faces,
+            size: self.size,
+        }
+    }
+}
+
+impl CubeImages {
+    pub(crate) fn cube(&self) -> Arc<dyn ImageViewAccess + Send + Sync> {
+        self.cube.clone()
+    }
+
+    pub(crate) fn faces(&self) -> Vec<Arc<dyn ImageViewAccess + Send + Sync>> {
+        self.faces
+            .iter()
+            .map(|face| face.clone() as Arc<dyn ImageViewAccess + Send + Sync>)
+            .collect()
+    }
+
+    // has to be recorded outside of a render pass
+    pub(crate) fn copy_faces(
+        &self,
+        mut cmd_buf: AutoCommandBufferBuilder,
+    ) -> AutoCommandBufferBuilder {
+        for (layer, face) in self.faces.iter().enumerate() {
+            cmd_buf = cmd_buf
+                .copy_image(
+                    face.clone(),
+                    [0, 0, 0],
+                    0,
+                    0,
+                    self.cube.clone(),
+                    [0, 0, 0],
+                    layer as u32,
+                    0,
+                    [self.size, self.size, 1],
+                    1,
+                )
+                .unwrap();
+        }
+
+        cmd_buf
+    }
+}
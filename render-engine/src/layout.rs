@@ -0,0 +1,80 @@
+// What #[derive(Data)] needs to know about a struct to check it against GLSL's
+// layout rules, both when it's uploaded by itself and when it's nested inside
+// another struct or array. See render-engine-derive for the derive itself.
+
+pub trait GlslLayout {
+    // the name of the struct in GLSL
+    const GLSL_NAME: &'static str;
+    // whether the struct's fields were checked against std430 rather than
+    // std140
+    const STD430: bool;
+    // alignment and size when used as a member of a std140 struct or array
+    const STD140_ALIGN: usize;
+    const STD140_SIZE: usize;
+    // the same for std430
+    const STD430_ALIGN: usize;
+    const STD430_SIZE: usize;
+
+    // one line per field, like "    vec3 position;\n"
+    fn glsl_fields() -> String;
+
+    // a GLSL struct declaration with the same layout, for pasting into shaders
+    // or putting in a file to #include
+    fn glsl_struct() -> String {
+        format!("struct {} {{\n{}}};\n", Self::GLSL_NAME, Self::glsl_fields())
+    }
+
+    // the same fields as a uniform block (or a buffer block, for std430)
+    fn glsl_block(set: u32, binding: u32, instance_name: &str) -> String {
+        let (layout, qualifier) = if Self::STD430 {
+            ("std430, ", "buffer")
+        } else {
+            ("", "uniform")
+        };
+
+        format!(
+            "layout({}set = {}, binding = {}) {} {} {{\n{}}} {};\n",
+            layout,
+            set,
+            binding,
+            qualifier,
+            Self::GLSL_NAME,
+            Self::glsl_fields(),
+            instance_name
+        )
+    }
+}
+
+/// Types that are nothing but initialized bytes. utils::as_bytes only takes
+/// these, since reading padding is undefined behaviour.
+///
+/// # Safety
+///
+/// Only scalars, arrays of them and #[repr(C)] structs of them with no padding
+/// anywhere, not even at the end, may implement this. #[derive(Data)]
+/// implements it after checking there's no padding, implementing it by hand
+/// means checking that yourself.
+pub unsafe trait Pod: 'static {}
+
+unsafe impl Pod for f32 {}
+unsafe impl Pod for f64 {}
+unsafe impl Pod for i32 {}
+unsafe impl Pod for u32 {}
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+// used by the generated code, which has to compute layouts in constants
+
+pub const fn align_up(offset: usize, align: usize) -> usize {
+    offset.div_ceil(align) * align
+}
+
+// fails to compile unless T is Pod
+pub const fn assert_pod<T: Pod>() {}
+
+pub const fn max(a: usize, b: usize) -> usize {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
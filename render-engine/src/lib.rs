@@ -6,6 +6,7 @@ pub mod system;
 // pub use camera::{FlyCamera, OrbitCamera, OrthoCamera};
 
 pub mod collection_cache;
+pub mod cube_target;
 pub mod pipeline_cache;
 
 pub mod input;
@@ -18,10 +19,38 @@ pub mod window;
 
 pub mod shaders;
 
+pub mod spirv_cache;
+
+pub mod spirv;
+
+pub mod reflection;
+
+pub mod shader_report;
+
+pub mod validation;
+
 pub mod render_passes;
 
 pub mod collection;
 
+pub mod buffer_pool;
+
+pub mod sampler;
+
+pub mod resources;
+
+pub mod texture;
+
+pub mod texture_data;
+
+pub mod compressed;
+
+pub mod texture_convert;
+
+pub mod texture_loader;
+
+pub mod layout;
+
 pub mod object;
 
 // re-exports of vulkano's stuff
@@ -34,5 +63,6 @@ pub type Buffer = Arc<dyn vulkano::buffer::BufferAccess + Send + Sync>;
 pub type Image = Arc<dyn vulkano::image::ImageViewAccess + Send + Sync>;
 pub type Set = Arc<dyn vulkano::descriptor::descriptor_set::DescriptorSet + Send + Sync>;
 pub use vulkano::format::Format;
+pub use vulkano::image::MipmapsCount;
 
 pub use vulkano::impl_vertex;
@@ -4,7 +4,7 @@ pub use vulkano::impl_vertex;
 use vulkano::device::{Device, Queue};
 use vulkano::buffer::{ImmutableBuffer, BufferAccess};
 use vulkano::framebuffer::{RenderPassAbstract, Subpass};
-use vulkano::pipeline::{GraphicsPipelineAbstract, GraphicsPipeline};
+use vulkano::pipeline::{GraphicsPipelineAbstract, GraphicsPipeline, GraphicsPipelineCreationError};
 use vulkano::pipeline::depth_stencil::{DepthStencil, Compare};
 
 use crate::utils::immutable_slice;
@@ -69,7 +69,7 @@ pub trait VertexTypeAbstract: Any {
         render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
         read_depth: bool,
         write_depth: bool,
-    ) -> Arc<dyn GraphicsPipelineAbstract + Send + Sync>;
+    ) -> Result<Arc<dyn GraphicsPipelineAbstract + Send + Sync>, GraphicsPipelineCreationError>;
 
     fn clone(&self) -> Arc<dyn VertexTypeAbstract>;
 }
@@ -83,44 +83,56 @@ impl<V: Vertex + Send + Sync + Clone + 'static> VertexTypeAbstract for VertexTyp
         render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
         read_depth: bool,
         write_depth: bool,
-    ) -> Arc<dyn GraphicsPipelineAbstract + Send + Sync> {
-        let (vs_main, fs_main) = shaders.get_entry_points();
-
-        if !read_depth && !write_depth {
-            // no depth buffer at all
-            Arc::new(
-                GraphicsPipeline::start()
-                    .vertex_input_single_buffer::<V>()
-                    .vertex_shader(vs_main, ())
-                    .primitive_topology(fill_type)
-                    .viewports_dynamic_scissors_irrelevant(1)
-                    .fragment_shader(fs_main, ())
-                    .render_pass(Subpass::from(render_pass, 0).unwrap())
-                    .build(device)
-                    .unwrap()
-            )
-        } else {
-            let mut stencil = DepthStencil::disabled();
+    ) -> Result<Arc<dyn GraphicsPipelineAbstract + Send + Sync>, GraphicsPipelineCreationError> {
+        let entry_points = shaders.get_entry_points();
+        let (vs_main, vs_spec) = entry_points.vs;
+        let (fs_main, fs_spec) = entry_points.fs;
+
+        // with both disabled this is the same as not having a depth buffer
+        let mut stencil = DepthStencil::disabled();
+        if read_depth || write_depth {
             stencil.depth_compare = if read_depth {
                 Compare::LessOrEqual
             } else {
                 Compare::Always
             };
             stencil.depth_write = write_depth;
-
-            Arc::new(
-                GraphicsPipeline::start()
-                    .vertex_input_single_buffer::<V>()
-                    .vertex_shader(vs_main, ())
-                    .primitive_topology(fill_type)
-                    .viewports_dynamic_scissors_irrelevant(1)
-                    .fragment_shader(fs_main, ())
-                    .depth_stencil(stencil)
-                    .render_pass(Subpass::from(render_pass, 0).unwrap())
-                    .build(device)
-                    .unwrap()
-            )
         }
+
+        let builder = GraphicsPipeline::start()
+            .vertex_input_single_buffer::<V>()
+            .vertex_shader(vs_main, vs_spec)
+            .primitive_topology(fill_type)
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fs_main, fs_spec)
+            .depth_stencil(stencil)
+            .render_pass(Subpass::from(render_pass, 0).unwrap());
+
+        // every optional stage changes the type of the builder, so each
+        // combination needs its own branch
+        let pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync> =
+            match (entry_points.tcs, entry_points.tes, entry_points.gs) {
+                (None, None, None) => Arc::new(builder.build(device)?),
+                (None, None, Some((gs_main, gs_spec))) => {
+                    Arc::new(builder.geometry_shader(gs_main, gs_spec).build(device)?)
+                }
+                (Some((tcs_main, tcs_spec)), Some((tes_main, tes_spec)), None) => Arc::new(
+                    builder
+                        .tessellation_shaders(tcs_main, tcs_spec, tes_main, tes_spec)
+                        .build(device)?,
+                ),
+                (Some((tcs_main, tcs_spec)), Some((tes_main, tes_spec)), Some((gs_main, gs_spec))) => {
+                    Arc::new(
+                        builder
+                            .tessellation_shaders(tcs_main, tcs_spec, tes_main, tes_spec)
+                            .geometry_shader(gs_main, gs_spec)
+                            .build(device)?,
+                    )
+                }
+                _ => unreachable!("ShaderSystem always has both tessellation shaders or neither"),
+            };
+
+        Ok(pipeline)
     }
 
     fn clone(&self) -> Arc<dyn VertexTypeAbstract> {
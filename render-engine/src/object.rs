@@ -1,35 +1,133 @@
 use vulkano::buffer::{BufferAccess, ImmutableBuffer};
 use vulkano::command_buffer::DynamicState;
 use vulkano::descriptor::DescriptorSet;
-use vulkano::device::Queue;
 use vulkano::framebuffer::RenderPassAbstract;
 use vulkano::pipeline::input_assembly::PrimitiveTopology;
 
-use crate::collection::{Collection, CollectionData};
+use crate::collection::{Collection, CollectionData, ProvidedDescriptor, SetLayers};
 use crate::mesh::{Mesh, MeshAbstract, Vertex, VertexType};
 use crate::pipeline_cache::PipelineSpec;
+use crate::resources::Resources;
+use crate::shader_report::ShaderReport;
+use crate::shaders::ShaderOptions;
+use crate::utils::as_bytes;
+use crate::validation::{
+    validate_collection, validate_push_constants, validate_vertex, BuildError, ValidationError,
+};
 
 use std::path::PathBuf;
 use std::sync::Arc;
 
 #[derive(Clone)]
-pub struct Object<C: Collection> {
+pub struct Object<C: Collection, M: Collection = ()> {
     pub pipeline_spec: PipelineSpec,
     pub vbuf: Arc<dyn BufferAccess + Send + Sync>,
     pub ibuf: Arc<ImmutableBuffer<[u32]>>,
+    pub material: M,
     pub collection: C,
+    // where the material and collection are bound. has to be the same as the
+    // system's, System::add_object panics otherwise.
+    pub set_layers: SetLayers,
+    // raw bytes, see utils::as_bytes. cheaper than a uniform buffer for small
+    // per-object data like a model matrix. private so they can't be changed
+    // without being checked against the shaders again, see
+    // set_push_constants.
+    push_constants: Option<Vec<u8>>,
     pub custom_dynamic_state: Option<DynamicState>,
+    // what the shaders declare and what build() checked against it, so
+    // changes after building can be checked again
+    shader_report: Arc<ShaderReport>,
+    checks: Arc<Checks>,
 }
 
+// everything build() checked except the push constants, which can change
+struct Checks {
+    vertex: fn(&ShaderReport) -> Result<(), ValidationError>,
+    material: Vec<Vec<ProvidedDescriptor>>,
+    collection: Vec<Vec<ProvidedDescriptor>>,
+}
+
+impl<C: Collection, M: Collection> Object<C, M> {
+    // replaces the push constants, as long as the shaders would still accept
+    // them. objects cloned from one another share everything else, so this
+    // is how lots of objects with the same pipeline and sets are made.
+    pub fn set_push_constants(
+        &mut self,
+        push_constants: Option<Vec<u8>>,
+    ) -> Result<(), ValidationError> {
+        validate_push_constants(
+            &self.shader_report,
+            push_constants.as_ref().map(|bytes| bytes.len()),
+        )?;
+        self.push_constants = push_constants;
+
+        Ok(())
+    }
+
+    // a copy of this object with nothing but `index` as its push constants,
+    // for drawing lots of objects without a set each: their data goes in one
+    // StorageSlice in the globals and each one picks its own out of it with
+    //
+    //     layout(push_constant) uniform Object { uint index; } object;
+    //
+    // the copy shares the pipeline and sets, so give it its own vbuf and ibuf
+    // if it's a different mesh. see obj-viewer.
+    pub fn with_index(&self, index: u32) -> Result<Self, ValidationError>
+    where
+        Self: Clone,
+    {
+        let mut object = self.clone();
+        object.set_push_constants(Some(as_bytes(&index)))?;
+
+        Ok(object)
+    }
+
+    // switches to another variant of the shaders, like different #defines.
+    // the shaders are loaded and checked again right away, and the object is
+    // left as it was if they don't fit. the pipeline itself is still only
+    // created the first time the object is drawn with the new options.
+    pub fn set_shader_options(
+        &mut self,
+        resources: &Resources,
+        shader_options: ShaderOptions,
+    ) -> Result<(), BuildError> {
+        let pipeline_spec = PipelineSpec {
+            shader_options,
+            ..self.pipeline_spec.clone()
+        };
+        let shaders = pipeline_spec.load_shaders(resources.queue.device().clone())?;
+        let report = shaders.report();
+        validate(
+            &report,
+            &self.checks,
+            self.set_layers,
+            self.push_constants.as_ref().map(|bytes| bytes.len()),
+        )?;
+
+        self.pipeline_spec = pipeline_spec;
+        self.shader_report = Arc::new(report);
+
+        Ok(())
+    }
+}
+
+// every object binds its own material and collection sets. to draw lots of
+// objects without a set each, put all of their data in one StorageSlice in the
+// globals and make the objects with Object::with_index. dynamic uniform buffers would be the other way, but vulkano
+// 0.14's AutoCommandBufferBuilder can't bind sets with dynamic offsets.
 pub trait Drawcall {
     fn pipe_spec(&self) -> &PipelineSpec;
     fn vbuf(&self) -> Arc<dyn BufferAccess + Send + Sync>;
     fn ibuf(&self) -> Arc<ImmutableBuffer<[u32]>>;
-    fn collection(&self) -> Vec<Arc<dyn DescriptorSet + Send + Sync>>;
+    // the sets the object supplies itself, along with their set indices
+    fn collection(&self) -> Vec<(usize, Arc<dyn DescriptorSet + Send + Sync>)>;
+    // what the indices in collection() were picked with
+    fn set_layers(&self) -> SetLayers;
+    fn push_constants(&self) -> Option<&[u8]>;
     fn custom_dynstate(&self) -> Option<DynamicState>;
 }
 
-impl<C: Collection> Drawcall for Object<C> {
+impl<C: Collection, M: Collection> Drawcall for Object<C, M> {
     fn pipe_spec(&self) -> &PipelineSpec {
         &self.pipeline_spec
     }
@@ -42,8 +140,29 @@ impl<C: Collection> Drawcall for Object<C> {
         self.ibuf.clone()
     }
 
-    fn collection(&self) -> Vec<Arc<dyn DescriptorSet + Send + Sync>> {
-        self.collection.get()
+    fn collection(&self) -> Vec<(usize, Arc<dyn DescriptorSet + Send + Sync>)> {
+        let material = self
+            .material
+            .get()
+            .into_iter()
+            .enumerate()
+            .map(|(idx, set)| (self.set_layers.material + idx, set));
+        let own = self
+            .collection
+            .get()
+            .into_iter()
+            .enumerate()
+            .map(|(idx, set)| (self.set_layers.object + idx, set));
+
+        material.chain(own).collect()
+    }
+
+    fn set_layers(&self) -> SetLayers {
+        self.set_layers
+    }
+
+    fn push_constants(&self) -> Option<&[u8]> {
+        self.push_constants.as_ref().map(|bytes| &bytes[..])
     }
 
     fn custom_dynstate(&self) -> Option<DynamicState> {
@@ -52,45 +171,91 @@ impl<C: Collection> Drawcall for Object<C> {
 }
 
 #[derive(Clone)]
-pub struct ObjectPrototype<V: Vertex, D: CollectionData> {
+pub struct ObjectPrototype<V: Vertex, D: CollectionData, M: CollectionData = ()> {
     pub vs_path: PathBuf,
     pub fs_path: PathBuf,
     pub fill_type: PrimitiveTopology,
     pub read_depth: bool,
     pub write_depth: bool,
+    pub shader_options: ShaderOptions,
     pub mesh: Mesh<V>,
+    // can be () if the object has no material, or everything is in collection
+    pub material: M,
     pub collection: D,
+    pub set_layers: SetLayers,
+    pub push_constants: Option<Vec<u8>>,
     pub custom_dynamic_state: Option<DynamicState>,
 }
 
-impl<V: Vertex, D: CollectionData + 'static> ObjectPrototype<V, D> {
+impl<V: Vertex, D: CollectionData + 'static, M: CollectionData + 'static> ObjectPrototype<V, D, M> {
+    // fails if the shaders don't compile or if the vertex type or collection
+    // don't match what the shaders declare
     pub fn build(
         self,
-        queue: Arc<Queue>,
+        resources: &Resources,
         render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
-    ) -> Object<D::Sets> {
-        let vbuf = self.mesh.get_vbuf(queue.clone());
-        let ibuf = self.mesh.get_ibuf(queue.clone());
-
+    ) -> Result<Object<D::Sets, M::Sets>, BuildError> {
         let pipeline_spec = PipelineSpec {
             vs_path: self.vs_path,
             fs_path: self.fs_path,
             fill_type: self.fill_type,
             read_depth: self.read_depth,
             write_depth: self.write_depth,
+            shader_options: self.shader_options,
             vtype: VertexType::<V>::new(),
         };
-        let pipeline = pipeline_spec.concrete(queue.device().clone(), render_pass);
+        let queue = resources.queue.clone();
+        let device = queue.device().clone();
 
-        // TODO: offset is not always 0
-        let collection = self.collection.create_sets(queue.device().clone(), pipeline, 0);
+        // check everything before vulkano gets to complain about it
+        let shaders = pipeline_spec.load_shaders(device.clone())?;
+        let report = shaders.report();
+        let checks = Checks {
+            vertex: validate_vertex::<V>,
+            material: self.material.descriptors(),
+            collection: self.collection.descriptors(),
+        };
+        validate(
+            &report,
+            &checks,
+            self.set_layers,
+            self.push_constants.as_ref().map(|bytes| bytes.len()),
+        )?;
+
+        let pipeline = pipeline_spec.pipeline_from_shaders(device.clone(), render_pass, shaders)?;
 
-        Object {
+        let vbuf = self.mesh.get_vbuf(queue.clone());
+        let ibuf = self.mesh.get_ibuf(queue.clone());
+        let material =
+            self.material
+                .create_sets(resources, pipeline.clone(), self.set_layers.material);
+        let collection = self
+            .collection
+            .create_sets(resources, pipeline, self.set_layers.object);
+
+        Ok(Object {
             pipeline_spec,
             vbuf,
             ibuf,
+            material,
             collection,
+            set_layers: self.set_layers,
+            push_constants: self.push_constants,
             custom_dynamic_state: self.custom_dynamic_state,
-        }
+            shader_report: Arc::new(report),
+            checks: Arc::new(checks),
+        })
     }
 }
+
+fn validate(
+    report: &ShaderReport,
+    checks: &Checks,
+    set_layers: SetLayers,
+    push_constants_size: Option<usize>,
+) -> Result<(), ValidationError> {
+    (checks.vertex)(report)?;
+    validate_collection(report, &checks.material, set_layers.material)?;
+    validate_collection(report, &checks.collection, set_layers.object)?;
+    validate_push_constants(report, push_constants_size)
+}
@@ -3,12 +3,17 @@ use vulkano::framebuffer::RenderPassAbstract;
 use vulkano::pipeline::input_assembly::PrimitiveTopology;
 use vulkano::pipeline::GraphicsPipelineAbstract;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Instant, SystemTime};
 
 use crate::input::get_elapsed;
 use crate::mesh::VertexTypeAbstract;
-use crate::shaders::ShaderSystem;
+use crate::shaders::{ShaderError, ShaderErrorKind, ShaderOptions, ShaderSystem};
+
+// how often reload_changed actually looks at the shader files, in seconds.
+// stat-ing every shader every frame adds up when there are a lot of pipelines.
+const RELOAD_POLL_INTERVAL: f32 = 0.25;
 
 // pipeline caches are specific to a single render pass.
 pub struct PipelineCache {
@@ -17,11 +22,15 @@ pub struct PipelineCache {
     device: Arc<Device>,
     render_pass: Arc<dyn RenderPassAbstract + Send + Sync>,
     stats: CacheStats,
+    last_reload_check: Instant,
 }
 
 struct CachedPipeline {
     spec: PipelineSpec,
     pipeline: Arc<dyn GraphicsPipelineAbstract + Send + Sync>,
+    // modification times of the shader files and everything they include at
+    // the time the pipeline was created, used to notice when they change
+    mtimes: Vec<(PathBuf, Option<SystemTime>)>,
 }
 
 #[derive(Default)]
@@ -38,6 +47,7 @@ pub struct PipelineSpec {
     pub fill_type: PrimitiveTopology,
     pub read_depth: bool,
     pub write_depth: bool,
+    pub shader_options: ShaderOptions,
     pub vtype: Arc<dyn VertexTypeAbstract>
 }
 
@@ -51,10 +61,11 @@ impl PipelineCache {
             device,
             render_pass,
             stats: CacheStats::default(),
+            last_reload_check: Instant::now(),
         }
     }
 
-    pub fn get(&mut self, spec: &PipelineSpec) -> Arc<dyn GraphicsPipelineAbstract + Send + Sync> {
+    pub fn get(&mut self, spec: &PipelineSpec) -> Result<Arc<dyn GraphicsPipelineAbstract + Send + Sync>, ShaderError> {
         let mut pipeline = None;
 
         // first search through cached pipelines to see if we have one with matching spec
@@ -67,26 +78,74 @@ impl PipelineCache {
         }
 
         match pipeline {
-            Some(pipeline) => pipeline,
+            Some(pipeline) => Ok(pipeline),
             None => {
                 self.stats.misses += 1;
                 let start_time = std::time::Instant::now();
 
-                let pipeline = spec.concrete(self.device.clone(), self.render_pass.clone());
+                let (pipeline, shaders) =
+                    spec.concrete_with_shaders(self.device.clone(), self.render_pass.clone())?;
                 let c_pipe = CachedPipeline {
                     spec: spec.clone(),
                     pipeline: pipeline.clone(),
+                    mtimes: mtimes(&shaders.source_files()),
                 };
 
                 self.c_pipes.push(c_pipe);
 
                 self.stats.gen_times.push(get_elapsed(start_time));
 
-                pipeline
+                Ok(pipeline)
             }
         }
     }
 
+    // checks whether the shader files of any cached pipeline were modified
+    // since it was created and recompiles them if so. returns the specs of all
+    // pipelines that were replaced, so anything created from the old pipelines
+    // can be thrown away too. if a shader fails to compile the error is printed
+    // and the old pipeline is kept.
+    pub fn reload_changed(&mut self) -> Vec<PipelineSpec> {
+        if get_elapsed(self.last_reload_check) < RELOAD_POLL_INTERVAL {
+            return vec![];
+        }
+        self.last_reload_check = Instant::now();
+
+        let mut reloaded = vec![];
+
+        for c_pipe in self.c_pipes.iter_mut() {
+            let sources: Vec<PathBuf> = c_pipe.mtimes.iter().map(|(path, _)| path.clone()).collect();
+            let new_mtimes = mtimes(&sources);
+            if new_mtimes == c_pipe.mtimes {
+                continue;
+            }
+
+            // remember the new times even if compilation fails, otherwise a
+            // broken shader would be recompiled (and complained about) on
+            // every poll until it is fixed
+            c_pipe.mtimes = new_mtimes;
+
+            match c_pipe
+                .spec
+                .concrete_with_shaders(self.device.clone(), self.render_pass.clone())
+            {
+                Ok((pipeline, shaders)) => {
+                    // the includes might have changed too
+                    c_pipe.mtimes = mtimes(&shaders.source_files());
+                    println!(
+                        "Reloaded shaders {:?} and {:?}",
+                        c_pipe.spec.vs_path, c_pipe.spec.fs_path
+                    );
+                    c_pipe.pipeline = pipeline;
+                    reloaded.push(c_pipe.spec.clone());
+                }
+                Err(e) => println!("Couldn't reload shaders, keeping the old pipeline.\n{}", e),
+            }
+        }
+
+        reloaded
+    }
+
     pub fn print_stats(&self) {
         let avg: f32 =
             self.stats.gen_times.iter().sum::<f32>() / (self.stats.gen_times.len() as f32) * 1_000.0;
@@ -100,19 +159,76 @@ impl PipelineCache {
 }
 
 impl PipelineSpec {
-    pub fn concrete(&self, device: Arc<Device>, render_pass: Arc<dyn RenderPassAbstract + Send + Sync>) -> Arc<dyn GraphicsPipelineAbstract + Send + Sync> {
-        let shader_sys =
-            ShaderSystem::load_from_file(device.clone(), &self.vs_path, &self.fs_path);
+    pub fn concrete(&self, device: Arc<Device>, render_pass: Arc<dyn RenderPassAbstract + Send + Sync>) -> Result<Arc<dyn GraphicsPipelineAbstract + Send + Sync>, ShaderError> {
+        self.concrete_with_shaders(device, render_pass)
+            .map(|(pipeline, _shaders)| pipeline)
+    }
 
-        self.vtype.create_pipeline(
+    // also returns the shaders, so their source files can be watched for
+    // changes
+    fn concrete_with_shaders(&self, device: Arc<Device>, render_pass: Arc<dyn RenderPassAbstract + Send + Sync>) -> Result<(Arc<dyn GraphicsPipelineAbstract + Send + Sync>, ShaderSystem), ShaderError> {
+        let shader_sys = self.load_shaders(device.clone())?;
+        let pipeline = self.pipeline_from_shaders(device, render_pass, shader_sys.clone())?;
+
+        Ok((pipeline, shader_sys))
+    }
+
+    // loading the shaders and creating the pipeline are separate so the
+    // shaders can be checked against the data that will be bound in between
+    pub fn load_shaders(&self, device: Arc<Device>) -> Result<ShaderSystem, ShaderError> {
+        let shader_sys = ShaderSystem::load_from_file(
             device,
-            shader_sys,
-            self.fill_type,
-            render_pass,
-            self.read_depth,
-            self.write_depth,
-        )
+            &self.vs_path,
+            &self.fs_path,
+            &self.shader_options,
+        )?;
+
+        let is_patch_list = matches!(self.fill_type, PrimitiveTopology::PatchList { .. });
+        if is_patch_list != shader_sys.has_tessellation() {
+            return Err(ShaderError {
+                path: self.vs_path.clone(),
+                stage: None,
+                kind: ShaderErrorKind::Pipeline(
+                    "a fill_type of PatchList must be used if and only if there are tessellation shaders"
+                        .to_string(),
+                ),
+            });
+        }
+
+        Ok(shader_sys)
     }
+
+    pub fn pipeline_from_shaders(&self, device: Arc<Device>, render_pass: Arc<dyn RenderPassAbstract + Send + Sync>, shader_sys: ShaderSystem) -> Result<Arc<dyn GraphicsPipelineAbstract + Send + Sync>, ShaderError> {
+        self.vtype
+            .create_pipeline(
+                device,
+                shader_sys,
+                self.fill_type,
+                render_pass,
+                self.read_depth,
+                self.write_depth,
+            )
+            .map_err(|e| ShaderError {
+                path: self.vs_path.clone(),
+                stage: None,
+                kind: ShaderErrorKind::Pipeline(format!("{:?}", e)),
+            })
+    }
+}
+
+// pairs each file with when it was last modified. files that can't be read
+// have a time of None.
+fn mtimes(paths: &[PathBuf]) -> Vec<(PathBuf, Option<SystemTime>)> {
+    paths
+        .iter()
+        .map(|path| (path.clone(), modified_time(path)))
+        .collect()
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
 }
 
 impl PartialEq for PipelineSpec {
@@ -122,6 +238,7 @@ impl PartialEq for PipelineSpec {
             && self.fill_type == other.fill_type
             && self.read_depth == other.read_depth
             && self.write_depth == other.write_depth
+            && self.shader_options == other.shader_options
     }
 }
 
@@ -133,6 +250,7 @@ impl Clone for PipelineSpec {
             fill_type: self.fill_type,
             read_depth: self.read_depth,
             write_depth: self.write_depth,
+            shader_options: self.shader_options.clone(),
             vtype: self.vtype.clone(),
         }
     }
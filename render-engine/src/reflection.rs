@@ -0,0 +1,1288 @@
+// Reflection straight from the SPIR-V, mostly for the shader stages
+// shade_runner doesn't handle (geometry and tessellation). Reads the inputs,
+// outputs, descriptors and push constants of an entry point and turns them into
+// the interface and layout types vulkano's pipeline builder wants.
+
+use vulkano::descriptor::descriptor::{
+    DescriptorBufferDesc, DescriptorDesc, DescriptorDescTy, DescriptorImageDesc,
+    DescriptorImageDescArray, DescriptorImageDescDimensions, ShaderStages,
+};
+use vulkano::descriptor::pipeline_layout::{PipelineLayoutDesc, PipelineLayoutDescPcRange};
+use vulkano::format::Format;
+use vulkano::pipeline::shader::{
+    GeometryShaderExecutionMode, ShaderInterfaceDef, ShaderInterfaceDefEntry,
+};
+
+use crate::spirv::{self, SpecConstantInfo};
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+// opcodes, from the SPIR-V spec
+const OP_NAME: u16 = 5;
+const OP_MEMBER_NAME: u16 = 6;
+const OP_ENTRY_POINT: u16 = 15;
+const OP_EXECUTION_MODE: u16 = 16;
+const OP_TYPE_VOID: u16 = 19;
+const OP_TYPE_BOOL: u16 = 20;
+const OP_TYPE_INT: u16 = 21;
+const OP_TYPE_FLOAT: u16 = 22;
+const OP_TYPE_VECTOR: u16 = 23;
+const OP_TYPE_MATRIX: u16 = 24;
+const OP_TYPE_IMAGE: u16 = 25;
+const OP_TYPE_SAMPLER: u16 = 26;
+const OP_TYPE_SAMPLED_IMAGE: u16 = 27;
+const OP_TYPE_ARRAY: u16 = 28;
+const OP_TYPE_RUNTIME_ARRAY: u16 = 29;
+const OP_TYPE_STRUCT: u16 = 30;
+const OP_TYPE_POINTER: u16 = 32;
+const OP_CONSTANT: u16 = 43;
+const OP_SPEC_CONSTANT: u16 = 50;
+const OP_VARIABLE: u16 = 59;
+const OP_DECORATE: u16 = 71;
+const OP_MEMBER_DECORATE: u16 = 72;
+
+// decorations
+const DECORATION_BLOCK: u32 = 2;
+const DECORATION_BUFFER_BLOCK: u32 = 3;
+const DECORATION_ARRAY_STRIDE: u32 = 6;
+const DECORATION_MATRIX_STRIDE: u32 = 7;
+const DECORATION_BUILT_IN: u32 = 11;
+const DECORATION_PATCH: u32 = 15;
+const DECORATION_NON_WRITABLE: u32 = 24;
+const DECORATION_LOCATION: u32 = 30;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+const DECORATION_OFFSET: u32 = 35;
+
+// storage classes
+const STORAGE_UNIFORM_CONSTANT: u32 = 0;
+const STORAGE_INPUT: u32 = 1;
+const STORAGE_UNIFORM: u32 = 2;
+const STORAGE_OUTPUT: u32 = 3;
+const STORAGE_PUSH_CONSTANT: u32 = 9;
+const STORAGE_STORAGE_BUFFER: u32 = 12;
+
+// execution models
+const MODEL_VERTEX: u32 = 0;
+const MODEL_TESS_CONTROL: u32 = 1;
+const MODEL_TESS_EVALUATION: u32 = 2;
+const MODEL_GEOMETRY: u32 = 3;
+const MODEL_FRAGMENT: u32 = 4;
+const MODEL_COMPUTE: u32 = 5;
+
+// image dimensions
+const DIM_1D: u32 = 0;
+const DIM_3D: u32 = 2;
+const DIM_CUBE: u32 = 3;
+const DIM_BUFFER: u32 = 5;
+const DIM_SUBPASS_DATA: u32 = 6;
+
+// geometry shader input primitives
+const MODE_INPUT_POINTS: u32 = 19;
+const MODE_INPUT_LINES: u32 = 20;
+const MODE_INPUT_LINES_ADJACENCY: u32 = 21;
+const MODE_TRIANGLES: u32 = 22;
+const MODE_INPUT_TRIANGLES_ADJACENCY: u32 = 23;
+
+#[derive(Debug, Clone)]
+pub struct Reflection {
+    pub entry_name: String,
+    pub stages: ShaderStages,
+    pub inputs: Vec<InterfaceVariable>,
+    pub outputs: Vec<InterfaceVariable>,
+    pub descriptors: Vec<DescriptorBinding>,
+    // the size in bytes of the push constant block, if there is one
+    pub push_constants_size: Option<u32>,
+    pub spec_constants: Vec<SpecConstantInfo>,
+    pub geometry_input: Option<GeometryShaderExecutionMode>,
+}
+
+#[derive(Debug, Clone)]
+pub struct InterfaceVariable {
+    pub name: Option<String>,
+    pub type_name: String,
+    // the interface entries the variable turns into. blocks turn into one per
+    // member, everything else into just one.
+    pub entries: Vec<ShaderInterfaceDefEntry>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DescriptorBinding {
+    pub name: Option<String>,
+    pub type_name: String,
+    pub set: u32,
+    pub binding: u32,
+    pub desc: DescriptorDesc,
+    // for buffers, the size of the block in bytes. None if it ends in a
+    // runtime-sized array or isn't a buffer.
+    pub block_size: Option<u32>,
+    // for sampled images, whether the shader compares against them, like
+    // sampler2DShadow. those need a sampler with a compare op.
+    pub compare: bool,
+}
+
+impl Reflection {
+    pub fn new(words: &[u32], entry_name: &str) -> Result<Self, String> {
+        let module = Module::parse(words);
+
+        let entry_point = module
+            .entry_points
+            .iter()
+            .find(|entry_point| entry_point.name == entry_name)
+            .ok_or_else(|| format!("no entry point named {:?}", entry_name))?;
+
+        let stages = stages_for_model(entry_point.model)
+            .ok_or_else(|| format!("unsupported execution model {}", entry_point.model))?;
+
+        // inputs to tessellation and geometry shaders, and outputs of
+        // tessellation control shaders, have one element per vertex. their
+        // outer array isn't part of the interface.
+        let arrayed_inputs = matches!(
+            entry_point.model,
+            MODEL_TESS_CONTROL | MODEL_TESS_EVALUATION | MODEL_GEOMETRY
+        );
+        let arrayed_outputs = entry_point.model == MODEL_TESS_CONTROL;
+
+        let mut inputs = vec![];
+        let mut outputs = vec![];
+        for &var_id in entry_point.interface.iter() {
+            let var = match module.variables.iter().find(|var| var.id == var_id) {
+                Some(var) => var,
+                None => continue,
+            };
+
+            match var.storage {
+                STORAGE_INPUT => {
+                    if let Some(var) = module.interface_variable(var, arrayed_inputs) {
+                        inputs.push(var);
+                    }
+                }
+                STORAGE_OUTPUT => {
+                    if let Some(var) = module.interface_variable(var, arrayed_outputs) {
+                        outputs.push(var);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let descriptors = module
+            .variables
+            .iter()
+            .filter_map(|var| module.descriptor_binding(var, stages))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let push_constants_size = module
+            .variables
+            .iter()
+            .find(|var| var.storage == STORAGE_PUSH_CONSTANT)
+            .and_then(|var| module.size_of(var.pointee));
+
+        let geometry_input = module
+            .execution_modes
+            .iter()
+            .filter(|(entry, _)| *entry == entry_point.function)
+            .filter_map(|(_, mode)| match *mode {
+                MODE_INPUT_POINTS => Some(GeometryShaderExecutionMode::Points),
+                MODE_INPUT_LINES => Some(GeometryShaderExecutionMode::Lines),
+                MODE_INPUT_LINES_ADJACENCY => Some(GeometryShaderExecutionMode::LinesWithAdjacency),
+                MODE_TRIANGLES => Some(GeometryShaderExecutionMode::Triangles),
+                MODE_INPUT_TRIANGLES_ADJACENCY => {
+                    Some(GeometryShaderExecutionMode::TrianglesWithAdjacency)
+                }
+                _ => None,
+            })
+            .next();
+
+        Ok(Self {
+            entry_name: entry_name.to_string(),
+            stages,
+            inputs,
+            outputs,
+            descriptors,
+            push_constants_size,
+            spec_constants: spirv::spec_constants(words),
+            geometry_input,
+        })
+    }
+
+    pub fn input_interface(&self) -> Interface {
+        Interface::from_variables(&self.inputs)
+    }
+
+    pub fn output_interface(&self) -> Interface {
+        Interface::from_variables(&self.outputs)
+    }
+
+    pub fn layout(&self) -> Layout {
+        let num_sets = self
+            .descriptors
+            .iter()
+            .map(|descriptor| descriptor.set as usize + 1)
+            .max()
+            .unwrap_or(0);
+
+        let mut sets: Vec<Vec<Option<DescriptorDesc>>> = vec![vec![]; num_sets];
+        for descriptor in self.descriptors.iter() {
+            let set = &mut sets[descriptor.set as usize];
+            let binding = descriptor.binding as usize;
+            if set.len() <= binding {
+                set.resize(binding + 1, None);
+            }
+            set[binding] = Some(descriptor.desc.clone());
+        }
+
+        let push_constants = self
+            .push_constants_size
+            .map(|size| PipelineLayoutDescPcRange {
+                offset: 0,
+                size: size as usize,
+                stages: self.stages,
+            })
+            .into_iter()
+            .collect();
+
+        Layout {
+            sets,
+            push_constants,
+        }
+    }
+}
+
+fn stages_for_model(model: u32) -> Option<ShaderStages> {
+    let mut stages = ShaderStages::none();
+    match model {
+        MODEL_VERTEX => stages.vertex = true,
+        MODEL_TESS_CONTROL => stages.tessellation_control = true,
+        MODEL_TESS_EVALUATION => stages.tessellation_evaluation = true,
+        MODEL_GEOMETRY => stages.geometry = true,
+        MODEL_FRAGMENT => stages.fragment = true,
+        MODEL_COMPUTE => stages.compute = true,
+        _ => return None,
+    }
+
+    Some(stages)
+}
+
+// the inputs or outputs of a shader stage
+#[derive(Debug, Clone)]
+pub struct Interface {
+    entries: Vec<ShaderInterfaceDefEntry>,
+}
+
+impl Interface {
+    fn from_variables(variables: &[InterfaceVariable]) -> Self {
+        Self {
+            entries: variables
+                .iter()
+                .flat_map(|var| var.entries.iter().cloned())
+                .collect(),
+        }
+    }
+
+    // copies another interface, for example one made by shade_runner
+    pub fn from_def<I: ShaderInterfaceDef>(def: &I) -> Self {
+        Self {
+            entries: def.elements().collect(),
+        }
+    }
+}
+
+unsafe impl ShaderInterfaceDef for Interface {
+    type Iter = std::vec::IntoIter<ShaderInterfaceDefEntry>;
+
+    fn elements(&self) -> Self::Iter {
+        self.entries.clone().into_iter()
+    }
+}
+
+// the descriptors and push constants used by a shader stage
+#[derive(Debug, Clone)]
+pub struct Layout {
+    sets: Vec<Vec<Option<DescriptorDesc>>>,
+    push_constants: Vec<PipelineLayoutDescPcRange>,
+}
+
+unsafe impl PipelineLayoutDesc for Layout {
+    fn num_sets(&self) -> usize {
+        self.sets.len()
+    }
+
+    fn num_bindings_in_set(&self, set: usize) -> Option<usize> {
+        self.sets.get(set).map(|bindings| bindings.len())
+    }
+
+    fn descriptor(&self, set: usize, binding: usize) -> Option<DescriptorDesc> {
+        self.sets.get(set)?.get(binding)?.clone()
+    }
+
+    fn num_push_constants_ranges(&self) -> usize {
+        self.push_constants.len()
+    }
+
+    fn push_constants_range(&self, num: usize) -> Option<PipelineLayoutDescPcRange> {
+        self.push_constants.get(num).cloned()
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Type {
+    Void,
+    Bool,
+    Int { width: u32, signed: bool },
+    Float { width: u32 },
+    Vector { component: u32, count: u32 },
+    Matrix { column: u32, columns: u32 },
+    Image {
+        dim: u32,
+        // only true if the shader says so, not if it's left unknown
+        depth: bool,
+        arrayed: bool,
+        multisampled: bool,
+        // 1 if used with a sampler, 2 if used as a storage image
+        sampled: u32,
+    },
+    Sampler,
+    SampledImage { image: u32 },
+    // length is None for runtime-sized arrays
+    Array { element: u32, length: Option<u32> },
+    Struct { members: Vec<u32> },
+    Pointer { pointee: u32 },
+}
+
+struct EntryPoint {
+    model: u32,
+    function: u32,
+    name: String,
+    interface: Vec<u32>,
+}
+
+struct Variable {
+    id: u32,
+    storage: u32,
+    // the type the variable points to
+    pointee: u32,
+}
+
+struct Module {
+    entry_points: Vec<EntryPoint>,
+    // (entry point function, mode)
+    execution_modes: Vec<(u32, u32)>,
+    names: HashMap<u32, String>,
+    member_names: HashMap<(u32, u32), String>,
+    // (target, decoration) -> first literal, or 0 if there isn't one
+    decorations: HashMap<(u32, u32), u32>,
+    member_decorations: HashMap<(u32, u32, u32), u32>,
+    types: HashMap<u32, Type>,
+    constants: HashMap<u32, u32>,
+    variables: Vec<Variable>,
+}
+
+impl Module {
+    fn parse(words: &[u32]) -> Self {
+        let mut module = Self {
+            entry_points: vec![],
+            execution_modes: vec![],
+            names: HashMap::new(),
+            member_names: HashMap::new(),
+            decorations: HashMap::new(),
+            member_decorations: HashMap::new(),
+            types: HashMap::new(),
+            constants: HashMap::new(),
+            variables: vec![],
+        };
+
+        for inst in spirv::instructions(words) {
+            let ops = inst.operands;
+
+            // each arm checks its own operand count, since some types (void,
+            // bool, sampler) only have a result id
+            match inst.opcode {
+                OP_NAME if ops.len() > 1 => {
+                    module.names.insert(ops[0], spirv::string_literal(&ops[1..]));
+                }
+                OP_MEMBER_NAME if ops.len() > 2 => {
+                    module
+                        .member_names
+                        .insert((ops[0], ops[1]), spirv::string_literal(&ops[2..]));
+                }
+                OP_ENTRY_POINT if ops.len() > 2 => {
+                    let name = spirv::string_literal(&ops[2..]);
+                    // the name takes up its length plus a nul, rounded up to
+                    // whole words. the interface ids come after it.
+                    let name_words = name.len() / 4 + 1;
+                    module.entry_points.push(EntryPoint {
+                        model: ops[0],
+                        function: ops[1],
+                        name,
+                        interface: ops.get(2 + name_words..).unwrap_or(&[]).to_vec(),
+                    });
+                }
+                OP_EXECUTION_MODE if ops.len() > 1 => module.execution_modes.push((ops[0], ops[1])),
+                OP_DECORATE if ops.len() > 1 => {
+                    let literal = ops.get(2).cloned().unwrap_or(0);
+                    module.decorations.insert((ops[0], ops[1]), literal);
+                }
+                OP_MEMBER_DECORATE if ops.len() > 2 => {
+                    let literal = ops.get(3).cloned().unwrap_or(0);
+                    module
+                        .member_decorations
+                        .insert((ops[0], ops[1], ops[2]), literal);
+                }
+                OP_TYPE_VOID if !ops.is_empty() => {
+                    module.types.insert(ops[0], Type::Void);
+                }
+                OP_TYPE_BOOL if !ops.is_empty() => {
+                    module.types.insert(ops[0], Type::Bool);
+                }
+                OP_TYPE_INT if ops.len() > 2 => {
+                    module.types.insert(
+                        ops[0],
+                        Type::Int {
+                            width: ops[1],
+                            signed: ops[2] != 0,
+                        },
+                    );
+                }
+                OP_TYPE_FLOAT if ops.len() > 1 => {
+                    module.types.insert(ops[0], Type::Float { width: ops[1] });
+                }
+                OP_TYPE_VECTOR if ops.len() > 2 => {
+                    module.types.insert(
+                        ops[0],
+                        Type::Vector {
+                            component: ops[1],
+                            count: ops[2],
+                        },
+                    );
+                }
+                OP_TYPE_MATRIX if ops.len() > 2 => {
+                    module.types.insert(
+                        ops[0],
+                        Type::Matrix {
+                            column: ops[1],
+                            columns: ops[2],
+                        },
+                    );
+                }
+                // operands are the result, sampled type, dim, depth, arrayed,
+                // multisampled, sampled and format
+                OP_TYPE_IMAGE if ops.len() > 6 => {
+                    module.types.insert(
+                        ops[0],
+                        Type::Image {
+                            dim: ops[2],
+                            depth: ops[3] == 1,
+                            arrayed: ops[4] != 0,
+                            multisampled: ops[5] != 0,
+                            sampled: ops[6],
+                        },
+                    );
+                }
+                OP_TYPE_SAMPLER if !ops.is_empty() => {
+                    module.types.insert(ops[0], Type::Sampler);
+                }
+                OP_TYPE_SAMPLED_IMAGE if ops.len() > 1 => {
+                    module
+                        .types
+                        .insert(ops[0], Type::SampledImage { image: ops[1] });
+                }
+                OP_TYPE_ARRAY if ops.len() > 2 => {
+                    // the length is the id of a constant, which comes before
+                    // the array type
+                    let length = module.constants.get(&ops[2]).cloned();
+                    module.types.insert(
+                        ops[0],
+                        Type::Array {
+                            element: ops[1],
+                            length,
+                        },
+                    );
+                }
+                OP_TYPE_RUNTIME_ARRAY if ops.len() > 1 => {
+                    module.types.insert(
+                        ops[0],
+                        Type::Array {
+                            element: ops[1],
+                            length: None,
+                        },
+                    );
+                }
+                OP_TYPE_STRUCT if !ops.is_empty() => {
+                    module.types.insert(
+                        ops[0],
+                        Type::Struct {
+                            members: ops[1..].to_vec(),
+                        },
+                    );
+                }
+                OP_TYPE_POINTER if ops.len() > 2 => {
+                    module
+                        .types
+                        .insert(ops[0], Type::Pointer { pointee: ops[2] });
+                }
+                // operands are the result type, result id and value. spec
+                // constants are taken at their default value.
+                OP_CONSTANT | OP_SPEC_CONSTANT if ops.len() > 2 => {
+                    module.constants.insert(ops[1], ops[2]);
+                }
+                OP_VARIABLE if ops.len() > 2 => {
+                    let pointee = match module.types.get(&ops[0]) {
+                        Some(Type::Pointer { pointee }) => *pointee,
+                        _ => continue,
+                    };
+                    module.variables.push(Variable {
+                        id: ops[1],
+                        storage: ops[2],
+                        pointee,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        module
+    }
+
+    fn decoration(&self, id: u32, decoration: u32) -> Option<u32> {
+        self.decorations.get(&(id, decoration)).cloned()
+    }
+
+    fn member_decoration(&self, ty: u32, member: u32, decoration: u32) -> Option<u32> {
+        self.member_decorations
+            .get(&(ty, member, decoration))
+            .cloned()
+    }
+
+    fn interface_variable(&self, var: &Variable, arrayed: bool) -> Option<InterfaceVariable> {
+        // built-ins like gl_Position don't have a location and aren't part of
+        // the interface vulkano checks
+        if self.decoration(var.id, DECORATION_BUILT_IN).is_some() {
+            return None;
+        }
+
+        let mut ty = var.pointee;
+        // patch variables are per-patch rather than per-vertex, so they never
+        // have the extra array
+        if arrayed && self.decoration(var.id, DECORATION_PATCH).is_none() {
+            if let Some(Type::Array { element, .. }) = self.types.get(&ty) {
+                ty = *element;
+            }
+        }
+
+        let name = self.names.get(&var.id).cloned();
+        let mut entries = vec![];
+
+        match self.types.get(&ty) {
+            // interface blocks: each member gets its own location(s), either
+            // explicitly or following the previous member
+            Some(Type::Struct { members }) => {
+                // blocks made only of built-ins (gl_PerVertex) are skipped
+                if self.member_decoration(ty, 0, DECORATION_BUILT_IN).is_some() {
+                    return None;
+                }
+
+                let mut location = self.decoration(var.id, DECORATION_LOCATION).unwrap_or(0);
+                for (idx, &member) in members.iter().enumerate() {
+                    if let Some(explicit) = self.member_decoration(ty, idx as u32, DECORATION_LOCATION) {
+                        location = explicit;
+                    }
+                    let member_name = self.member_names.get(&(ty, idx as u32)).cloned();
+                    let entry = self.interface_entry(member, location, member_name)?;
+                    location = entry.location.end;
+                    entries.push(entry);
+                }
+            }
+            _ => {
+                let location = self.decoration(var.id, DECORATION_LOCATION)?;
+                entries.push(self.interface_entry(ty, location, name.clone())?);
+            }
+        }
+
+        Some(InterfaceVariable {
+            name,
+            type_name: self.type_name(ty),
+            entries,
+        })
+    }
+
+    // matrices and arrays take up several locations, but are described by a
+    // single entry with the format of one of their parts, the same way
+    // vulkano-shaders does it
+    fn interface_entry(
+        &self,
+        ty: u32,
+        location: u32,
+        name: Option<String>,
+    ) -> Option<ShaderInterfaceDefEntry> {
+        let locations = self.locations(ty)?;
+        Some(ShaderInterfaceDefEntry {
+            location: location..location + locations,
+            format: self.format(self.innermost(ty))?,
+            name: name.map(Cow::Owned),
+        })
+    }
+
+    fn locations(&self, ty: u32) -> Option<u32> {
+        Some(match self.types.get(&ty)? {
+            // 64-bit vectors with more than 2 components take up 2 locations
+            Type::Vector { component, count } => match self.types.get(component)? {
+                Type::Float { width: 64 } | Type::Int { width: 64, .. } if *count > 2 => 2,
+                _ => 1,
+            },
+            Type::Matrix { column, columns } => self.locations(*column)? * columns,
+            Type::Array { element, length } => self.locations(*element)? * (*length)?,
+            Type::Struct { members } => members
+                .iter()
+                .map(|&member| self.locations(member))
+                .sum::<Option<u32>>()?,
+            _ => 1,
+        })
+    }
+
+    // strips arrays and matrices down to a scalar or vector
+    fn innermost(&self, ty: u32) -> u32 {
+        match self.types.get(&ty) {
+            Some(Type::Array { element, .. }) => self.innermost(*element),
+            Some(Type::Matrix { column, .. }) => *column,
+            _ => ty,
+        }
+    }
+
+    fn format(&self, ty: u32) -> Option<Format> {
+        let (component, count) = match self.types.get(&ty)? {
+            Type::Vector { component, count } => (self.types.get(component)?, *count),
+            scalar => (scalar, 1),
+        };
+
+        use Format::*;
+        let formats = match component {
+            Type::Float { width: 32 } => [R32Sfloat, R32G32Sfloat, R32G32B32Sfloat, R32G32B32A32Sfloat],
+            Type::Float { width: 64 } => [R64Sfloat, R64G64Sfloat, R64G64B64Sfloat, R64G64B64A64Sfloat],
+            Type::Int { width: 32, signed: true } => [R32Sint, R32G32Sint, R32G32B32Sint, R32G32B32A32Sint],
+            Type::Int { width: 32, signed: false } => [R32Uint, R32G32Uint, R32G32B32Uint, R32G32B32A32Uint],
+            _ => return None,
+        };
+
+        formats.get(count.checked_sub(1)? as usize).cloned()
+    }
+
+    // returns None for variables that aren't descriptors
+    fn descriptor_binding(
+        &self,
+        var: &Variable,
+        stages: ShaderStages,
+    ) -> Option<Result<DescriptorBinding, String>> {
+        match var.storage {
+            STORAGE_UNIFORM_CONSTANT | STORAGE_UNIFORM | STORAGE_STORAGE_BUFFER => {}
+            _ => return None,
+        }
+
+        let set = self.decoration(var.id, DECORATION_DESCRIPTOR_SET)?;
+        let binding = self.decoration(var.id, DECORATION_BINDING)?;
+        let name = self.names.get(&var.id).cloned();
+
+        // arrays of descriptors
+        let mut ty = var.pointee;
+        let mut array_count = 1;
+        while let Some(Type::Array { element, length }) = self.types.get(&ty) {
+            array_count *= match length {
+                Some(length) => *length,
+                None => {
+                    return Some(Err(format!(
+                        "set {} binding {} is a runtime-sized array of descriptors, which isn't supported",
+                        set, binding
+                    )))
+                }
+            };
+            ty = *element;
+        }
+
+        let storage_block = var.storage == STORAGE_STORAGE_BUFFER
+            || self.decoration(ty, DECORATION_BUFFER_BLOCK).is_some();
+
+        let (desc_ty, readonly) = match self.types.get(&ty)? {
+            // glslang puts readonly on each member of the block rather than
+            // on the variable
+            Type::Struct { members } if storage_block => (
+                DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                    dynamic: Some(false),
+                    storage: true,
+                }),
+                self.decoration(var.id, DECORATION_NON_WRITABLE).is_some()
+                    || (0..members.len() as u32).all(|member| {
+                        self.member_decoration(ty, member, DECORATION_NON_WRITABLE)
+                            .is_some()
+                    }),
+            ),
+            Type::Struct { .. } if self.decoration(ty, DECORATION_BLOCK).is_some() => (
+                DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                    dynamic: Some(false),
+                    storage: false,
+                }),
+                true,
+            ),
+            Type::Sampler => (DescriptorDescTy::Sampler, true),
+            Type::SampledImage { image } => match self.types.get(image)? {
+                Type::Image { dim: DIM_BUFFER, .. } => (
+                    DescriptorDescTy::TexelBuffer {
+                        storage: false,
+                        format: None,
+                    },
+                    true,
+                ),
+                image_ty => (
+                    DescriptorDescTy::CombinedImageSampler(image_desc(image_ty)?),
+                    true,
+                ),
+            },
+            Type::Image {
+                dim: DIM_SUBPASS_DATA,
+                arrayed,
+                multisampled,
+                ..
+            } => (
+                DescriptorDescTy::InputAttachment {
+                    multisampled: *multisampled,
+                    array_layers: if *arrayed {
+                        DescriptorImageDescArray::Arrayed { max_layers: None }
+                    } else {
+                        DescriptorImageDescArray::NonArrayed
+                    },
+                },
+                true,
+            ),
+            Type::Image {
+                dim: DIM_BUFFER,
+                sampled,
+                ..
+            } => (
+                DescriptorDescTy::TexelBuffer {
+                    storage: *sampled == 2,
+                    format: None,
+                },
+                *sampled != 2,
+            ),
+            image @ Type::Image { .. } => {
+                let desc = image_desc(image)?;
+                let readonly = desc.sampled;
+                (DescriptorDescTy::Image(desc), readonly)
+            }
+            _ => return None,
+        };
+
+        let block_size = match self.types.get(&ty) {
+            Some(Type::Struct { .. }) => self.size_of(ty),
+            _ => None,
+        };
+        let compare = match self.types.get(&ty) {
+            Some(Type::SampledImage { image }) => {
+                matches!(self.types.get(image), Some(Type::Image { depth: true, .. }))
+            }
+            _ => false,
+        };
+
+        Some(Ok(DescriptorBinding {
+            name,
+            type_name: self.type_name(var.pointee),
+            set,
+            binding,
+            desc: DescriptorDesc {
+                ty: desc_ty,
+                array_count,
+                stages,
+                readonly,
+            },
+            block_size,
+            compare,
+        }))
+    }
+
+    // the size of a type as laid out in a buffer, following the Offset,
+    // ArrayStride and MatrixStride decorations the compiler adds
+    fn size_of(&self, ty: u32) -> Option<u32> {
+        Some(match self.types.get(&ty)? {
+            Type::Bool => 4,
+            Type::Int { width, .. } | Type::Float { width } => width / 8,
+            Type::Vector { component, count } => self.size_of(*component)? * count,
+            Type::Matrix { column, columns } => self.size_of(*column)? * columns,
+            Type::Array { length: None, .. } => return None,
+            Type::Array { element, length: Some(length) } => {
+                let stride = match self.decoration(ty, DECORATION_ARRAY_STRIDE) {
+                    Some(stride) => stride,
+                    None => self.size_of(*element)?,
+                };
+                stride * length
+            }
+            Type::Struct { members } => {
+                let mut size = 0;
+                for (idx, &member) in members.iter().enumerate() {
+                    let offset = self.member_decoration(ty, idx as u32, DECORATION_OFFSET)?;
+                    // matrices in blocks have their column stride on the member
+                    let member_size = match (
+                        self.types.get(&member),
+                        self.member_decoration(ty, idx as u32, DECORATION_MATRIX_STRIDE),
+                    ) {
+                        (Some(Type::Matrix { columns, .. }), Some(stride)) => stride * columns,
+                        _ => self.size_of(member)?,
+                    };
+                    size = size.max(offset + member_size);
+                }
+                size
+            }
+            _ => return None,
+        })
+    }
+
+    // a GLSL-style name, for printing
+    fn type_name(&self, ty: u32) -> String {
+        let ty_ref = match self.types.get(&ty) {
+            Some(ty) => ty,
+            None => return "?".to_string(),
+        };
+
+        match ty_ref {
+            Type::Void => "void".to_string(),
+            Type::Bool => "bool".to_string(),
+            Type::Int { width: 32, signed: true } => "int".to_string(),
+            Type::Int { width: 32, signed: false } => "uint".to_string(),
+            Type::Int { width, signed } => format!("{}int{}_t", if *signed { "" } else { "u" }, width),
+            Type::Float { width: 32 } => "float".to_string(),
+            Type::Float { width: 64 } => "double".to_string(),
+            Type::Float { width } => format!("float{}_t", width),
+            Type::Vector { component, count } => {
+                let prefix = match self.types.get(component) {
+                    Some(Type::Bool) => "b",
+                    Some(Type::Int { signed: true, .. }) => "i",
+                    Some(Type::Int { signed: false, .. }) => "u",
+                    Some(Type::Float { width: 64 }) => "d",
+                    _ => "",
+                };
+                format!("{}vec{}", prefix, count)
+            }
+            Type::Matrix { column, columns } => {
+                let rows = match self.types.get(column) {
+                    Some(Type::Vector { count, .. }) => *count,
+                    _ => 0,
+                };
+                if rows == *columns {
+                    format!("mat{}", columns)
+                } else {
+                    format!("mat{}x{}", columns, rows)
+                }
+            }
+            Type::Image {
+                dim,
+                arrayed,
+                multisampled,
+                sampled,
+                ..
+            } => format!(
+                "{}{}",
+                if *sampled == 2 { "image" } else { "texture" },
+                image_suffix(*dim, *arrayed, *multisampled)
+            ),
+            Type::Sampler => "sampler".to_string(),
+            Type::SampledImage { image } => match self.types.get(image) {
+                Some(Type::Image {
+                    dim,
+                    depth,
+                    arrayed,
+                    multisampled,
+                    ..
+                }) => format!(
+                    "sampler{}{}",
+                    image_suffix(*dim, *arrayed, *multisampled),
+                    if *depth { "Shadow" } else { "" }
+                ),
+                _ => "sampler?".to_string(),
+            },
+            Type::Array { element, length } => match length {
+                Some(length) => format!("{}[{}]", self.type_name(*element), length),
+                None => format!("{}[]", self.type_name(*element)),
+            },
+            Type::Struct { .. } => self
+                .names
+                .get(&ty)
+                .cloned()
+                .unwrap_or_else(|| "struct".to_string()),
+            Type::Pointer { pointee } => self.type_name(*pointee),
+        }
+    }
+}
+
+fn image_desc(image: &Type) -> Option<DescriptorImageDesc> {
+    match image {
+        Type::Image {
+            dim,
+            arrayed,
+            multisampled,
+            sampled,
+            ..
+        } => Some(DescriptorImageDesc {
+            sampled: *sampled != 2,
+            dimensions: match *dim {
+                DIM_1D => DescriptorImageDescDimensions::OneDimensional,
+                DIM_3D => DescriptorImageDescDimensions::ThreeDimensional,
+                DIM_CUBE => DescriptorImageDescDimensions::Cube,
+                // 2D and Rect
+                _ => DescriptorImageDescDimensions::TwoDimensional,
+            },
+            format: None,
+            multisampled: *multisampled,
+            array_layers: if *arrayed {
+                DescriptorImageDescArray::Arrayed { max_layers: None }
+            } else {
+                DescriptorImageDescArray::NonArrayed
+            },
+        }),
+        _ => None,
+    }
+}
+
+fn image_suffix(dim: u32, arrayed: bool, multisampled: bool) -> String {
+    let dim = match dim {
+        DIM_1D => "1D",
+        DIM_3D => "3D",
+        DIM_CUBE => "Cube",
+        4 => "2DRect",
+        DIM_BUFFER => "Buffer",
+        DIM_SUBPASS_DATA => "SubpassInput",
+        _ => "2D",
+    };
+
+    format!(
+        "{}{}{}",
+        dim,
+        if multisampled { "MS" } else { "" },
+        if arrayed { "Array" } else { "" }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // just enough of an assembler to build the modules below. the GLSL each
+    // one stands for is above its test, ids are numbered by hand.
+    struct Assembler {
+        words: Vec<u32>,
+    }
+
+    impl Assembler {
+        fn new() -> Self {
+            // magic, version 1.0, generator, id bound, schema
+            Self {
+                words: vec![0x0723_0203, 0x0001_0000, 0, 200, 0],
+            }
+        }
+
+        fn op(&mut self, opcode: u16, operands: &[u32]) -> &mut Self {
+            self.words
+                .push(((operands.len() as u32 + 1) << 16) | u32::from(opcode));
+            self.words.extend_from_slice(operands);
+            self
+        }
+
+        fn entry_point(&mut self, model: u32, name: &str, interface: &[u32]) -> &mut Self {
+            let mut operands = vec![model, 100];
+            operands.extend(string(name));
+            operands.extend_from_slice(interface);
+            self.op(OP_ENTRY_POINT, &operands)
+        }
+
+        fn name(&mut self, id: u32, name: &str) -> &mut Self {
+            let mut operands = vec![id];
+            operands.extend(string(name));
+            self.op(OP_NAME, &operands)
+        }
+
+        fn decorate(&mut self, id: u32, decoration: u32, literal: Option<u32>) -> &mut Self {
+            let mut operands = vec![id, decoration];
+            operands.extend(literal);
+            self.op(OP_DECORATE, &operands)
+        }
+
+        fn member_decorate(
+            &mut self,
+            ty: u32,
+            member: u32,
+            decoration: u32,
+            literal: u32,
+        ) -> &mut Self {
+            self.op(OP_MEMBER_DECORATE, &[ty, member, decoration, literal])
+        }
+
+        fn reflect(&self, entry_name: &str) -> Reflection {
+            Reflection::new(&self.words, entry_name).unwrap()
+        }
+    }
+
+    // nul-terminated and padded to whole words
+    fn string(s: &str) -> Vec<u32> {
+        let mut bytes = s.as_bytes().to_vec();
+        bytes.push(0);
+        while bytes.len() % 4 != 0 {
+            bytes.push(0);
+        }
+
+        bytes
+            .chunks(4)
+            .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect()
+    }
+
+    fn only(stage: fn(&mut ShaderStages)) -> ShaderStages {
+        let mut stages = ShaderStages::none();
+        stage(&mut stages);
+        stages
+    }
+
+    // layout(location = 0) in vec3 position;
+    // layout(location = 1) in vec2 tex_coord;
+    // layout(location = 0) out vec2 v_tex_coord;
+    // out gl_PerVertex { vec4 gl_Position; };
+    // layout(set = 0, binding = 0) uniform Camera { mat4 view; mat4 proj; } camera;
+    // layout(push_constant) uniform Model { mat4 model; } model;
+    #[test]
+    fn vertex_shader() {
+        let mut asm = Assembler::new();
+        asm.entry_point(MODEL_VERTEX, "main", &[9, 10, 11, 14])
+            .name(9, "position")
+            .name(10, "tex_coord")
+            .name(11, "v_tex_coord")
+            .name(15, "Camera")
+            .name(17, "camera")
+            .decorate(9, DECORATION_LOCATION, Some(0))
+            .decorate(10, DECORATION_LOCATION, Some(1))
+            .decorate(11, DECORATION_LOCATION, Some(0))
+            .member_decorate(12, 0, DECORATION_BUILT_IN, 0)
+            .decorate(12, DECORATION_BLOCK, None)
+            .decorate(15, DECORATION_BLOCK, None)
+            .member_decorate(15, 0, DECORATION_OFFSET, 0)
+            .member_decorate(15, 0, DECORATION_MATRIX_STRIDE, 16)
+            .member_decorate(15, 1, DECORATION_OFFSET, 64)
+            .member_decorate(15, 1, DECORATION_MATRIX_STRIDE, 16)
+            .decorate(17, DECORATION_DESCRIPTOR_SET, Some(0))
+            .decorate(17, DECORATION_BINDING, Some(0))
+            .decorate(18, DECORATION_BLOCK, None)
+            .member_decorate(18, 0, DECORATION_OFFSET, 0)
+            .member_decorate(18, 0, DECORATION_MATRIX_STRIDE, 16)
+            .op(OP_TYPE_FLOAT, &[1, 32])
+            .op(OP_TYPE_VECTOR, &[2, 1, 3])
+            .op(OP_TYPE_VECTOR, &[3, 1, 2])
+            .op(OP_TYPE_VECTOR, &[4, 1, 4])
+            .op(OP_TYPE_MATRIX, &[5, 4, 4])
+            .op(OP_TYPE_POINTER, &[6, STORAGE_INPUT, 2])
+            .op(OP_TYPE_POINTER, &[7, STORAGE_INPUT, 3])
+            .op(OP_TYPE_POINTER, &[8, STORAGE_OUTPUT, 3])
+            .op(OP_VARIABLE, &[6, 9, STORAGE_INPUT])
+            .op(OP_VARIABLE, &[7, 10, STORAGE_INPUT])
+            .op(OP_VARIABLE, &[8, 11, STORAGE_OUTPUT])
+            .op(OP_TYPE_STRUCT, &[12, 4])
+            .op(OP_TYPE_POINTER, &[13, STORAGE_OUTPUT, 12])
+            .op(OP_VARIABLE, &[13, 14, STORAGE_OUTPUT])
+            .op(OP_TYPE_STRUCT, &[15, 5, 5])
+            .op(OP_TYPE_POINTER, &[16, STORAGE_UNIFORM, 15])
+            .op(OP_VARIABLE, &[16, 17, STORAGE_UNIFORM])
+            .op(OP_TYPE_STRUCT, &[18, 5])
+            .op(OP_TYPE_POINTER, &[19, STORAGE_PUSH_CONSTANT, 18])
+            .op(OP_VARIABLE, &[19, 20, STORAGE_PUSH_CONSTANT]);
+        let reflection = asm.reflect("main");
+
+        assert_eq!(reflection.stages, only(|stages| stages.vertex = true));
+        assert!(Reflection::new(&asm.words, "other").is_err());
+
+        let inputs: Vec<_> = reflection
+            .inputs
+            .iter()
+            .map(|var| {
+                (
+                    var.name.as_deref(),
+                    var.entries[0].location.clone(),
+                    var.entries[0].format,
+                )
+            })
+            .collect();
+        assert_eq!(
+            inputs,
+            vec![
+                (Some("position"), 0..1, Format::R32G32B32Sfloat),
+                (Some("tex_coord"), 1..2, Format::R32G32Sfloat),
+            ]
+        );
+        // gl_PerVertex isn't part of the interface
+        assert_eq!(reflection.outputs.len(), 1);
+        assert_eq!(reflection.outputs[0].entries[0].location, 0..1);
+
+        assert_eq!(reflection.descriptors.len(), 1);
+        let camera = &reflection.descriptors[0];
+        assert_eq!((camera.set, camera.binding), (0, 0));
+        assert_eq!(camera.type_name, "Camera");
+        assert_eq!(camera.block_size, Some(128));
+        assert!(matches!(
+            camera.desc.ty,
+            DescriptorDescTy::Buffer(DescriptorBufferDesc { storage: false, .. })
+        ));
+
+        assert_eq!(reflection.push_constants_size, Some(64));
+        let layout = reflection.layout();
+        assert_eq!(layout.num_sets(), 1);
+        assert_eq!(layout.push_constants_range(0).unwrap().size, 64);
+        assert_eq!(reflection.geometry_input, None);
+    }
+
+    // layout(vertices = 3) out;
+    // layout(location = 0) in vec3 position[];
+    // layout(location = 0) out vec3 tc_position[];
+    // layout(location = 1) patch out float level;
+    #[test]
+    fn tessellation_control_shader() {
+        let mut asm = Assembler::new();
+        asm.entry_point(MODEL_TESS_CONTROL, "main", &[11, 12, 13])
+            .decorate(11, DECORATION_LOCATION, Some(0))
+            .decorate(12, DECORATION_LOCATION, Some(0))
+            .decorate(13, DECORATION_LOCATION, Some(1))
+            .decorate(13, DECORATION_PATCH, None)
+            .op(OP_TYPE_FLOAT, &[1, 32])
+            .op(OP_TYPE_VECTOR, &[2, 1, 3])
+            .op(OP_TYPE_INT, &[3, 32, 0])
+            .op(OP_CONSTANT, &[3, 4, 32])
+            .op(OP_CONSTANT, &[3, 5, 3])
+            .op(OP_TYPE_ARRAY, &[6, 2, 4])
+            .op(OP_TYPE_ARRAY, &[7, 2, 5])
+            .op(OP_TYPE_POINTER, &[8, STORAGE_INPUT, 6])
+            .op(OP_TYPE_POINTER, &[9, STORAGE_OUTPUT, 7])
+            .op(OP_TYPE_POINTER, &[10, STORAGE_OUTPUT, 1])
+            .op(OP_VARIABLE, &[8, 11, STORAGE_INPUT])
+            .op(OP_VARIABLE, &[9, 12, STORAGE_OUTPUT])
+            .op(OP_VARIABLE, &[10, 13, STORAGE_OUTPUT]);
+        let reflection = asm.reflect("main");
+
+        assert_eq!(
+            reflection.stages,
+            only(|stages| stages.tessellation_control = true)
+        );
+        // the per-vertex arrays are left out, the patch output isn't one
+        let entries: Vec<_> = reflection
+            .inputs
+            .iter()
+            .chain(reflection.outputs.iter())
+            .map(|var| {
+                (
+                    var.type_name.as_str(),
+                    var.entries[0].location.clone(),
+                    var.entries[0].format,
+                )
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                ("vec3", 0..1, Format::R32G32B32Sfloat),
+                ("vec3", 0..1, Format::R32G32B32Sfloat),
+                ("float", 1..2, Format::R32Sfloat),
+            ]
+        );
+        assert!(reflection.descriptors.is_empty());
+        assert_eq!(reflection.push_constants_size, None);
+    }
+
+    // layout(triangles) in;
+    // layout(triangle_strip, max_vertices = 3) out;
+    // layout(location = 0) in vec3 v_normal[];
+    // layout(location = 0) out vec3 g_normal;
+    // layout(set = 1, binding = 2) uniform samplerCubeShadow shadow_map;
+    // layout(set = 1, binding = 0) readonly buffer Lights { uint count; vec3 positions[]; } lights;
+    // layout(push_constant) uniform Face { uint index; vec4 color; } face;
+    #[test]
+    fn geometry_shader() {
+        let mut asm = Assembler::new();
+        asm.entry_point(MODEL_GEOMETRY, "main", &[8, 9])
+            .op(OP_EXECUTION_MODE, &[100, MODE_TRIANGLES])
+            // OutputTriangleStrip and OutputVertices
+            .op(OP_EXECUTION_MODE, &[100, 29])
+            .op(OP_EXECUTION_MODE, &[100, 26, 3])
+            .name(15, "Lights")
+            .decorate(8, DECORATION_LOCATION, Some(0))
+            .decorate(9, DECORATION_LOCATION, Some(0))
+            .decorate(13, DECORATION_DESCRIPTOR_SET, Some(1))
+            .decorate(13, DECORATION_BINDING, Some(2))
+            .decorate(14, DECORATION_ARRAY_STRIDE, Some(16))
+            .decorate(15, DECORATION_BUFFER_BLOCK, None)
+            .member_decorate(15, 0, DECORATION_OFFSET, 0)
+            .member_decorate(15, 0, DECORATION_NON_WRITABLE, 0)
+            .member_decorate(15, 1, DECORATION_OFFSET, 16)
+            .member_decorate(15, 1, DECORATION_NON_WRITABLE, 0)
+            .decorate(17, DECORATION_DESCRIPTOR_SET, Some(1))
+            .decorate(17, DECORATION_BINDING, Some(0))
+            .decorate(18, DECORATION_BLOCK, None)
+            .member_decorate(18, 0, DECORATION_OFFSET, 0)
+            .member_decorate(18, 1, DECORATION_OFFSET, 16)
+            .op(OP_TYPE_FLOAT, &[1, 32])
+            .op(OP_TYPE_VECTOR, &[2, 1, 3])
+            .op(OP_TYPE_INT, &[3, 32, 0])
+            .op(OP_CONSTANT, &[3, 4, 3])
+            .op(OP_TYPE_ARRAY, &[5, 2, 4])
+            .op(OP_TYPE_POINTER, &[6, STORAGE_INPUT, 5])
+            .op(OP_TYPE_POINTER, &[7, STORAGE_OUTPUT, 2])
+            .op(OP_VARIABLE, &[6, 8, STORAGE_INPUT])
+            .op(OP_VARIABLE, &[7, 9, STORAGE_OUTPUT])
+            // sampled float, cube, depth, not arrayed or multisampled,
+            // sampled, unknown format
+            .op(OP_TYPE_IMAGE, &[10, 1, DIM_CUBE, 1, 0, 0, 1, 0])
+            .op(OP_TYPE_SAMPLED_IMAGE, &[11, 10])
+            .op(OP_TYPE_POINTER, &[12, STORAGE_UNIFORM_CONSTANT, 11])
+            .op(OP_VARIABLE, &[12, 13, STORAGE_UNIFORM_CONSTANT])
+            .op(OP_TYPE_RUNTIME_ARRAY, &[14, 2])
+            .op(OP_TYPE_STRUCT, &[15, 3, 14])
+            .op(OP_TYPE_POINTER, &[16, STORAGE_UNIFORM, 15])
+            .op(OP_VARIABLE, &[16, 17, STORAGE_UNIFORM])
+            .op(OP_TYPE_VECTOR, &[19, 1, 4])
+            .op(OP_TYPE_STRUCT, &[18, 3, 19])
+            .op(OP_TYPE_POINTER, &[20, STORAGE_PUSH_CONSTANT, 18])
+            .op(OP_VARIABLE, &[20, 21, STORAGE_PUSH_CONSTANT]);
+        let reflection = asm.reflect("main");
+
+        assert_eq!(reflection.stages, only(|stages| stages.geometry = true));
+        assert_eq!(
+            reflection.geometry_input,
+            Some(GeometryShaderExecutionMode::Triangles)
+        );
+        assert_eq!(reflection.inputs[0].entries[0].location, 0..1);
+        assert_eq!(reflection.inputs[0].type_name, "vec3");
+        assert_eq!(reflection.outputs[0].entries[0].location, 0..1);
+
+        let binding = |binding: u32| {
+            reflection
+                .descriptors
+                .iter()
+                .find(|descriptor| descriptor.set == 1 && descriptor.binding == binding)
+                .unwrap()
+        };
+        let shadow_map = binding(2);
+        assert_eq!(shadow_map.type_name, "samplerCubeShadow");
+        assert!(shadow_map.compare);
+        assert!(matches!(
+            shadow_map.desc.ty,
+            DescriptorDescTy::CombinedImageSampler(DescriptorImageDesc {
+                dimensions: DescriptorImageDescDimensions::Cube,
+                ..
+            })
+        ));
+        let lights = binding(0);
+        assert_eq!(lights.type_name, "Lights");
+        // ends in a runtime-sized array
+        assert_eq!(lights.block_size, None);
+        assert!(lights.desc.readonly);
+        assert!(matches!(
+            lights.desc.ty,
+            DescriptorDescTy::Buffer(DescriptorBufferDesc { storage: true, .. })
+        ));
+        assert_eq!(reflection.layout().num_bindings_in_set(1), Some(3));
+
+        // the vec4 starts at 16, after the uint and its padding
+        assert_eq!(reflection.push_constants_size, Some(32));
+    }
+}
@@ -0,0 +1,33 @@
+// What building objects and uploading sets needs besides the data itself: the
+// queue, the pool uniform buffers come from and the samplers. Every System
+// creates one, and System::resources() hands it out. Nothing in it outlives
+// the system and the sets uploaded with it, so neither does the device.
+
+use vulkano::device::{Device, Queue};
+
+use crate::buffer_pool::UniformPool;
+use crate::sampler::SamplerCache;
+
+use std::sync::Arc;
+
+pub struct Resources {
+    pub queue: Arc<Queue>,
+    pub uniform_pool: UniformPool,
+    pub samplers: SamplerCache,
+}
+
+impl Resources {
+    pub fn new(queue: Arc<Queue>) -> Arc<Self> {
+        let device = queue.device().clone();
+
+        Arc::new(Self {
+            queue,
+            uniform_pool: UniformPool::new(device.clone()),
+            samplers: SamplerCache::new(device),
+        })
+    }
+
+    pub fn device(&self) -> &Arc<Device> {
+        self.queue.device()
+    }
+}
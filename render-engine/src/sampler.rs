@@ -0,0 +1,176 @@
+// Samplers described by a plain struct instead of Sampler::new's 11 positional
+// arguments. A SamplerCache, which every System has one of in its Resources,
+// only turns each SamplerDesc into a real sampler once: equal descriptions
+// share the same Arc<Sampler>, so it's fine to call get() every time a set is
+// uploaded.
+
+// The default is linear filtering, repeating, and blending between all the
+// mip levels the image has.
+
+use vulkano::device::Device;
+use vulkano::pipeline::depth_stencil::Compare;
+use vulkano::sampler::{BorderColor, Filter, MipmapMode, Sampler, SamplerAddressMode};
+
+use std::sync::{Arc, Mutex};
+
+pub struct SamplerCache {
+    device: Arc<Device>,
+    // SamplerDesc has floats in it, so it can't be hashed, but there are never
+    // more than a handful of samplers anyway
+    samplers: Mutex<Vec<(SamplerDesc, Arc<Sampler>)>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplerDesc {
+    pub mag_filter: Filter,
+    pub min_filter: Filter,
+    pub mipmap_mode: MipmapMode,
+    // u, v and w
+    pub address_mode: [AddressMode; 3],
+    // only used by AddressMode::ClampToBorder. vulkan only allows one border
+    // colour per sampler, which is why it isn't part of the address mode like
+    // in vulkano.
+    pub border_color: BorderColor,
+    // 1.0 disables anisotropic filtering. anything higher needs the
+    // samplerAnisotropy feature, which the device is created with if it's
+    // supported.
+    pub max_anisotropy: f32,
+    pub mip_lod_bias: f32,
+    pub min_lod: f32,
+    pub max_lod: f32,
+    // makes this a depth comparison sampler, for sampler2DShadow in GLSL. the
+    // image has to have a depth format.
+    pub compare: Option<Compare>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AddressMode {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+    ClampToBorder,
+    MirrorClampToEdge,
+}
+
+impl Default for SamplerDesc {
+    fn default() -> Self {
+        Self {
+            mag_filter: Filter::Linear,
+            min_filter: Filter::Linear,
+            mipmap_mode: MipmapMode::Linear,
+            address_mode: [AddressMode::Repeat; 3],
+            border_color: BorderColor::FloatTransparentBlack,
+            max_anisotropy: 1.0,
+            mip_lod_bias: 0.0,
+            min_lod: 0.0,
+            // no limit, the image's own number of levels is the limit
+            max_lod: 1000.0,
+            compare: None,
+        }
+    }
+}
+
+impl SamplerDesc {
+    // what pass images are sampled with by default
+    pub fn clamped() -> Self {
+        Self {
+            address_mode: [AddressMode::ClampToEdge; 3],
+            ..Self::default()
+        }
+    }
+
+    // no filtering at all, for pixel art
+    pub fn nearest() -> Self {
+        Self {
+            mag_filter: Filter::Nearest,
+            min_filter: Filter::Nearest,
+            ..Self::default()
+        }
+    }
+
+    // for shadow maps with hardware PCF. anything outside the shadow map
+    // counts as lit.
+    pub fn shadow() -> Self {
+        Self {
+            address_mode: [AddressMode::ClampToBorder; 3],
+            border_color: BorderColor::FloatOpaqueWhite,
+            compare: Some(Compare::LessOrEqual),
+            ..Self::default()
+        }
+    }
+
+    fn create(&self, device: Arc<Device>) -> Arc<Sampler> {
+        let [u, v, w] = self.address_mode;
+        let (u, v, w) = (
+            u.to_vulkano(self.border_color),
+            v.to_vulkano(self.border_color),
+            w.to_vulkano(self.border_color),
+        );
+
+        match self.compare {
+            Some(compare) => Sampler::compare(
+                device,
+                self.mag_filter,
+                self.min_filter,
+                self.mipmap_mode,
+                u,
+                v,
+                w,
+                self.mip_lod_bias,
+                self.max_anisotropy,
+                self.min_lod,
+                self.max_lod,
+                compare,
+            ),
+            None => Sampler::new(
+                device,
+                self.mag_filter,
+                self.min_filter,
+                self.mipmap_mode,
+                u,
+                v,
+                w,
+                self.mip_lod_bias,
+                self.max_anisotropy,
+                self.min_lod,
+                self.max_lod,
+            ),
+        }
+        .unwrap()
+    }
+}
+
+impl SamplerCache {
+    pub fn new(device: Arc<Device>) -> Self {
+        Self {
+            device,
+            samplers: Mutex::new(vec![]),
+        }
+    }
+
+    // the sampler for this description, created the first time it's asked for
+    pub fn get(&self, desc: &SamplerDesc) -> Arc<Sampler> {
+        let mut samplers = self.samplers.lock().unwrap();
+
+        if let Some((_, sampler)) = samplers.iter().find(|(existing, _)| existing == desc) {
+            return sampler.clone();
+        }
+
+        let sampler = desc.create(self.device.clone());
+        samplers.push((*desc, sampler.clone()));
+
+        sampler
+    }
+}
+
+impl AddressMode {
+    fn to_vulkano(self, border_color: BorderColor) -> SamplerAddressMode {
+        match self {
+            AddressMode::Repeat => SamplerAddressMode::Repeat,
+            AddressMode::MirroredRepeat => SamplerAddressMode::MirroredRepeat,
+            AddressMode::ClampToEdge => SamplerAddressMode::ClampToEdge,
+            AddressMode::ClampToBorder => SamplerAddressMode::ClampToBorder(border_color),
+            AddressMode::MirrorClampToEdge => SamplerAddressMode::MirrorClampToEdge,
+        }
+    }
+}
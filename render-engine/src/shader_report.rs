@@ -0,0 +1,429 @@
+// A readable summary of what a set of shaders expects: vertex attributes, the
+// varyings passed between stages, descriptor sets and push constants. Mostly
+// useful for finding out which set index something is at before writing the
+// CollectionData for it.
+
+use vulkano::descriptor::descriptor::{DescriptorDescTy, ShaderStages};
+
+use crate::reflection::{DescriptorBinding, InterfaceVariable, Reflection};
+use crate::shaders::{compile_to_spirv, ShaderError, ShaderErrorKind, ShaderOptions, ShaderStage};
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+pub struct ShaderReport {
+    // in the order the stages run
+    pub stages: Vec<StageReport>,
+}
+
+pub struct StageReport {
+    pub stage: ShaderStage,
+    pub path: PathBuf,
+    pub reflection: Reflection,
+}
+
+impl ShaderReport {
+    // compiles and reflects the shaders without needing a device. the
+    // geometry and tessellation shaders are taken from options, like in
+    // ShaderSystem::load_from_file.
+    pub fn from_files(
+        vs_path: &Path,
+        fs_path: &Path,
+        options: &ShaderOptions,
+    ) -> Result<Self, ShaderError> {
+        let mut paths = vec![(ShaderStage::Vertex, vs_path.to_path_buf())];
+        if let (Some(tcs_path), Some(tes_path)) = (&options.tcs_path, &options.tes_path) {
+            paths.push((ShaderStage::TessControl, tcs_path.clone()));
+            paths.push((ShaderStage::TessEvaluation, tes_path.clone()));
+        }
+        if let Some(gs_path) = &options.gs_path {
+            paths.push((ShaderStage::Geometry, gs_path.clone()));
+        }
+        paths.push((ShaderStage::Fragment, fs_path.to_path_buf()));
+
+        let stages = paths
+            .into_iter()
+            .map(|(stage, path)| {
+                let entry_name = options.entry_name(stage);
+                let words = compile_to_spirv(&path, stage, entry_name, options)?;
+                let reflection = Reflection::new(&words, entry_name).map_err(|e| ShaderError {
+                    path: path.clone(),
+                    stage: Some(stage),
+                    kind: ShaderErrorKind::Reflection(e),
+                })?;
+
+                Ok(StageReport {
+                    stage,
+                    path,
+                    reflection,
+                })
+            })
+            .collect::<Result<Vec<_>, ShaderError>>()?;
+
+        Ok(Self { stages })
+    }
+
+    // every descriptor used by any stage, sorted by set and binding, along
+    // with the stages that use it
+    pub fn descriptors(&self) -> Vec<(&DescriptorBinding, ShaderStages)> {
+        let mut descriptors: Vec<(&DescriptorBinding, ShaderStages)> = vec![];
+
+        for stage in self.stages.iter() {
+            for descriptor in stage.reflection.descriptors.iter() {
+                match descriptors.iter_mut().find(|(existing, _)| {
+                    existing.set == descriptor.set && existing.binding == descriptor.binding
+                }) {
+                    Some((_, stages)) => *stages = *stages | descriptor.desc.stages,
+                    None => descriptors.push((descriptor, descriptor.desc.stages)),
+                }
+            }
+        }
+
+        descriptors.sort_by_key(|(descriptor, _)| (descriptor.set, descriptor.binding));
+        descriptors
+    }
+
+    pub fn to_json(&self) -> String {
+        let stages: Vec<String> = self
+            .stages
+            .iter()
+            .map(|stage| {
+                let reflection = &stage.reflection;
+                format!(
+                    "{{\"stage\":{},\"path\":{},\"entry_point\":{},\"inputs\":{},\"outputs\":{},\"push_constants_size\":{},\"spec_constants\":[{}]}}",
+                    json_string(&stage.stage.to_string()),
+                    json_string(&stage.path.to_string_lossy()),
+                    json_string(&reflection.entry_name),
+                    json_variables(&reflection.inputs),
+                    json_variables(&reflection.outputs),
+                    reflection
+                        .push_constants_size
+                        .map_or("null".to_string(), |size| size.to_string()),
+                    reflection
+                        .spec_constants
+                        .iter()
+                        .map(|spec| format!("{{\"id\":{},\"default\":{}}}", spec.constant_id, spec.default))
+                        .collect::<Vec<_>>()
+                        .join(","),
+                )
+            })
+            .collect();
+
+        let descriptors: Vec<String> = self
+            .descriptors()
+            .iter()
+            .map(|(descriptor, stages)| {
+                format!(
+                    "{{\"set\":{},\"binding\":{},\"name\":{},\"type\":{},\"glsl_type\":{},\"array_count\":{},\"block_size\":{},\"stages\":[{}]}}",
+                    descriptor.set,
+                    descriptor.binding,
+                    descriptor
+                        .name
+                        .as_ref()
+                        .map_or("null".to_string(), |name| json_string(name)),
+                    json_string(descriptor_kind(&descriptor.desc.ty)),
+                    json_string(&descriptor.type_name),
+                    descriptor.desc.array_count,
+                    descriptor
+                        .block_size
+                        .map_or("null".to_string(), |size| size.to_string()),
+                    stage_names(*stages)
+                        .iter()
+                        .map(|name| json_string(name))
+                        .collect::<Vec<_>>()
+                        .join(","),
+                )
+            })
+            .collect();
+
+        format!(
+            "{{\"stages\":[{}],\"descriptors\":[{}]}}",
+            stages.join(","),
+            descriptors.join(",")
+        )
+    }
+}
+
+impl fmt::Display for ShaderReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for stage in self.stages.iter() {
+            let reflection = &stage.reflection;
+            writeln!(
+                f,
+                "{} shader {:?}, entry point {}",
+                stage.stage, stage.path, reflection.entry_name
+            )?;
+
+            // a vertex shader's inputs are the vertex attributes, everything
+            // else is a varying
+            let input_title = if stage.stage == ShaderStage::Vertex {
+                "vertex attributes"
+            } else {
+                "inputs"
+            };
+            write_variables(f, input_title, &reflection.inputs)?;
+            write_variables(f, "outputs", &reflection.outputs)?;
+
+            if let Some(size) = reflection.push_constants_size {
+                writeln!(f, "  push constants: {} bytes", size)?;
+            }
+
+            if !reflection.spec_constants.is_empty() {
+                writeln!(f, "  specialization constants:")?;
+                for spec in reflection.spec_constants.iter() {
+                    writeln!(f, "    id {}, default {}", spec.constant_id, spec.default)?;
+                }
+            }
+
+            writeln!(f)?;
+        }
+
+        let descriptors = self.descriptors();
+        if descriptors.is_empty() {
+            return writeln!(f, "no descriptor sets");
+        }
+
+        writeln!(f, "descriptor sets:")?;
+        let mut last_set = None;
+        for (descriptor, stages) in descriptors {
+            if last_set != Some(descriptor.set) {
+                writeln!(f, "  set {}:", descriptor.set)?;
+                last_set = Some(descriptor.set);
+            }
+
+            write!(
+                f,
+                "    binding {}: {} {}",
+                descriptor.binding,
+                descriptor_kind(&descriptor.desc.ty),
+                descriptor.type_name
+            )?;
+            if let Some(name) = &descriptor.name {
+                write!(f, " {}", name)?;
+            }
+            if descriptor.desc.array_count != 1 {
+                write!(f, " (array of {})", descriptor.desc.array_count)?;
+            }
+            if let Some(size) = descriptor.block_size {
+                write!(f, " ({} bytes)", size)?;
+            }
+            writeln!(f, " [{}]", stage_names(stages).join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn write_variables(f: &mut fmt::Formatter, title: &str, variables: &[InterfaceVariable]) -> fmt::Result {
+    if variables.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(f, "  {}:", title)?;
+    for var in variables {
+        for entry in var.entries.iter() {
+            let name = entry
+                .name
+                .as_ref()
+                .map_or_else(|| var.name.clone().unwrap_or_default(), |name| name.to_string());
+            writeln!(
+                f,
+                "    location {}: {} {} ({:?})",
+                locations(&entry.location),
+                var.type_name,
+                name,
+                entry.format
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn locations(range: &std::ops::Range<u32>) -> String {
+    if range.end - range.start > 1 {
+        format!("{}-{}", range.start, range.end - 1)
+    } else {
+        range.start.to_string()
+    }
+}
+
+pub(crate) fn descriptor_kind(ty: &DescriptorDescTy) -> &'static str {
+    match ty {
+        DescriptorDescTy::Sampler => "sampler",
+        DescriptorDescTy::CombinedImageSampler(_) => "combined image sampler",
+        DescriptorDescTy::Image(desc) if desc.sampled => "sampled image",
+        DescriptorDescTy::Image(_) => "storage image",
+        DescriptorDescTy::TexelBuffer { storage: false, .. } => "uniform texel buffer",
+        DescriptorDescTy::TexelBuffer { storage: true, .. } => "storage texel buffer",
+        DescriptorDescTy::InputAttachment { .. } => "input attachment",
+        DescriptorDescTy::Buffer(desc) if desc.storage => "storage buffer",
+        DescriptorDescTy::Buffer(_) => "uniform buffer",
+    }
+}
+
+fn stage_names(stages: ShaderStages) -> Vec<&'static str> {
+    let mut names = vec![];
+    if stages.vertex {
+        names.push("vertex");
+    }
+    if stages.tessellation_control {
+        names.push("tessellation control");
+    }
+    if stages.tessellation_evaluation {
+        names.push("tessellation evaluation");
+    }
+    if stages.geometry {
+        names.push("geometry");
+    }
+    if stages.fragment {
+        names.push("fragment");
+    }
+    if stages.compute {
+        names.push("compute");
+    }
+
+    names
+}
+
+fn json_variables(variables: &[InterfaceVariable]) -> String {
+    let entries: Vec<String> = variables
+        .iter()
+        .flat_map(|var| {
+            var.entries.iter().map(move |entry| {
+                let name = entry
+                    .name
+                    .as_ref()
+                    .map(|name| name.to_string())
+                    .or_else(|| var.name.clone());
+                format!(
+                    "{{\"location\":{},\"location_count\":{},\"name\":{},\"glsl_type\":{},\"format\":{}}}",
+                    entry.location.start,
+                    entry.location.end - entry.location.start,
+                    name.map_or("null".to_string(), |name| json_string(&name)),
+                    json_string(&var.type_name),
+                    json_string(&format!("{:?}", entry.format)),
+                )
+            })
+        })
+        .collect();
+
+    format!("[{}]", entries.join(","))
+}
+
+fn json_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use vulkano::descriptor::descriptor::{DescriptorBufferDesc, DescriptorDesc};
+
+    fn buffer(set: u32, binding: u32, stages: ShaderStages) -> DescriptorBinding {
+        DescriptorBinding {
+            name: None,
+            type_name: "Block".to_string(),
+            set,
+            binding,
+            desc: DescriptorDesc {
+                ty: DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                    dynamic: Some(false),
+                    storage: false,
+                }),
+                array_count: 1,
+                stages,
+                readonly: true,
+            },
+            block_size: Some(16),
+            compare: false,
+        }
+    }
+
+    fn stage(
+        stage: ShaderStage,
+        stages: ShaderStages,
+        descriptors: Vec<DescriptorBinding>,
+    ) -> StageReport {
+        StageReport {
+            stage,
+            path: PathBuf::from(format!("{}.glsl", stage)),
+            reflection: Reflection {
+                entry_name: "main".to_string(),
+                stages,
+                inputs: vec![],
+                outputs: vec![],
+                descriptors,
+                push_constants_size: None,
+                spec_constants: vec![],
+                geometry_input: None,
+            },
+        }
+    }
+
+    #[test]
+    fn descriptors_are_merged_and_sorted() {
+        let vertex = ShaderStages {
+            vertex: true,
+            ..ShaderStages::none()
+        };
+        let fragment = ShaderStages {
+            fragment: true,
+            ..ShaderStages::none()
+        };
+        let report = ShaderReport {
+            stages: vec![
+                stage(
+                    ShaderStage::Vertex,
+                    vertex,
+                    vec![buffer(1, 0, vertex), buffer(0, 0, vertex)],
+                ),
+                stage(
+                    ShaderStage::Fragment,
+                    fragment,
+                    vec![buffer(0, 0, fragment), buffer(0, 1, fragment)],
+                ),
+            ],
+        };
+
+        let descriptors: Vec<_> = report
+            .descriptors()
+            .into_iter()
+            .map(|(descriptor, stages)| (descriptor.set, descriptor.binding, stage_names(stages)))
+            .collect();
+        assert_eq!(
+            descriptors,
+            vec![
+                (0, 0, vec!["vertex", "fragment"]),
+                (0, 1, vec!["fragment"]),
+                (1, 0, vec!["vertex"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn json_strings_are_escaped() {
+        assert_eq!(json_string("plain"), "\"plain\"");
+        assert_eq!(
+            json_string("C:\\shaders\\\"a\".glsl"),
+            "\"C:\\\\shaders\\\\\\\"a\\\".glsl\""
+        );
+        assert_eq!(json_string("a\nb\tc\u{1}"), "\"a\\nb\\tc\\u0001\"");
+    }
+}
@@ -1,84 +1,1058 @@
 use vulkano::device::Device;
 use vulkano::pipeline::shader::GraphicsEntryPoint;
+use vulkano::pipeline::shader::GraphicsShaderType;
 use vulkano::pipeline::shader::ShaderModule;
+use vulkano::pipeline::shader::{SpecializationConstants, SpecializationMapEntry};
 
-use shade_runner::{
-    load, parse, Entry, FragInput, FragLayout, FragOutput, VertInput, VertLayout, VertOutput,
-};
+#[cfg(feature = "glsl")]
+use shade_runner::{parse, CompiledShaders, Entry, FragLayout, FragOutput, VertInput, VertLayout};
 
+use lazy_static::lazy_static;
+
+use crate::reflection::{Interface, Layout, Reflection};
+use crate::shader_report::{ShaderReport, StageReport};
+use crate::spirv;
+#[cfg(feature = "glsl")]
+use crate::spirv_cache;
+
+#[cfg(feature = "glsl")]
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::fmt;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 #[derive(Clone)]
 pub struct Shader {
     pub path: PathBuf,
+    pub stage: ShaderStage,
     pub module: Arc<ShaderModule>,
-    pub entry: Entry,
+    // every file pulled in with #include, directly or not
+    pub includes: Vec<PathBuf>,
+    pub entry_name: CString,
+    pub spec_constants: SpecConstants,
+    pub reflection: Reflection,
 }
 
 #[derive(Clone)]
 pub struct ShaderSystem {
     pub vs: Shader,
     pub fs: Shader,
+    pub gs: Option<Shader>,
+    pub tcs: Option<Shader>,
+    pub tes: Option<Shader>,
+    // shade_runner's reflection of the vertex and fragment shaders. without
+    // the glsl feature our own reflection is used for every stage.
+    #[cfg(feature = "glsl")]
+    pub entry: Entry,
 }
 
 impl ShaderSystem {
-    pub fn load_from_file(device: Arc<Device>, vs_path: &Path, fs_path: &Path) -> Self {
-        let shaders = load(vs_path, fs_path).expect(&format!("Couldn't load shaders: {:?} and {:?}", vs_path, fs_path));
-        let entry = parse(&shaders).expect("Couldn't parse shaders");
+    // the geometry and tessellation shaders, if any, are taken from options
+    pub fn load_from_file(
+        device: Arc<Device>,
+        vs_path: &Path,
+        fs_path: &Path,
+        options: &ShaderOptions,
+    ) -> Result<Self, ShaderError> {
+        let (vs, vertex) = Shader::load(device.clone(), vs_path, ShaderStage::Vertex, options)?;
+        let (fs, fragment) = Shader::load(device.clone(), fs_path, ShaderStage::Fragment, options)?;
 
-        let vs_module =
-            unsafe { ShaderModule::from_words(device.clone(), &shaders.vertex) }.unwrap();
+        #[cfg(feature = "glsl")]
+        let entry = {
+            let shaders = CompiledShaders {
+                vertex,
+                fragment,
+                compute: vec![],
+            };
 
-        let fs_module =
-            unsafe { ShaderModule::from_words(device.clone(), &shaders.fragment) }.unwrap();
+            // shade_runner doesn't say which of the two shaders it couldn't
+            // reflect, so blame the vertex shader and leave the stage out
+            parse(&shaders).map_err(|e| ShaderError {
+                path: vs_path.to_path_buf(),
+                stage: None,
+                kind: ShaderErrorKind::Reflection(format!("{:?}", e)),
+            })?
+        };
+        // only shade_runner needs the SPIR-V
+        #[cfg(not(feature = "glsl"))]
+        let _ = (vertex, fragment);
 
-        let vs = Shader {
-            path: vs_path.to_path_buf(),
-            module: vs_module,
-            entry: entry.clone(),
+        let (tcs, tes) = match (&options.tcs_path, &options.tes_path) {
+            (Some(tcs_path), Some(tes_path)) => (
+                Some(Shader::load(device.clone(), tcs_path, ShaderStage::TessControl, options)?.0),
+                Some(Shader::load(device.clone(), tes_path, ShaderStage::TessEvaluation, options)?.0),
+            ),
+            (None, None) => (None, None),
+            _ => {
+                return Err(ShaderError {
+                    path: vs_path.to_path_buf(),
+                    stage: None,
+                    kind: ShaderErrorKind::Pipeline(
+                        "tessellation needs both a control and an evaluation shader".to_string(),
+                    ),
+                })
+            }
         };
 
-        let fs = Shader {
-            path: fs_path.to_path_buf(),
-            module: fs_module,
-            entry: entry.clone(),
+        let gs = match &options.gs_path {
+            Some(gs_path) => {
+                let gs = Shader::load(device.clone(), gs_path, ShaderStage::Geometry, options)?.0;
+                if gs.reflection.geometry_input.is_none() {
+                    return Err(ShaderError {
+                        path: gs_path.clone(),
+                        stage: Some(ShaderStage::Geometry),
+                        kind: ShaderErrorKind::Reflection(
+                            "geometry shader doesn't declare its input primitive".to_string(),
+                        ),
+                    });
+                }
+                Some(gs)
+            }
+            None => None,
         };
 
-        Self { vs, fs }
+        Ok(Self {
+            vs,
+            fs,
+            gs,
+            tcs,
+            tes,
+            #[cfg(feature = "glsl")]
+            entry,
+        })
+    }
+
+    pub fn has_tessellation(&self) -> bool {
+        self.tcs.is_some()
+    }
+
+    // all the files the shaders were built from, including the ones pulled in
+    // with #include
+    pub fn source_files(&self) -> Vec<PathBuf> {
+        let mut files = vec![];
+        for shader in self.all_shaders() {
+            for path in std::iter::once(&shader.path).chain(shader.includes.iter()) {
+                if !files.contains(path) {
+                    files.push(path.clone());
+                }
+            }
+        }
+
+        files
+    }
+
+    // every stage in the order they run
+    pub fn all_shaders(&self) -> Vec<&Shader> {
+        let mut shaders = vec![&self.vs];
+        shaders.extend(self.tcs.iter());
+        shaders.extend(self.tes.iter());
+        shaders.extend(self.gs.iter());
+        shaders.push(&self.fs);
+
+        shaders
     }
 
-    pub fn get_entry_points(&self) -> (VertEntry, FragEntry) {
-        let vs_entry = self.vs.entry.clone();
-        let fs_entry = self.fs.entry.clone();
+    // what each stage takes in and puts out, for debugging mismatches between
+    // the shaders and the data given to them
+    pub fn report(&self) -> ShaderReport {
+        let stages = self
+            .all_shaders()
+            .into_iter()
+            .map(|shader| StageReport {
+                stage: shader.stage,
+                path: shader.path.clone(),
+                reflection: shader.reflection.clone(),
+            })
+            .collect();
 
-        let vert_main = unsafe {
+        ShaderReport { stages }
+    }
+
+    #[cfg(feature = "glsl")]
+    pub fn get_entry_points(&self) -> EntryPoints<'_> {
+        let entry = self.entry.clone();
+
+        // shade_runner only knows about vertex and fragment shaders, so if
+        // there are any stages in between all the interfaces between stages
+        // are taken from our own reflection to make sure they line up
+        let only_vs_fs = self.gs.is_none() && self.tcs.is_none();
+        let (vs_output, fs_input) = if only_vs_fs {
+            (
+                Interface::from_def(&entry.vert_output),
+                Interface::from_def(&entry.frag_input),
+            )
+        } else {
+            (
+                self.vs.reflection.output_interface(),
+                self.fs.reflection.input_interface(),
+            )
+        };
+
+        let vs = unsafe {
             self.vs.module.graphics_entry_point(
-                std::ffi::CStr::from_bytes_with_nul_unchecked(b"main\0"),
-                vs_entry.vert_input,
-                vs_entry.vert_output,
-                vs_entry.vert_layout,
-                vulkano::pipeline::shader::GraphicsShaderType::Vertex,
+                &self.vs.entry_name,
+                entry.vert_input,
+                vs_output,
+                entry.vert_layout,
+                GraphicsShaderType::Vertex,
             )
         };
 
-        let frag_main = unsafe {
+        let fs = unsafe {
             self.fs.module.graphics_entry_point(
-                std::ffi::CStr::from_bytes_with_nul_unchecked(b"main\0"),
-                fs_entry.frag_input,
-                fs_entry.frag_output,
-                fs_entry.frag_layout,
-                vulkano::pipeline::shader::GraphicsShaderType::Fragment,
+                &self.fs.entry_name,
+                fs_input,
+                entry.frag_output,
+                entry.frag_layout,
+                GraphicsShaderType::Fragment,
             )
         };
 
-        (vert_main, frag_main)
+        EntryPoints {
+            vs: (vs, self.vs.spec_constants),
+            fs: (fs, self.fs.spec_constants),
+            gs: self.gs_entry_point(),
+            tcs: self.tcs_entry_point(),
+            tes: self.tes_entry_point(),
+        }
+    }
+
+    // without shade_runner the vertex and fragment shaders are reflected the
+    // same way as the stages in between
+    #[cfg(not(feature = "glsl"))]
+    pub fn get_entry_points(&self) -> EntryPoints<'_> {
+        EntryPoints {
+            vs: self.vs.stage_entry_point(GraphicsShaderType::Vertex),
+            fs: self.fs.stage_entry_point(GraphicsShaderType::Fragment),
+            gs: self.gs_entry_point(),
+            tcs: self.tcs_entry_point(),
+            tes: self.tes_entry_point(),
+        }
+    }
+
+    fn tcs_entry_point(&self) -> Option<(StageEntry<'_>, SpecConstants)> {
+        self.tcs
+            .as_ref()
+            .map(|tcs| tcs.stage_entry_point(GraphicsShaderType::TessellationControl))
+    }
+
+    fn tes_entry_point(&self) -> Option<(StageEntry<'_>, SpecConstants)> {
+        self.tes
+            .as_ref()
+            .map(|tes| tes.stage_entry_point(GraphicsShaderType::TessellationEvaluation))
+    }
+
+    fn gs_entry_point(&self) -> Option<(StageEntry<'_>, SpecConstants)> {
+        self.gs.as_ref().map(|gs| {
+            // checked when loading
+            let mode = gs.reflection.geometry_input.unwrap();
+            gs.stage_entry_point(GraphicsShaderType::Geometry(mode))
+        })
+    }
+}
+
+impl Shader {
+    // also returns the SPIR-V, which shade_runner needs for vertex and
+    // fragment shaders
+    fn load(
+        device: Arc<Device>,
+        path: &Path,
+        stage: ShaderStage,
+        options: &ShaderOptions,
+    ) -> Result<(Self, Vec<u32>), ShaderError> {
+        let entry_name_str = options.entry_name(stage);
+
+        let (words, includes) = compile(path, stage, entry_name_str, options)?;
+        let entry_name = entry_name(path, stage, entry_name_str, &words)?;
+        let spec_constants = SpecConstants::new(path, stage, &words, &options.specialization)?;
+        let reflection = Reflection::new(&words, entry_name_str).map_err(|e| ShaderError {
+            path: path.to_path_buf(),
+            stage: Some(stage),
+            kind: ShaderErrorKind::Reflection(e),
+        })?;
+        let module = create_module(device, path, stage, &words)?;
+
+        let shader = Self {
+            path: path.to_path_buf(),
+            stage,
+            module,
+            includes,
+            entry_name,
+            spec_constants,
+            reflection,
+        };
+
+        Ok((shader, words))
+    }
+
+    // entry point for the stages between vertex and fragment shaders, which
+    // shade_runner can't reflect (and for every stage without the glsl
+    // feature)
+    fn stage_entry_point(&self, ty: GraphicsShaderType) -> (StageEntry<'_>, SpecConstants) {
+        let entry_point = unsafe {
+            self.module.graphics_entry_point(
+                &self.entry_name,
+                self.reflection.input_interface(),
+                self.reflection.output_interface(),
+                self.reflection.layout(),
+                ty,
+            )
+        };
+
+        (entry_point, self.spec_constants)
     }
 }
 
+// everything needed to feed the shaders to vulkano's pipeline builder
+pub struct EntryPoints<'a> {
+    pub vs: (VertEntry<'a>, SpecConstants),
+    pub fs: (FragEntry<'a>, SpecConstants),
+    pub gs: Option<(StageEntry<'a>, SpecConstants)>,
+    pub tcs: Option<(StageEntry<'a>, SpecConstants)>,
+    pub tes: Option<(StageEntry<'a>, SpecConstants)>,
+}
+
 pub fn relative_path(local_path: &str) -> PathBuf {
     [env!("CARGO_MANIFEST_DIR"), local_path].iter().collect()
 }
 
-type VertEntry<'a> = GraphicsEntryPoint<'a, (), VertInput, VertOutput, VertLayout>;
-type FragEntry<'a> = GraphicsEntryPoint<'a, (), FragInput, FragOutput, FragLayout>;
+#[cfg(feature = "glsl")]
+type VertEntry<'a> = GraphicsEntryPoint<'a, SpecConstants, VertInput, Interface, VertLayout>;
+#[cfg(feature = "glsl")]
+type FragEntry<'a> = GraphicsEntryPoint<'a, SpecConstants, Interface, FragOutput, FragLayout>;
+#[cfg(not(feature = "glsl"))]
+type VertEntry<'a> = StageEntry<'a>;
+#[cfg(not(feature = "glsl"))]
+type FragEntry<'a> = StageEntry<'a>;
+type StageEntry<'a> = GraphicsEntryPoint<'a, SpecConstants, Interface, Interface, Layout>;
+
+// settings for compiling and specializing the shaders. pipelines with the same
+// shaders but different options are cached separately, so one shader can be
+// compiled into several variants by switching parts of it on and off with
+// #ifdef or specialization constants.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShaderOptions {
+    // each entry is like #define NAME VALUE at the top of every shader. a value
+    // of None defines the name without a value.
+    pub defines: Vec<(String, Option<String>)>,
+    // #include "file" is first looked up relative to the including file, then
+    // in these directories in order. #include <file> only uses these.
+    // defines and include directories only apply to GLSL, giving either for a
+    // precompiled SPIR-V shader is an error.
+    pub include_dirs: Vec<PathBuf>,
+    // values for specialization constants as (constant_id, bits) pairs. they
+    // apply to every stage, stages that don't declare a constant ignore it.
+    // constants that aren't given a value keep the default from the shader.
+    pub specialization: Vec<(u32, u32)>,
+    // names of the entry points to use, "main" if None. when compiling GLSL,
+    // main() is renamed to this.
+    pub vs_entry: Option<String>,
+    pub fs_entry: Option<String>,
+    pub gs_entry: Option<String>,
+    pub tcs_entry: Option<String>,
+    pub tes_entry: Option<String>,
+    // optional stages between the vertex and fragment shader. tessellation
+    // needs both shaders and a fill_type of PatchList.
+    pub gs_path: Option<PathBuf>,
+    pub tcs_path: Option<PathBuf>,
+    pub tes_path: Option<PathBuf>,
+}
+
+impl ShaderOptions {
+    pub fn define(mut self, name: &str, value: Option<&str>) -> Self {
+        self.defines
+            .push((name.to_string(), value.map(|value| value.to_string())));
+        self
+    }
+
+    pub fn include_dir(mut self, dir: PathBuf) -> Self {
+        self.include_dirs.push(dir);
+        self
+    }
+
+    pub fn specialize(mut self, constant_id: u32, value: u32) -> Self {
+        self.specialization.retain(|(id, _)| *id != constant_id);
+        self.specialization.push((constant_id, value));
+        self
+    }
+
+    pub fn specialize_f32(self, constant_id: u32, value: f32) -> Self {
+        self.specialize(constant_id, value.to_bits())
+    }
+
+    pub fn specialize_bool(self, constant_id: u32, value: bool) -> Self {
+        self.specialize(constant_id, value as u32)
+    }
+
+    pub fn entry_points(mut self, vs_entry: &str, fs_entry: &str) -> Self {
+        self.vs_entry = Some(vs_entry.to_string());
+        self.fs_entry = Some(fs_entry.to_string());
+        self
+    }
+
+    pub fn geometry_shader(mut self, gs_path: PathBuf) -> Self {
+        self.gs_path = Some(gs_path);
+        self
+    }
+
+    pub fn tessellation_shaders(mut self, tcs_path: PathBuf, tes_path: PathBuf) -> Self {
+        self.tcs_path = Some(tcs_path);
+        self.tes_path = Some(tes_path);
+        self
+    }
+
+    pub fn entry_name(&self, stage: ShaderStage) -> &str {
+        let name = match stage {
+            ShaderStage::Vertex => &self.vs_entry,
+            ShaderStage::Fragment => &self.fs_entry,
+            ShaderStage::Geometry => &self.gs_entry,
+            ShaderStage::TessControl => &self.tcs_entry,
+            ShaderStage::TessEvaluation => &self.tes_entry,
+        };
+
+        name.as_ref().map_or("main", |name| name.as_str())
+    }
+}
+
+// vulkano wants the layout of specialization constants to be known at compile
+// time, so rather than building one per shader a fixed block of 32-bit slots
+// is used, one per constant id. ids the shader doesn't declare are ignored by
+// vulkan, and declared ones that weren't given a value are filled in with
+// their defaults.
+pub const MAX_SPEC_CONSTANTS: usize = 32;
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct SpecConstants {
+    values: [u32; MAX_SPEC_CONSTANTS],
+}
+
+lazy_static! {
+    static ref SPEC_CONSTANT_MAP: Vec<SpecializationMapEntry> = (0..MAX_SPEC_CONSTANTS)
+        .map(|idx| SpecializationMapEntry {
+            constant_id: idx as u32,
+            offset: (idx * 4) as u32,
+            size: 4,
+        })
+        .collect();
+}
+
+unsafe impl SpecializationConstants for SpecConstants {
+    fn descriptors() -> &'static [SpecializationMapEntry] {
+        &SPEC_CONSTANT_MAP
+    }
+}
+
+impl SpecConstants {
+    fn new(
+        path: &Path,
+        stage: ShaderStage,
+        words: &[u32],
+        values: &[(u32, u32)],
+    ) -> Result<Self, ShaderError> {
+        let error = |msg| ShaderError {
+            path: path.to_path_buf(),
+            stage: Some(stage),
+            kind: ShaderErrorKind::Specialization(msg),
+        };
+
+        let mut spec_constants = Self {
+            values: [0; MAX_SPEC_CONSTANTS],
+        };
+
+        for info in spirv::spec_constants(words) {
+            if info.constant_id as usize >= MAX_SPEC_CONSTANTS {
+                return Err(error(format!(
+                    "constant_id {} is too high, only ids below {} are supported",
+                    info.constant_id, MAX_SPEC_CONSTANTS
+                )));
+            }
+            if info.wide {
+                return Err(error(format!(
+                    "constant_id {} is 64-bit, only 32-bit constants are supported",
+                    info.constant_id
+                )));
+            }
+
+            spec_constants.values[info.constant_id as usize] = info.default;
+        }
+
+        for &(constant_id, value) in values {
+            if constant_id as usize >= MAX_SPEC_CONSTANTS {
+                return Err(error(format!(
+                    "constant_id {} is too high, only ids below {} are supported",
+                    constant_id, MAX_SPEC_CONSTANTS
+                )));
+            }
+
+            spec_constants.values[constant_id as usize] = value;
+        }
+
+        Ok(spec_constants)
+    }
+}
+
+// checks the module actually has an entry point with the given name, because
+// vulkan would just crash otherwise
+fn entry_name(
+    path: &Path,
+    stage: ShaderStage,
+    name: &str,
+    words: &[u32],
+) -> Result<CString, ShaderError> {
+    let entry_points = spirv::entry_points(words);
+    if !entry_points.iter().any(|entry_point| entry_point == name) {
+        return Err(ShaderError {
+            path: path.to_path_buf(),
+            stage: Some(stage),
+            kind: ShaderErrorKind::MissingEntryPoint {
+                name: name.to_string(),
+                available: entry_points,
+            },
+        });
+    }
+
+    // SPIR-V strings can't contain nul bytes, so neither can a name that
+    // matched one
+    Ok(CString::new(name).unwrap())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShaderStage {
+    Vertex,
+    TessControl,
+    TessEvaluation,
+    Geometry,
+    Fragment,
+}
+
+impl ShaderStage {
+    #[cfg(feature = "glsl")]
+    fn shaderc_kind(self) -> shaderc::ShaderKind {
+        match self {
+            ShaderStage::Vertex => shaderc::ShaderKind::Vertex,
+            ShaderStage::TessControl => shaderc::ShaderKind::TessControl,
+            ShaderStage::TessEvaluation => shaderc::ShaderKind::TessEvaluation,
+            ShaderStage::Geometry => shaderc::ShaderKind::Geometry,
+            ShaderStage::Fragment => shaderc::ShaderKind::Fragment,
+        }
+    }
+}
+
+impl fmt::Display for ShaderStage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShaderStage::Vertex => write!(f, "vertex"),
+            ShaderStage::TessControl => write!(f, "tessellation control"),
+            ShaderStage::TessEvaluation => write!(f, "tessellation evaluation"),
+            ShaderStage::Geometry => write!(f, "geometry"),
+            ShaderStage::Fragment => write!(f, "fragment"),
+        }
+    }
+}
+
+// everything that can go wrong between reading a shader file and having a
+// pipeline built from it. printing one with {} gives a compiler-style message
+// with the offending source lines.
+#[derive(Debug, Clone)]
+pub struct ShaderError {
+    pub path: PathBuf,
+    // None if the error can't be pinned to a single stage, for example when
+    // the vertex shader's outputs don't match the fragment shader's inputs
+    pub stage: Option<ShaderStage>,
+    pub kind: ShaderErrorKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum ShaderErrorKind {
+    // the file couldn't be read
+    Io(String),
+    Compile(Vec<Diagnostic>),
+    // a precompiled shader that doesn't start with the SPIR-V magic number
+    InvalidSpirv,
+    // options that only apply to GLSL (defines and include directories) were
+    // given for a precompiled shader, or GLSL was given without the glsl
+    // feature
+    Unsupported(String),
+    MissingEntryPoint {
+        name: String,
+        available: Vec<String>,
+    },
+    Specialization(String),
+    // shade_runner couldn't work out the interface or layout from the SPIR-V
+    Reflection(String),
+    // vulkan rejected the compiled SPIR-V
+    Module(String),
+    // the shaders compiled fine, but no pipeline could be made from them
+    Pipeline(String),
+}
+
+// a single error or warning reported by the compiler
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    // the file the error is in, which isn't the shader itself if the error is
+    // in an included file
+    pub file: Option<PathBuf>,
+    // 1-based, like the compiler reports them
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub message: String,
+    pub source_line: Option<String>,
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.stage {
+            Some(stage) => write!(f, "error in {} shader {:?}", stage, self.path)?,
+            None => write!(f, "error in shaders starting with {:?}", self.path)?,
+        }
+
+        match &self.kind {
+            ShaderErrorKind::Io(msg) => write!(f, ": couldn't read file: {}", msg),
+            ShaderErrorKind::InvalidSpirv => write!(f, ": not a valid SPIR-V file"),
+            ShaderErrorKind::Unsupported(msg) => write!(f, ": {}", msg),
+            ShaderErrorKind::MissingEntryPoint { name, available } => write!(
+                f,
+                ": no entry point named {:?}, the module has {:?}",
+                name, available
+            ),
+            ShaderErrorKind::Specialization(msg) => {
+                write!(f, ": couldn't specialize shader: {}", msg)
+            }
+            ShaderErrorKind::Reflection(msg) => write!(f, ": couldn't reflect shaders: {}", msg),
+            ShaderErrorKind::Module(msg) => write!(f, ": couldn't create shader module: {}", msg),
+            ShaderErrorKind::Pipeline(msg) => write!(f, ": couldn't create pipeline: {}", msg),
+            ShaderErrorKind::Compile(diagnostics) => {
+                writeln!(f, ": failed to compile")?;
+                for diagnostic in diagnostics {
+                    write!(f, "{}", diagnostic)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "  ")?;
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file.display())?;
+        }
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => writeln!(f, "{}:{}: {}", line, column, self.message)?,
+            (Some(line), None) => writeln!(f, "{}: {}", line, self.message)?,
+            _ => writeln!(f, "{}", self.message)?,
+        }
+
+        if let Some(source_line) = &self.source_line {
+            writeln!(f, "    | {}", source_line)?;
+            if let Some(column) = self.column {
+                writeln!(f, "    | {}^", " ".repeat(column.saturating_sub(1) as usize))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+lazy_static! {
+    static ref EMBEDDED_SPIRV: Mutex<HashMap<PathBuf, Vec<u32>>> = Mutex::new(HashMap::new());
+}
+
+// makes SPIR-V that's compiled into the program, usually with include_bytes!,
+// loadable from the given path. a PipelineSpec pointing at that path will use
+// it instead of looking at the file system, so shipped builds don't need the
+// shader files at all.
+pub fn embed_spirv(path: PathBuf, bytes: &[u8]) {
+    let words = spirv_from_bytes(bytes)
+        .unwrap_or_else(|| panic!("Embedded shader {:?} isn't valid SPIR-V", path));
+
+    EMBEDDED_SPIRV.lock().unwrap().insert(path, words);
+}
+
+// compiles a shader the same way a pipeline would, for writing it out ahead
+// of time with write_spirv. that way the final program only needs the .spv
+// files and never has to compile GLSL.
+pub fn compile_to_spirv(
+    path: &Path,
+    stage: ShaderStage,
+    entry_name: &str,
+    options: &ShaderOptions,
+) -> Result<Vec<u32>, ShaderError> {
+    compile(path, stage, entry_name, options).map(|(words, _includes)| words)
+}
+
+pub fn write_spirv(path: &Path, words: &[u32]) -> std::io::Result<()> {
+    std::fs::write(path, spirv_to_bytes(words))
+}
+
+const SPIRV_MAGIC: u32 = 0x0723_0203;
+
+// returns None if the bytes aren't SPIR-V. either endianness is accepted.
+pub fn spirv_from_bytes(bytes: &[u8]) -> Option<Vec<u32>> {
+    if bytes.len() % 4 != 0 {
+        return None;
+    }
+
+    let words: Vec<u32> = bytes
+        .chunks(4)
+        .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect();
+
+    match words.first() {
+        Some(&SPIRV_MAGIC) => Some(words),
+        Some(&magic) if magic == SPIRV_MAGIC.swap_bytes() => {
+            Some(words.iter().map(|word| word.swap_bytes()).collect())
+        }
+        _ => None,
+    }
+}
+
+pub fn spirv_to_bytes(words: &[u32]) -> Vec<u8> {
+    words
+        .iter()
+        .flat_map(|word| word.to_le_bytes().to_vec())
+        .collect()
+}
+
+// returns the SPIR-V along with the files that were included. paths ending in
+// .spv (and anything registered with embed_spirv) are loaded as they are,
+// everything else is compiled as GLSL.
+fn compile(
+    path: &Path,
+    stage: ShaderStage,
+    entry_name: &str,
+    options: &ShaderOptions,
+) -> Result<(Vec<u32>, Vec<PathBuf>), ShaderError> {
+    let error = |kind| ShaderError {
+        path: path.to_path_buf(),
+        stage: Some(stage),
+        kind,
+    };
+
+    let embedded = EMBEDDED_SPIRV.lock().unwrap().get(path).cloned();
+    let is_spirv = embedded.is_some() || path.extension().is_some_and(|ext| ext == "spv");
+
+    // SPIR-V is already compiled, so these would silently do nothing
+    if is_spirv && !options.defines.is_empty() {
+        return Err(error(ShaderErrorKind::Unsupported(
+            "defines can't be applied to precompiled SPIR-V".to_string(),
+        )));
+    }
+    if is_spirv && !options.include_dirs.is_empty() {
+        return Err(error(ShaderErrorKind::Unsupported(
+            "include directories can't be applied to precompiled SPIR-V".to_string(),
+        )));
+    }
+
+    if let Some(words) = embedded {
+        return Ok((words, vec![]));
+    }
+
+    if is_spirv {
+        let bytes = std::fs::read(path).map_err(|e| error(ShaderErrorKind::Io(e.to_string())))?;
+        let words = spirv_from_bytes(&bytes).ok_or_else(|| error(ShaderErrorKind::InvalidSpirv))?;
+
+        return Ok((words, vec![]));
+    }
+
+    compile_glsl(path, stage, entry_name, options)
+}
+
+#[cfg(not(feature = "glsl"))]
+fn compile_glsl(
+    path: &Path,
+    stage: ShaderStage,
+    _entry_name: &str,
+    _options: &ShaderOptions,
+) -> Result<(Vec<u32>, Vec<PathBuf>), ShaderError> {
+    Err(ShaderError {
+        path: path.to_path_buf(),
+        stage: Some(stage),
+        kind: ShaderErrorKind::Unsupported(
+            "compiling GLSL needs the glsl feature, load a .spv file instead".to_string(),
+        ),
+    })
+}
+
+#[cfg(feature = "glsl")]
+fn compile_glsl(
+    path: &Path,
+    stage: ShaderStage,
+    entry_name: &str,
+    options: &ShaderOptions,
+) -> Result<(Vec<u32>, Vec<PathBuf>), ShaderError> {
+    let error = |kind| ShaderError {
+        path: path.to_path_buf(),
+        stage: Some(stage),
+        kind,
+    };
+
+    let source = std::fs::read_to_string(path).map_err(|e| error(ShaderErrorKind::Io(e.to_string())))?;
+    let file_name = path.to_string_lossy().to_string();
+
+    // the contents of every file the compiler saw, so errors in included files
+    // can show the right source line
+    let sources = RefCell::new(HashMap::new());
+    sources.borrow_mut().insert(file_name.clone(), source.clone());
+
+    let mut compile_options =
+        shaderc::CompileOptions::new().expect("Couldn't create shader compile options");
+    for (name, value) in options.defines.iter() {
+        compile_options.add_macro_definition(name, value.as_ref().map(|value| value.as_str()));
+    }
+    compile_options.set_include_callback(|requested, include_type, requesting, _depth| {
+        let resolved = resolve_include(requested, include_type, Path::new(requesting), &options.include_dirs)
+            .ok_or_else(|| format!("Couldn't find include file {:?}", requested))?;
+        let content = std::fs::read_to_string(&resolved)
+            .map_err(|e| format!("Couldn't read include file {:?}: {}", resolved, e))?;
+
+        let resolved_name = resolved.to_string_lossy().to_string();
+        sources.borrow_mut().insert(resolved_name.clone(), content.clone());
+
+        Ok(shaderc::ResolvedInclude {
+            resolved_name,
+            content,
+        })
+    });
+
+    let mut compiler = shaderc::Compiler::new().expect("Couldn't create shader compiler");
+
+    // preprocessing is cheap compared to compiling, and its output covers
+    // everything that affects the SPIR-V: the source, all includes and all
+    // defines. so it's what the on-disk cache is keyed by.
+    let preprocessed = compiler
+        .preprocess(&source, &file_name, entry_name, Some(&compile_options))
+        .map_err(|e| error(compile_error_kind(e, &sources.borrow())))?;
+    let cache_key = spirv_cache::key(&[
+        preprocessed.as_text().as_bytes(),
+        stage.to_string().as_bytes(),
+        entry_name.as_bytes(),
+    ]);
+
+    let includes = sources
+        .borrow()
+        .keys()
+        .filter(|name| **name != file_name)
+        .map(PathBuf::from)
+        .collect();
+
+    if let Some(words) = spirv_cache::load(cache_key) {
+        return Ok((words, includes));
+    }
+
+    // compile the original rather than the preprocessed text so errors point
+    // at the real files
+    let words = compiler
+        .compile_into_spirv(
+            &source,
+            stage.shaderc_kind(),
+            &file_name,
+            entry_name,
+            Some(&compile_options),
+        )
+        .map_err(|e| error(compile_error_kind(e, &sources.borrow())))?
+        .as_binary()
+        .to_vec();
+
+    // the shader compiled fine, so a cache that can't be written is only
+    // worth a warning
+    if let Err(e) = spirv_cache::store(cache_key, &words) {
+        eprintln!("{}", e);
+    }
+
+    Ok((words, includes))
+}
+
+#[cfg(feature = "glsl")]
+fn compile_error_kind(e: shaderc::Error, sources: &HashMap<String, String>) -> ShaderErrorKind {
+    match e {
+        shaderc::Error::CompilationError(_, log) => {
+            ShaderErrorKind::Compile(parse_diagnostics(&log, sources))
+        }
+        other => ShaderErrorKind::Compile(vec![Diagnostic {
+            file: None,
+            line: None,
+            column: None,
+            message: format!("{:?}", other),
+            source_line: None,
+        }]),
+    }
+}
+
+#[cfg(feature = "glsl")]
+fn resolve_include(
+    requested: &str,
+    include_type: shaderc::IncludeType,
+    requesting: &Path,
+    include_dirs: &[PathBuf],
+) -> Option<PathBuf> {
+    let relative_dir = match include_type {
+        shaderc::IncludeType::Relative => requesting.parent().map(|dir| dir.to_path_buf()),
+        shaderc::IncludeType::Standard => None,
+    };
+
+    relative_dir
+        .iter()
+        .chain(include_dirs.iter())
+        .map(|dir| dir.join(requested))
+        .find(|path| path.is_file())
+}
+
+fn create_module(
+    device: Arc<Device>,
+    path: &Path,
+    stage: ShaderStage,
+    words: &[u32],
+) -> Result<Arc<ShaderModule>, ShaderError> {
+    unsafe { ShaderModule::from_words(device, words) }.map_err(|e| ShaderError {
+        path: path.to_path_buf(),
+        stage: Some(stage),
+        kind: ShaderErrorKind::Module(format!("{:?}", e)),
+    })
+}
+
+// glslang reports errors as "<file>:<line>: error: <message>", sometimes with
+// a column after the line. the summary at the end ("1 error generated.") is
+// left out.
+#[cfg(any(feature = "glsl", test))]
+fn parse_diagnostics(log: &str, sources: &HashMap<String, String>) -> Vec<Diagnostic> {
+    log.lines()
+        .filter(|line| !line.trim().is_empty() && !line.ends_with("generated."))
+        .map(|line| {
+            let (location, message) = match line.find(": error: ").or_else(|| line.find(": warning: ")) {
+                Some(idx) => (&line[..idx], line[idx + 2..].trim()),
+                None => ("", line.trim()),
+            };
+
+            // the file name itself might contain colons, so take the numbers
+            // from the end
+            let numbers: Vec<u32> = location
+                .rsplit(':')
+                .map(|part| part.trim().parse::<u32>())
+                .take_while(Result::is_ok)
+                .filter_map(Result::ok)
+                .take(2)
+                .collect();
+            let (line_num, column) = match numbers.as_slice() {
+                [column, line] => (Some(*line), Some(*column)),
+                [line] => (Some(*line), None),
+                _ => (None, None),
+            };
+
+            // whatever is left in front of the numbers is the file name
+            let file = location
+                .rsplitn(numbers.len() + 1, ':')
+                .last()
+                .filter(|file| !file.is_empty() && !numbers.is_empty());
+
+            let source_line = file
+                .and_then(|file| sources.get(file))
+                .zip(line_num)
+                .and_then(|(source, num)| source.lines().nth((num as usize).checked_sub(1)?))
+                .map(|line| line.trim_end().to_string());
+
+            Diagnostic {
+                file: file.map(PathBuf::from),
+                line: line_num,
+                column,
+                message: message.to_string(),
+                source_line,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(log: &str) -> Vec<Diagnostic> {
+        let mut sources = HashMap::new();
+        sources.insert(
+            "frag.glsl".to_string(),
+            "#version 450\nvoid main() {\n  foo = 1;   \n}\n".to_string(),
+        );
+        parse_diagnostics(log, &sources)
+    }
+
+    #[test]
+    fn line_and_column() {
+        let diagnostics = parse(
+            "frag.glsl:3: error: 'foo' : undeclared identifier\n\
+             frag.glsl:2:6: warning: unused\n",
+        );
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].file, Some(PathBuf::from("frag.glsl")));
+        assert_eq!(
+            (diagnostics[0].line, diagnostics[0].column),
+            (Some(3), None)
+        );
+        assert_eq!(
+            diagnostics[0].message,
+            "error: 'foo' : undeclared identifier"
+        );
+        // without the trailing whitespace
+        assert_eq!(diagnostics[0].source_line.as_deref(), Some("  foo = 1;"));
+        assert_eq!(
+            (diagnostics[1].line, diagnostics[1].column),
+            (Some(2), Some(6))
+        );
+        assert_eq!(diagnostics[1].message, "warning: unused");
+    }
+
+    #[test]
+    fn colons_in_file_names() {
+        let diagnostics = parse("C:\\shaders\\frag.glsl:4: error: bad");
+
+        assert_eq!(
+            diagnostics[0].file,
+            Some(PathBuf::from("C:\\shaders\\frag.glsl"))
+        );
+        assert_eq!(diagnostics[0].line, Some(4));
+        // not one of the sources
+        assert_eq!(diagnostics[0].source_line, None);
+    }
+
+    #[test]
+    fn summary_and_blank_lines_are_left_out() {
+        let diagnostics = parse("frag.glsl:1: error: x\n\n   \n1 error generated.\n");
+
+        assert_eq!(diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn malformed_messages_are_kept_whole() {
+        let diagnostics = parse(
+            "error: no location\n\
+             frag.glsl:abc: error: not a line number\n\
+             internal compiler error",
+        );
+
+        assert_eq!(diagnostics.len(), 3);
+        for diagnostic in diagnostics.iter() {
+            assert_eq!(diagnostic.file, None);
+            assert_eq!((diagnostic.line, diagnostic.column), (None, None));
+            assert_eq!(diagnostic.source_line, None);
+        }
+        assert_eq!(diagnostics[0].message, "error: no location");
+        assert_eq!(diagnostics[1].message, "error: not a line number");
+        assert_eq!(diagnostics[2].message, "internal compiler error");
+    }
+
+    #[test]
+    fn lines_outside_the_source() {
+        let diagnostics = parse("frag.glsl:0: error: x\nfrag.glsl:99: error: y");
+
+        assert_eq!(diagnostics[0].line, Some(0));
+        assert_eq!(diagnostics[0].source_line, None);
+        assert_eq!(diagnostics[1].line, Some(99));
+        assert_eq!(diagnostics[1].source_line, None);
+    }
+}
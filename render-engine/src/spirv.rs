@@ -0,0 +1,104 @@
+// Just enough of a SPIR-V parser to find the things shade_runner doesn't tell
+// us about: entry point names and specialization constants.
+
+// opcodes and decorations, from the SPIR-V spec
+const OP_ENTRY_POINT: u16 = 15;
+const OP_SPEC_CONSTANT_TRUE: u16 = 48;
+const OP_SPEC_CONSTANT_FALSE: u16 = 49;
+const OP_SPEC_CONSTANT: u16 = 50;
+const OP_DECORATE: u16 = 71;
+const DECORATION_SPEC_ID: u32 = 1;
+
+// the magic number, version, generator, id bound and schema
+const HEADER_LEN: usize = 5;
+
+pub struct Instruction<'a> {
+    pub opcode: u16,
+    pub operands: &'a [u32],
+}
+
+// goes through the instructions of a module in order, stopping early if the
+// module is malformed
+pub fn instructions(words: &[u32]) -> impl Iterator<Item = Instruction<'_>> {
+    let mut idx = HEADER_LEN;
+
+    std::iter::from_fn(move || {
+        let first = *words.get(idx)?;
+        let len = (first >> 16) as usize;
+        if len == 0 || idx + len > words.len() {
+            return None;
+        }
+
+        let instruction = Instruction {
+            opcode: (first & 0xffff) as u16,
+            operands: &words[idx + 1..idx + len],
+        };
+        idx += len;
+
+        Some(instruction)
+    })
+}
+
+// the names of all entry points in the module
+pub fn entry_points(words: &[u32]) -> Vec<String> {
+    instructions(words)
+        // operands are the execution model, the function id, then the name
+        .filter(|inst| inst.opcode == OP_ENTRY_POINT && inst.operands.len() > 2)
+        .map(|inst| string_literal(&inst.operands[2..]))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpecConstantInfo {
+    pub constant_id: u32,
+    // the bits of the default value. bools are 0 or 1.
+    pub default: u32,
+    // true for 64-bit constants, whose default is cut down to the low 32 bits
+    pub wide: bool,
+}
+
+// every specialization constant declared in the module, along with its default
+pub fn spec_constants(words: &[u32]) -> Vec<SpecConstantInfo> {
+    // result id -> SpecId decoration
+    let spec_ids: Vec<(u32, u32)> = instructions(words)
+        .filter(|inst| {
+            inst.opcode == OP_DECORATE
+                && inst.operands.len() >= 3
+                && inst.operands[1] == DECORATION_SPEC_ID
+        })
+        .map(|inst| (inst.operands[0], inst.operands[2]))
+        .collect();
+
+    instructions(words)
+        .filter_map(|inst| {
+            // operands are the result type, the result id, then the value
+            let (default, wide) = match inst.opcode {
+                OP_SPEC_CONSTANT_TRUE => (1, false),
+                OP_SPEC_CONSTANT_FALSE => (0, false),
+                OP_SPEC_CONSTANT => (*inst.operands.get(2)?, inst.operands.len() > 3),
+                _ => return None,
+            };
+            let result_id = *inst.operands.get(1)?;
+
+            spec_ids
+                .iter()
+                .find(|(id, _)| *id == result_id)
+                .map(|&(_, constant_id)| SpecConstantInfo {
+                    constant_id,
+                    default,
+                    wide,
+                })
+        })
+        .collect()
+}
+
+// strings are packed 4 bytes to a word, little-endian, ending with a nul
+pub fn string_literal(words: &[u32]) -> String {
+    let bytes: Vec<u8> = words
+        .iter()
+        .flat_map(|word| word.to_le_bytes().to_vec())
+        .take_while(|&byte| byte != 0)
+        .collect();
+
+    String::from_utf8_lossy(&bytes).to_string()
+}
@@ -0,0 +1,130 @@
+// Compiled SPIR-V is kept on disk between runs, so launching doesn't mean
+// recompiling every shader. Entries are keyed by a hash of everything that
+// went into the compiler (see shaders.rs), so editing a shader, one of its
+// includes or its defines just makes a new entry.
+
+// Ideally the pipelines themselves would be cached too, with a Vulkan pipeline
+// cache saved to disk. Unfortunately vulkano 0.14's GraphicsPipelineBuilder
+// always creates pipelines without one, so there's no way to make use of it
+// here.
+
+use crate::shaders::{spirv_from_bytes, spirv_to_bytes};
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+// bump this whenever the way shaders are compiled changes, so old entries
+// don't get used
+const CACHE_VERSION: &[u8] = b"render-engine spirv 1";
+
+// the cache lives in RENDER_ENGINE_CACHE_DIR if it's set, otherwise in the
+// system's temp directory. set RENDER_ENGINE_CACHE_DIR to an empty string to
+// turn the cache off.
+pub fn cache_dir() -> Option<PathBuf> {
+    match std::env::var_os("RENDER_ENGINE_CACHE_DIR") {
+        Some(dir) if dir.is_empty() => None,
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => Some(std::env::temp_dir().join("render-engine-cache")),
+    }
+}
+
+// 64-bit FNV-1a over all the parts. the parts are separated so ["ab", "c"]
+// and ["a", "bc"] give different keys.
+pub fn key(parts: &[&[u8]]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut feed = |byte: u8| {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    };
+
+    CACHE_VERSION.iter().for_each(|&byte| feed(byte));
+    for part in parts {
+        (part.len() as u64)
+            .to_le_bytes()
+            .iter()
+            .for_each(|&byte| feed(byte));
+        part.iter().for_each(|&byte| feed(byte));
+    }
+
+    hash
+}
+
+// returns None if there is no entry or it's not valid SPIR-V
+pub fn load(key: u64) -> Option<Vec<u32>> {
+    let bytes = fs::read(entry_path(key)?).ok()?;
+    spirv_from_bytes(&bytes)
+}
+
+// does nothing if the cache is turned off. the error is for the caller to
+// decide on, failing to write the cache isn't fatal: the shader will just be
+// compiled again next time.
+pub fn store(key: u64, words: &[u32]) -> io::Result<()> {
+    let path = match entry_path(key) {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+
+    let bytes = spirv_to_bytes(words);
+
+    // write to a temporary file first so a crash halfway through can't leave
+    // a broken entry behind
+    let tmp_path = path.with_extension(format!("tmp{}", std::process::id()));
+    path.parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&tmp_path, &bytes))
+        .and_then(|_| fs::rename(&tmp_path, &path))
+        .map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("Couldn't write SPIR-V cache entry {:?}: {}", path, e),
+            )
+        })
+}
+
+fn entry_path(key: u64) -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join(format!("{:016x}.spv", key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARTS: [&[u8]; 3] = [b"void main() {}", b"vertex", b"main"];
+
+    // entries written by earlier runs, or other builds, have to keep being
+    // found. if this changes on purpose, bump CACHE_VERSION.
+    #[test]
+    fn keys_are_stable() {
+        assert_eq!(key(&PARTS), 0xcd4e_506c_c7b4_5494);
+    }
+
+    #[test]
+    fn keys_depend_on_order() {
+        assert_ne!(key(&PARTS), key(&[PARTS[1], PARTS[0], PARTS[2]]));
+        assert_ne!(key(&[b"vertex", b"main"]), key(&[b"main", b"vertex"]));
+    }
+
+    #[test]
+    fn keys_depend_on_where_parts_split() {
+        assert_ne!(key(&[b"ab", b"c"]), key(&[b"a", b"bc"]));
+        assert_ne!(key(&[b"abc"]), key(&[b"abc", b""]));
+        assert_ne!(key(&[]), key(&[b""]));
+    }
+
+    #[test]
+    fn stored_entries_load_again() {
+        let dir =
+            std::env::temp_dir().join(format!("render-engine-cache-test-{}", std::process::id()));
+        std::env::set_var("RENDER_ENGINE_CACHE_DIR", &dir);
+
+        let words = [0x0723_0203, 0x0001_0000, 0, 1, 0];
+        let key = key(&PARTS);
+        store(key, &words).unwrap();
+        assert_eq!(load(key), Some(words.to_vec()));
+        assert_eq!(load(key.wrapping_add(1)), None);
+
+        std::env::remove_var("RENDER_ENGINE_CACHE_DIR");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
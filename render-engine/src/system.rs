@@ -10,11 +10,16 @@ use vulkano::sync::GpuFuture;
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use crate::collection::{CollectionData, SetLayers};
 use crate::collection_cache::CollectionCache;
+use crate::cube_target::{CubeImages, CubeTarget};
 use crate::object::Drawcall;
 use crate::pipeline_cache::PipelineCache;
 use crate::render_passes::clear_values_for_pass;
+use crate::resources::Resources;
+use crate::sampler::SamplerDesc;
 use crate::utils::Timer;
+use crate::validation::MAX_PUSH_CONSTANTS_SIZE;
 use crate::window::Window;
 
 // TODO: make the whole thing less prone to runtime panics. vecs of strings are
@@ -33,7 +38,20 @@ pub struct System<'a> {
     pub output_tag: &'a str,
     cached_images: Option<HashMap<String, Arc<dyn ImageViewAccess + Send + Sync>>>,
     pub custom_images: HashMap<&'a str, Arc<dyn ImageViewAccess + Send + Sync>>,
+    // cubemaps rendered one face per pass, see cube_target.rs
+    pub cube_targets: Vec<CubeTarget<'a>>,
+    // the images for each cube target, by tag. they don't depend on the
+    // window's size, so they're only created once.
+    cube_images: HashMap<String, CubeImages>,
+    // if true, shaders are recompiled whenever their files change on disk
+    pub hot_reload: bool,
+    // where the globals and pass images are bound. objects have their own
+    // copy, which has to match.
+    pub set_layers: SetLayers,
     state: DrawState,
+    // the uniform buffer pool and samplers every set is uploaded with
+    resources: Arc<Resources>,
+    frames: u32,
     pass_timers: Vec<Timer>,
     cmd_buf_timer: Timer,
     present_timer: Timer,
@@ -41,6 +59,9 @@ pub struct System<'a> {
     setup_timer: Timer,
 }
 
+// there's only ever one of these, so the size of the Drawing variant doesn't
+// matter
+#[allow(clippy::large_enum_variant)]
 enum DrawState {
     Uninitialized,
     Drawing {
@@ -63,7 +84,7 @@ enum DrawState {
 // shaders read from. This does NOT mean textures! Data you want to feed your
 // shaders from the CPU, whether in the form of buffers or images, should go in
 // Object's custom_set field. The images listed in images_needed will be fed to
-// the vertex shader of every object drawn.
+// the vertex shader of every object drawn, at set_layers.pass_images.
 
 // Often drawing a frame requires multiple vertex and fragment shaders operating
 // in sequence. This what System is for.
@@ -83,8 +104,9 @@ impl<'a> System<'a> {
     ) -> Self {
         let device = queue.device().clone();
 
+        let resources = Resources::new(queue.clone());
         let pipeline_caches = pipe_caches_for_passes(device.clone(), &passes);
-        let collection_cache = CollectionCache::new(device.clone());
+        let collection_cache = CollectionCache::new(resources.clone());
         let pass_timers = passes.iter().map(|pass| Timer::new(pass.name)).collect();
 
         Self {
@@ -96,7 +118,13 @@ impl<'a> System<'a> {
             output_tag,
             cached_images: None,
             custom_images,
+            cube_targets: vec![],
+            cube_images: HashMap::new(),
+            hot_reload: true,
+            set_layers: SetLayers::default(),
             state: DrawState::Uninitialized,
+            resources,
+            frames: 0,
             pass_timers,
             cmd_buf_timer: Timer::new("command buffer"),
             present_timer: Timer::new("present to window"),
@@ -107,6 +135,11 @@ impl<'a> System<'a> {
 
     pub fn start(&mut self, dest_image: Arc<dyn ImageViewAccess + Send + Sync>) {
         self.setup_timer.start();
+        self.frames += 1;
+
+        if self.hot_reload {
+            self.reload_shaders();
+        }
 
         // all images will be created with the same dimensions as the
         // destination image. if you need to use an image with a different
@@ -122,6 +155,19 @@ impl<'a> System<'a> {
         // replace destination image with the real one
         images.insert(self.output_tag.to_string(), dest_image);
 
+        for target in self.cube_targets.iter() {
+            let device = self.device.clone();
+            let cube_images = self
+                .cube_images
+                .entry(target.tag.to_string())
+                .or_insert_with(|| target.create_images(device));
+
+            images.insert(target.tag.to_string(), cube_images.cube());
+            for (tag, face) in target.face_tags.iter().zip(cube_images.faces()) {
+                images.insert(tag.to_string(), face);
+            }
+        }
+
         // use any custom images to replace existing ones
         for (tag, image) in self.custom_images.iter() {
             images.insert(tag.to_string(), image.clone());
@@ -132,6 +178,7 @@ impl<'a> System<'a> {
         // when you begin rendering, you automatically enter the first pass (for
         // which the first framebuffer is used)
         let first_framebuffer = framebuffers[0].clone();
+        let first_dims = framebuffer_dims(&first_framebuffer);
         let first_render_pass = self.passes[0].render_pass.clone();
 
         let clear_values = clear_values_for_pass(first_render_pass);
@@ -150,8 +197,7 @@ impl<'a> System<'a> {
             pass_idx: 0,
             images,
             framebuffers,
-            // TODO: support passes with different dimensions
-            cur_dims: dimensions,
+            cur_dims: first_dims,
         };
 
         self.setup_timer.stop();
@@ -159,6 +205,35 @@ impl<'a> System<'a> {
         self.pass_timers[0].start();
     }
 
+    // recompiles the shaders of any pipelines whose files were modified. the
+    // collections created for the old pipelines are thrown away, because they
+    // were created with the old pipeline's layout.
+    pub fn reload_shaders(&mut self) {
+        for pipeline_cache in self.pipeline_caches.iter_mut() {
+            for spec in pipeline_cache.reload_changed() {
+                self.collection_cache.invalidate(&spec);
+            }
+        }
+    }
+
+    // data bound for every object drawn from now on, usually the camera and
+    // lights. call it again whenever the data changes, like once per frame.
+    pub fn set_globals<D: CollectionData + 'static>(&mut self, globals: D) {
+        self.collection_cache.set_globals(globals);
+    }
+
+    // the sampler used when a pass reads the image with the given tag. by
+    // default pass images are sampled with SamplerDesc::clamped. for example,
+    // a shadow map sampled with sampler2DShadow needs SamplerDesc::shadow.
+    pub fn set_sampler(&mut self, tag: &str, sampler: SamplerDesc) {
+        self.collection_cache.set_sampler(tag, sampler);
+    }
+
+    // for building objects and uploading their sets
+    pub fn resources(&self) -> Arc<Resources> {
+        self.resources.clone()
+    }
+
     pub fn start_window(&mut self, window: &mut Window) {
         self.acquire_timer.start();
         let swapchain_image = window.next_image();
@@ -189,19 +264,41 @@ impl<'a> System<'a> {
                     dynamic_state_for_dimensions(cur_dims)
                 };
 
-                let pipeline = self.pipeline_caches[pass_idx].get(object.pipe_spec());
+                // the object's sets were validated and uploaded for its own
+                // layers, so binding them at the system's would be wrong
+                assert_eq!(
+                    object.set_layers(),
+                    self.set_layers,
+                    "the object using {:?} was built with different set layers than the system's",
+                    object.pipe_spec().vs_path
+                );
+
+                let pipeline = self.pipeline_caches[pass_idx]
+                    .get(object.pipe_spec())
+                    .unwrap_or_else(|e| panic!("{}", e));
 
-                let mut collection = self.collection_cache.get(
+                let collection = self.collection_cache.get(
                     object.pipe_spec(),
                     pipeline.clone(),
                     &self.passes[pass_idx],
                     &images,
+                    self.set_layers,
+                    object.collection(),
                 );
 
-                let mut obj_collection =
-                    object.collection();
-
-                collection.append(&mut obj_collection);
+                // vulkano reads as many bytes as the pipeline's push constant
+                // ranges cover straight out of whatever it's given, so the
+                // bytes have to be in a fixed-size array
+                let mut push_constants = [0u8; MAX_PUSH_CONSTANTS_SIZE];
+                if let Some(bytes) = object.push_constants() {
+                    assert!(
+                        bytes.len() <= MAX_PUSH_CONSTANTS_SIZE,
+                        "push constants are {} bytes, the maximum is {}",
+                        bytes.len(),
+                        MAX_PUSH_CONSTANTS_SIZE
+                    );
+                    push_constants[..bytes.len()].copy_from_slice(bytes);
+                }
 
                 cmd_buf = cmd_buf
                     .draw_indexed(
@@ -210,12 +307,14 @@ impl<'a> System<'a> {
                         vec![object.vbuf()],
                         object.ibuf(),
                         collection,
-                        (),
+                        push_constants,
                     )
-                    .expect(&format!(
-                        "error building cmd buf, in pass {}",
-                        self.passes[pass_idx].name
-                    ));
+                    .unwrap_or_else(|e| {
+                        panic!(
+                            "error building cmd buf, in pass {}: {:?}",
+                            self.passes[pass_idx].name, e
+                        )
+                    });
 
                 // give state a real value again
                 self.state = DrawState::Drawing {
@@ -241,19 +340,19 @@ impl<'a> System<'a> {
                 mut pass_idx,
                 images,
                 framebuffers,
-                cur_dims,
+                ..
             } => {
                 self.pass_timers[pass_idx].stop();
+                cmd_buf = self.copy_finished_cubes(cmd_buf.end_render_pass().unwrap(), pass_idx);
                 pass_idx += 1;
                 self.pass_timers[pass_idx].start();
 
                 let framebuffer = framebuffers[pass_idx].clone();
+                let cur_dims = framebuffer_dims(&framebuffer);
                 let render_pass = self.passes[pass_idx].render_pass.clone();
                 let clear_values = clear_values_for_pass(render_pass);
 
                 cmd_buf = cmd_buf
-                    .end_render_pass()
-                    .unwrap()
                     .begin_render_pass(framebuffer, false, clear_values)
                     .unwrap();
 
@@ -274,24 +373,21 @@ impl<'a> System<'a> {
 
         let state = std::mem::replace(&mut self.state, DrawState::Uninitialized);
 
-        let fut = match state {
+        match state {
             DrawState::Uninitialized => panic!("Can't finish render without having begun it"),
             DrawState::Drawing {
                 cmd_buf, pass_idx, ..
             } => {
                 self.pass_timers[pass_idx].stop();
+                let cmd_buf =
+                    self.copy_finished_cubes(cmd_buf.end_render_pass().unwrap(), pass_idx);
                 Box::new(
                     future
-                        .then_execute(
-                            self.queue.clone(),
-                            cmd_buf.end_render_pass().unwrap().build().unwrap(),
-                        )
+                        .then_execute(self.queue.clone(), cmd_buf.build().unwrap())
                         .unwrap(),
                 )
             }
-        };
-
-        fut
+        }
     }
 
     pub fn finish_to_window(&mut self, window: &mut Window) {
@@ -304,7 +400,7 @@ impl<'a> System<'a> {
         self.present_timer.stop();
     }
 
-    pub fn get_passes(&self) -> &[Pass] {
+    pub fn get_passes(&self) -> &[Pass<'_>] {
         &self.passes
     }
 
@@ -317,6 +413,20 @@ impl<'a> System<'a> {
         self.acquire_timer.print();
         self.pass_timers.iter().for_each(|timer| timer.print());
 
+        // includes uploads of objects' own sets, as long as they were made
+        // with this system's resources
+        let pool_stats = self.resources.uniform_pool.stats();
+        println!(
+            "uniform buffer pool: {} uploads, {} allocations",
+            pool_stats.uploads, pool_stats.allocations
+        );
+        if self.frames > 0 {
+            println!(
+                "{} uploads per frame",
+                pool_stats.uploads as f32 / self.frames as f32
+            );
+        }
+
         println!();
 
         (0..self.passes.len()).for_each(|idx| {
@@ -329,6 +439,28 @@ impl<'a> System<'a> {
         println!();
     }
 
+    // copies the faces of every cube target whose last face was drawn in the
+    // given pass into the cube
+    fn copy_finished_cubes(
+        &self,
+        mut cmd_buf: AutoCommandBufferBuilder,
+        pass_idx: usize,
+    ) -> AutoCommandBufferBuilder {
+        for target in self.cube_targets.iter() {
+            let last_face_pass = self.passes.iter().rposition(|pass| {
+                pass.images_created_tags
+                    .iter()
+                    .any(|tag| target.face_tags.contains(tag))
+            });
+
+            if last_face_pass == Some(pass_idx) {
+                cmd_buf = self.cube_images[target.tag].copy_faces(cmd_buf);
+            }
+        }
+
+        cmd_buf
+    }
+
     fn get_images(
         &mut self,
         dimensions: [u32; 2],
@@ -351,11 +483,19 @@ impl<'a> System<'a> {
         } else {
             let new = images_for_passes(self.device.clone(), dimensions, &self.passes);
             self.cached_images = Some(new.clone());
+            // sets reading the old images would never be hit again, and keep
+            // the old images alive
+            self.collection_cache.clear_pass_images();
             new
         }
     }
 }
 
+fn framebuffer_dims(framebuffer: &Arc<dyn FramebufferAbstract + Send + Sync>) -> [u32; 2] {
+    let [width, height, _] = framebuffer.dimensions();
+    [width, height]
+}
+
 fn create_image_for_desc(
     device: Arc<Device>,
     dimensions: [u32; 2],
@@ -429,10 +569,10 @@ fn fb_from_images(
     }
 }
 
-fn images_for_passes<'a>(
+fn images_for_passes(
     device: Arc<Device>,
     dimensions: [u32; 2],
-    passes: &'a [Pass],
+    passes: &[Pass],
 ) -> HashMap<String, Arc<dyn ImageViewAccess + Send + Sync>> {
     // for now this ignores the fact that the output image is special and
     // provided from outside System if drawing to a window. any users of this
@@ -454,16 +594,16 @@ This means the image description for each tag cannot be determined from the rend
             let desc = pass
                 .render_pass
                 .attachment_desc(image_idx)
-                .expect(&format!(
-                    "Couldn't get the attachment description when creating images for passes.
+                .unwrap_or_else(|| {
+                    panic!(
+                        "Couldn't get the attachment description when creating images for passes.
 
 Dimensions we're supposed to be creating for: {:?}
 Image index we tried to create: {}
 Image tag: {}",
-                    dimensions,
-                    image_idx,
-                    image_tag,
-                ));
+                        dimensions, image_idx, image_tag,
+                    )
+                });
 
             // FIXME: yeah this needs a better solution
             let image = if image_tag.contains("lowres") {
@@ -479,9 +619,9 @@ Image tag: {}",
     images
 }
 
-fn framebuffers_for_passes<'a>(
+fn framebuffers_for_passes(
     images: HashMap<String, Arc<dyn ImageViewAccess + Send + Sync>>,
-    passes: &'a [Pass],
+    passes: &[Pass],
 ) -> Vec<Arc<dyn FramebufferAbstract + Send + Sync>> {
     let mut framebuffers = vec![];
 
@@ -491,7 +631,7 @@ fn framebuffers_for_passes<'a>(
             .iter()
             .map(|tag| {
                 images
-                    .get(&tag.to_string())
+                    .get(*tag)
                     .expect("Couldn't get image when creating framebuffers for passes")
                     .clone()
             })
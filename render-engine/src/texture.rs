@@ -0,0 +1,460 @@
+// Uploading textures with a full chain of mipmaps, so they don't shimmer when
+// seen from far away.
+
+// The mipmaps are made on the GPU with blits when the format is guaranteed to
+// support them, and on the CPU otherwise. Every level is blitted from the one
+// above it. Blitting between two levels of the same image doesn't work with
+// vulkano's automatic synchronization, which treats the whole image as one
+// resource in one layout, so each level is first made in a temporary image and
+// then copied into the texture.
+
+// Cubemaps are uploaded the same way, with each face as a separate layer. They
+// can be made from six images or from a single equirectangular one.
+
+// Textures that already have their mipmaps, like the ones in KTX2 and DDS
+// files, are uploaded level by level instead, see upload_mip_chain.
+
+// The data is converted to fit the format first, so an 8-bit image can still
+// be uploaded as R16G16B16A16Sfloat and a grayscale one as R8G8B8A8Srgb. See
+// texture_data.rs.
+
+use image::{ImageBuffer, Pixel};
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::command_buffer::{AutoCommandBuffer, AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::image::immutable::ImmutableImageInitialization;
+use vulkano::image::{
+    AttachmentImage, Dimensions, ImageLayout, ImageUsage, ImageViewAccess, ImmutableImage,
+    MipmapsCount,
+};
+use vulkano::sampler::Filter;
+use vulkano::sync::GpuFuture;
+
+use crate::texture_data::TextureData;
+use crate::Image;
+
+use std::sync::Arc;
+
+type Init = Arc<ImmutableImageInitialization<Format>>;
+
+// uploads an image as the given format, with as many mipmaps as asked for.
+// MipmapsCount::Log2 gives the full chain down to 1x1. use
+// data.native_format() to upload it without converting anything.
+pub fn upload_texture(
+    queue: Arc<Queue>,
+    data: TextureData,
+    format: Format,
+    mipmaps: MipmapsCount,
+) -> Arc<dyn ImageViewAccess + Send + Sync> {
+    let (width, height) = data.dimensions();
+
+    upload_layers(
+        queue,
+        vec![data],
+        Dimensions::Dim2d { width, height },
+        format,
+        mipmaps,
+    )
+}
+
+// the faces have to be square, the same size, and in the order +X, -X, +Y, -Y,
+// +Z, -Z. they're sampled with samplerCube in GLSL.
+pub fn upload_cubemap(
+    queue: Arc<Queue>,
+    faces: Vec<TextureData>,
+    format: Format,
+    mipmaps: MipmapsCount,
+) -> Arc<dyn ImageViewAccess + Send + Sync> {
+    assert_eq!(faces.len(), 6, "a cubemap needs 6 faces");
+    let (size, _) = faces[0].dimensions();
+    assert!(
+        faces.iter().all(|face| face.dimensions() == (size, size)),
+        "cubemap faces have to be square and all the same size"
+    );
+
+    upload_layers(queue, faces, Dimensions::Cubemap { size }, format, mipmaps)
+}
+
+// uploads a 2D texture whose mip levels were made beforehand. each level is
+// the raw bytes for that level in the given format, biggest first, so this
+// works for block-compressed formats too.
+pub fn upload_mip_chain(
+    queue: Arc<Queue>,
+    width: u32,
+    height: u32,
+    format: Format,
+    levels: Vec<Vec<u8>>,
+) -> Arc<dyn ImageViewAccess + Send + Sync> {
+    let builder =
+        AutoCommandBufferBuilder::primary_one_time_submit(queue.device().clone(), queue.family())
+            .unwrap();
+    let (builder, texture) = record_mip_chain(builder, &queue, width, height, format, levels);
+    submit_and_wait(builder, queue);
+
+    texture
+}
+
+// turns an equirectangular (latitude-longitude) panorama into 6 cubemap faces,
+// for upload_cubemap. +Y is up, and the middle of the panorama faces -Z. the
+// faces keep the panorama's channels and bit depth.
+pub fn equirectangular_to_faces(panorama: &TextureData, face_size: u32) -> Vec<TextureData> {
+    match panorama {
+        TextureData::Luma8(image) => faces_of(image, face_size, TextureData::Luma8),
+        TextureData::LumaA8(image) => faces_of(image, face_size, TextureData::LumaA8),
+        TextureData::Rgba8(image) => faces_of(image, face_size, TextureData::Rgba8),
+        TextureData::Luma16(image) => faces_of(image, face_size, TextureData::Luma16),
+        TextureData::LumaA16(image) => faces_of(image, face_size, TextureData::LumaA16),
+        TextureData::Rgba16(image) => faces_of(image, face_size, TextureData::Rgba16),
+        TextureData::RgbaF32(image) => faces_of(image, face_size, TextureData::RgbaF32),
+    }
+}
+
+type Buffer<P> = ImageBuffer<P, Vec<<P as Pixel>::Subpixel>>;
+
+fn faces_of<P: Pixel + 'static>(
+    panorama: &Buffer<P>,
+    face_size: u32,
+    wrap: fn(Buffer<P>) -> TextureData,
+) -> Vec<TextureData> {
+    (0..6)
+        .map(|face| {
+            let image = ImageBuffer::from_fn(face_size, face_size, |x, y| {
+                // -1 to 1 across the face, through the middle of each pixel
+                let u = (x as f32 + 0.5) / face_size as f32 * 2.0 - 1.0;
+                let v = (y as f32 + 0.5) / face_size as f32 * 2.0 - 1.0;
+                let direction = cube_direction(face, u, v);
+
+                sample_equirectangular(panorama, direction)
+            });
+
+            wrap(image)
+        })
+        .collect()
+}
+
+// the direction a point on a face points in, as laid out in the Vulkan spec
+fn cube_direction(face: u32, u: f32, v: f32) -> [f32; 3] {
+    match face {
+        0 => [1.0, -v, -u],
+        1 => [-1.0, -v, u],
+        2 => [u, 1.0, v],
+        3 => [u, -1.0, -v],
+        4 => [u, -v, 1.0],
+        _ => [-u, -v, -1.0],
+    }
+}
+
+fn sample_equirectangular<P: Pixel + 'static>(panorama: &Buffer<P>, direction: [f32; 3]) -> P {
+    use std::f32::consts::PI;
+
+    let [x, y, z] = direction;
+    let length = (x * x + y * y + z * z).sqrt();
+    let longitude = x.atan2(-z);
+    let latitude = (y / length).acos();
+
+    let (width, height) = panorama.dimensions();
+    let px = ((longitude / (2.0 * PI) + 0.5) * width as f32) as u32 % width;
+    let py = ((latitude / PI * height as f32) as u32).min(height - 1);
+
+    *panorama.get_pixel(px, py)
+}
+
+fn upload_layers(
+    queue: Arc<Queue>,
+    layers: Vec<TextureData>,
+    dimensions: Dimensions,
+    format: Format,
+    mipmaps: MipmapsCount,
+) -> Arc<dyn ImageViewAccess + Send + Sync> {
+    let builder =
+        AutoCommandBufferBuilder::primary_one_time_submit(queue.device().clone(), queue.family())
+            .unwrap();
+    let (builder, texture) = record_layers(builder, &queue, layers, dimensions, format, mipmaps);
+    submit_and_wait(builder, queue);
+
+    texture
+}
+
+// the recording half of upload_layers, so several textures can share a
+// command buffer. the data is only valid once the command buffer is done.
+pub(crate) fn record_layers(
+    mut builder: AutoCommandBufferBuilder,
+    queue: &Arc<Queue>,
+    layers: Vec<TextureData>,
+    dimensions: Dimensions,
+    format: Format,
+    mipmaps: MipmapsCount,
+) -> (AutoCommandBufferBuilder, Image) {
+    let device = queue.device().clone();
+    let usage = ImageUsage {
+        transfer_source: true,
+        transfer_destination: true,
+        sampled: true,
+        ..ImageUsage::none()
+    };
+
+    let (texture, init) = ImmutableImage::uninitialized(
+        device.clone(),
+        dimensions,
+        format,
+        mipmaps,
+        usage,
+        ImageLayout::ShaderReadOnlyOptimal,
+        device.active_queue_families(),
+    )
+    .unwrap();
+    let init = Arc::new(init);
+    let levels = texture.mipmap_levels();
+    let layers: Vec<_> = layers
+        .into_iter()
+        .map(|layer| layer.convert_for(format))
+        .collect();
+
+    for (layer, image) in layers.iter().enumerate() {
+        builder = if levels > 1 && supports_linear_blit(format) {
+            blit_mips(builder, queue, &init, image, layer as u32, format, levels)
+        } else {
+            cpu_mips(builder, queue, &init, image, layer as u32, format, levels)
+        };
+    }
+
+    (builder, texture)
+}
+
+// the recording half of upload_mip_chain
+pub(crate) fn record_mip_chain(
+    mut builder: AutoCommandBufferBuilder,
+    queue: &Arc<Queue>,
+    width: u32,
+    height: u32,
+    format: Format,
+    levels: Vec<Vec<u8>>,
+) -> (AutoCommandBufferBuilder, Image) {
+    let device = queue.device().clone();
+    let usage = ImageUsage {
+        transfer_destination: true,
+        sampled: true,
+        ..ImageUsage::none()
+    };
+
+    let (texture, init) = ImmutableImage::uninitialized(
+        device.clone(),
+        Dimensions::Dim2d { width, height },
+        format,
+        MipmapsCount::Specific(levels.len() as u32),
+        usage,
+        ImageLayout::ShaderReadOnlyOptimal,
+        device.active_queue_families(),
+    )
+    .unwrap();
+    let init = Arc::new(init);
+
+    for (level, data) in levels.into_iter().enumerate() {
+        let [level_width, level_height] = mip_dimensions(width, height, level as u32);
+
+        builder = builder
+            .copy_buffer_to_image_dimensions(
+                staging_buffer(device.clone(), data),
+                init.clone(),
+                [0, 0, 0],
+                [level_width, level_height, 1],
+                0,
+                1,
+                level as u32,
+            )
+            .unwrap();
+    }
+
+    (builder, texture)
+}
+
+pub(crate) fn submit_and_wait(builder: AutoCommandBufferBuilder, queue: Arc<Queue>) {
+    let command_buffer: AutoCommandBuffer = builder.build().unwrap();
+
+    command_buffer
+        .execute(queue)
+        .unwrap()
+        .then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+}
+
+// level 0 is copied into the texture and a temporary image. every level after
+// that is blitted from the previous temporary image into a new one, which is
+// then copied into the texture.
+fn blit_mips(
+    builder: AutoCommandBufferBuilder,
+    queue: &Arc<Queue>,
+    init: &Init,
+    image: &TextureData,
+    layer: u32,
+    format: Format,
+    levels: u32,
+) -> AutoCommandBufferBuilder {
+    let device = queue.device().clone();
+    let (width, height) = image.dimensions();
+    let usage = ImageUsage {
+        transfer_source: true,
+        transfer_destination: true,
+        ..ImageUsage::none()
+    };
+    let staging = staging_buffer(device.clone(), image.to_bytes(format));
+
+    let mut previous =
+        AttachmentImage::with_usage(device.clone(), [width, height], format, usage).unwrap();
+    let mut builder = builder
+        .copy_buffer_to_image_dimensions(
+            staging.clone(),
+            init.clone(),
+            [0, 0, 0],
+            [width, height, 1],
+            layer,
+            1,
+            0,
+        )
+        .unwrap()
+        .copy_buffer_to_image(staging, previous.clone())
+        .unwrap();
+
+    for level in 1..levels {
+        let [prev_width, prev_height] = mip_dimensions(width, height, level - 1);
+        let [level_width, level_height] = mip_dimensions(width, height, level);
+        let current =
+            AttachmentImage::with_usage(device.clone(), [level_width, level_height], format, usage)
+                .unwrap();
+        let level_corner = [level_width as i32, level_height as i32, 1];
+
+        builder = builder
+            .blit_image(
+                previous.clone(),
+                [0, 0, 0],
+                [prev_width as i32, prev_height as i32, 1],
+                0,
+                0,
+                current.clone(),
+                [0, 0, 0],
+                level_corner,
+                0,
+                0,
+                1,
+                Filter::Linear,
+            )
+            .unwrap()
+            .blit_image(
+                current.clone(),
+                [0, 0, 0],
+                level_corner,
+                0,
+                0,
+                init.clone(),
+                [0, 0, 0],
+                level_corner,
+                layer,
+                level,
+                1,
+                Filter::Nearest,
+            )
+            .unwrap();
+
+        previous = current;
+    }
+
+    builder
+}
+
+fn cpu_mips(
+    mut builder: AutoCommandBufferBuilder,
+    queue: &Arc<Queue>,
+    init: &Init,
+    image: &TextureData,
+    layer: u32,
+    format: Format,
+    levels: u32,
+) -> AutoCommandBufferBuilder {
+    let device = queue.device().clone();
+    let (width, height) = image.dimensions();
+
+    for (level, data) in cpu_levels(image, format, levels).into_iter().enumerate() {
+        let [level_width, level_height] = mip_dimensions(width, height, level as u32);
+
+        builder = builder
+            .copy_buffer_to_image_dimensions(
+                staging_buffer(device.clone(), data),
+                init.clone(),
+                [0, 0, 0],
+                [level_width, level_height, 1],
+                layer,
+                1,
+                level as u32,
+            )
+            .unwrap();
+    }
+
+    builder
+}
+
+// the bytes of each mip level, made on the CPU. every level is resized from
+// the full image rather than the level above it, which is slower but doesn't
+// blur any more than it has to. the resizing happens in whatever space the
+// data is in, so sRGB textures get slightly darker mipmaps than they would
+// with blits.
+pub(crate) fn cpu_levels(image: &TextureData, format: Format, levels: u32) -> Vec<Vec<u8>> {
+    let (width, height) = image.dimensions();
+
+    (0..levels)
+        .map(|level| {
+            if level == 0 {
+                image.to_bytes(format)
+            } else {
+                let [level_width, level_height] = mip_dimensions(width, height, level);
+                image.resize(level_width, level_height).to_bytes(format)
+            }
+        })
+        .collect()
+}
+
+fn staging_buffer(device: Arc<Device>, data: Vec<u8>) -> Arc<CpuAccessibleBuffer<[u8]>> {
+    CpuAccessibleBuffer::from_iter(device, BufferUsage::transfer_source(), data.into_iter())
+        .unwrap()
+}
+
+pub fn mip_dimensions(width: u32, height: u32, level: u32) -> [u32; 2] {
+    [(width >> level).max(1), (height >> level).max(1)]
+}
+
+// how many levels an image of this size gets
+pub fn mip_level_count(width: u32, height: u32, mipmaps: MipmapsCount) -> u32 {
+    let full_chain = 32 - width.max(height).leading_zeros();
+
+    match mipmaps {
+        MipmapsCount::Log2 => full_chain,
+        MipmapsCount::One => 1,
+        MipmapsCount::Specific(levels) => levels.min(full_chain),
+    }
+}
+
+// formats that every Vulkan implementation has to support blitting from, to
+// and with linear filtering (and rendering to, which the temporary images
+// need). vulkano 0.14 can't ask the device about any others.
+pub(crate) fn supports_linear_blit(format: Format) -> bool {
+    matches!(
+        format,
+        Format::R5G6B5UnormPack16
+            | Format::A1R5G5B5UnormPack16
+            | Format::R8Unorm
+            | Format::R8G8Unorm
+            | Format::R8G8B8A8Unorm
+            | Format::R8G8B8A8Srgb
+            | Format::B8G8R8A8Unorm
+            | Format::B8G8R8A8Srgb
+            | Format::A8B8G8R8UnormPack32
+            | Format::A8B8G8R8SrgbPack32
+            | Format::A2B10G10R10UnormPack32
+            | Format::R16Sfloat
+            | Format::R16G16Sfloat
+            | Format::R16G16B16A16Sfloat
+            | Format::B10G11R11UfloatPack32
+    )
+}
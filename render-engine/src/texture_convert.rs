@@ -0,0 +1,243 @@
+// Converts images into block-compressed textures with a full chain of mipmaps,
+// ahead of time, for re-texture-convert. The results are meant to be written
+// as KTX2 files and loaded with load_texture like any other image.
+
+// Everything here is deterministic: the mipmaps are made on the CPU from the
+// full image and compressed single-threaded, so converting the same image with
+// the same options always gives the same bytes, and the files can be checked
+// in and compared by hash.
+
+// Normal maps can be stored as only their X and Y in BC5, which keeps much
+// more detail than BC1 or BC3. Shaders then have to rebuild Z themselves, with
+// z = sqrt(1 - x * x - y * y) after mapping x and y to -1..1.
+
+use image::{ImageBuffer, Rgba, RgbaImage};
+use vulkano::format::Format;
+
+use crate::compressed::CompressedTexture;
+use crate::texture::mip_dimensions;
+use crate::texture_data::TextureData;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlockFormat {
+    // RGB with 1-bit alpha, 4 bits per pixel
+    Bc1,
+    // RGBA, 8 bits per pixel
+    Bc3,
+    // a single channel (red), 4 bits per pixel
+    Bc4,
+    // two channels (red and green), 8 bits per pixel
+    Bc5,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ConvertOptions {
+    // picked from the image if not given: BC5 for normal maps, BC4 for
+    // grayscale images, BC3 for images with any transparency and BC1 for the
+    // rest
+    pub format: Option<BlockFormat>,
+    // for colour textures. BC4 and BC5 have no sRGB versions and ignore this.
+    pub srgb: bool,
+    // renormalizes every mip level and keeps only X and Y
+    pub normal_map: bool,
+}
+
+impl BlockFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "bc1" => Some(BlockFormat::Bc1),
+            "bc3" => Some(BlockFormat::Bc3),
+            "bc4" => Some(BlockFormat::Bc4),
+            "bc5" => Some(BlockFormat::Bc5),
+            _ => None,
+        }
+    }
+
+    fn vulkan_format(self, srgb: bool) -> Format {
+        match (self, srgb) {
+            (BlockFormat::Bc1, false) => Format::BC1_RGBAUnormBlock,
+            (BlockFormat::Bc1, true) => Format::BC1_RGBASrgbBlock,
+            (BlockFormat::Bc3, false) => Format::BC3UnormBlock,
+            (BlockFormat::Bc3, true) => Format::BC3SrgbBlock,
+            (BlockFormat::Bc4, _) => Format::BC4UnormBlock,
+            (BlockFormat::Bc5, _) => Format::BC5UnormBlock,
+        }
+    }
+
+    fn texpresso_format(self) -> texpresso::Format {
+        match self {
+            BlockFormat::Bc1 => texpresso::Format::Bc1,
+            BlockFormat::Bc3 => texpresso::Format::Bc3,
+            BlockFormat::Bc4 => texpresso::Format::Bc4,
+            BlockFormat::Bc5 => texpresso::Format::Bc5,
+        }
+    }
+}
+
+pub fn convert(data: TextureData, options: &ConvertOptions) -> CompressedTexture {
+    let format = options
+        .format
+        .unwrap_or_else(|| pick_format(&data, options.normal_map));
+    let (width, height) = data.dimensions();
+    let full = match data.convert_for(Format::R8G8B8A8Unorm) {
+        TextureData::Rgba8(image) => image,
+        _ => unreachable!(),
+    };
+
+    let level_count = 32 - width.max(height).leading_zeros();
+    let levels = (0..level_count)
+        .map(|level| {
+            let [level_width, level_height] = mip_dimensions(width, height, level);
+            let image = if level == 0 {
+                full.clone()
+            } else {
+                image::imageops::resize(
+                    &full,
+                    level_width,
+                    level_height,
+                    image::imageops::FilterType::Triangle,
+                )
+            };
+            let image = if options.normal_map {
+                renormalize(&image)
+            } else {
+                image
+            };
+
+            compress(&image, format)
+        })
+        .collect();
+
+    CompressedTexture {
+        format: format.vulkan_format(options.srgb),
+        width,
+        height,
+        levels,
+        colour_space_known: true,
+    }
+}
+
+fn pick_format(data: &TextureData, normal_map: bool) -> BlockFormat {
+    if normal_map {
+        return BlockFormat::Bc5;
+    }
+
+    match data {
+        TextureData::Luma8(_) | TextureData::Luma16(_) => BlockFormat::Bc4,
+        _ => {
+            let rgba = match data.clone().convert_for(Format::R8G8B8A8Unorm) {
+                TextureData::Rgba8(image) => image,
+                _ => unreachable!(),
+            };
+
+            if rgba.pixels().any(|pixel| pixel[3] < 255) {
+                BlockFormat::Bc3
+            } else {
+                BlockFormat::Bc1
+            }
+        }
+    }
+}
+
+// filtering averages the normals, which makes them shorter than 1
+fn renormalize(image: &RgbaImage) -> RgbaImage {
+    let to_unit = |value: u8| f32::from(value) / 255.0 * 2.0 - 1.0;
+    let to_u8 = |value: f32| ((value * 0.5 + 0.5) * 255.0).round() as u8;
+
+    ImageBuffer::from_fn(image.width(), image.height(), |x, y| {
+        let pixel = image.get_pixel(x, y);
+        let (nx, ny, nz) = (to_unit(pixel[0]), to_unit(pixel[1]), to_unit(pixel[2]));
+        let length = (nx * nx + ny * ny + nz * nz).sqrt().max(1e-6);
+
+        Rgba([
+            to_u8(nx / length),
+            to_u8(ny / length),
+            to_u8(nz / length),
+            255,
+        ])
+    })
+}
+
+fn compress(image: &RgbaImage, format: BlockFormat) -> Vec<u8> {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let format = format.texpresso_format();
+    let mut blocks = vec![0; format.compressed_size(width, height)];
+    format.compress(
+        image,
+        width,
+        height,
+        texpresso::Params::default(),
+        &mut blocks,
+    );
+
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // not a multiple of 4 wide, so the last column of blocks is partly
+    // padding
+    fn gradient(width: u32, height: u32, alpha: u8) -> TextureData {
+        TextureData::Rgba8(ImageBuffer::from_fn(width, height, |x, y| {
+            Rgba([(x * 17) as u8, (y * 29) as u8, ((x + y) * 7) as u8, alpha])
+        }))
+    }
+
+    #[test]
+    fn converting_twice_gives_the_same_bytes() {
+        let options = ConvertOptions::default();
+        let first = convert(gradient(22, 13, 255), &options).to_ktx2();
+        let second = convert(gradient(22, 13, 255), &options).to_ktx2();
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn ktx2_round_trip() {
+        let cases = [
+            (gradient(22, 13, 255), false, Format::BC1_RGBAUnormBlock, 8),
+            (gradient(22, 13, 255), true, Format::BC1_RGBASrgbBlock, 8),
+            (gradient(22, 13, 128), true, Format::BC3SrgbBlock, 16),
+        ];
+
+        for (data, srgb, format, block_bytes) in cases.iter().cloned() {
+            let options = ConvertOptions {
+                srgb,
+                ..ConvertOptions::default()
+            };
+            let texture = convert(data, &options);
+            let loaded = CompressedTexture::from_ktx2(&texture.to_ktx2()).unwrap();
+
+            assert_eq!(loaded.format, format);
+            assert_eq!((loaded.width, loaded.height), (22, 13));
+            // 22x13, 11x6, 5x3, 2x1, 1x1
+            assert_eq!(loaded.levels.len(), 5);
+            for (level, data) in loaded.levels.iter().enumerate() {
+                let [width, height] = mip_dimensions(22, 13, level as u32);
+                let blocks = width.div_ceil(4) * height.div_ceil(4);
+                assert_eq!(data.len(), blocks as usize * block_bytes);
+                assert_eq!(data, &texture.levels[level]);
+            }
+        }
+    }
+
+    #[test]
+    fn picks_bc5_for_normal_maps_and_bc4_for_grayscale() {
+        let normal_map = ConvertOptions {
+            normal_map: true,
+            ..ConvertOptions::default()
+        };
+        let grayscale = TextureData::Luma8(ImageBuffer::from_pixel(8, 8, image::Luma([40])));
+
+        assert_eq!(
+            convert(gradient(8, 8, 255), &normal_map).format,
+            Format::BC5UnormBlock
+        );
+        assert_eq!(
+            convert(grayscale, &ConvertOptions::default()).format,
+            Format::BC4UnormBlock
+        );
+    }
+}
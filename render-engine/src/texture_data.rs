@@ -0,0 +1,466 @@
+// Decoded images, kept at the channel count and bit depth they were stored
+// with instead of always being turned into 8-bit RGBA. A grayscale roughness
+// map stays one 8-bit channel, a 16-bit heightmap stays 16 bits, and HDR
+// environment maps stay floats.
+
+// Images with 3 channels are given a 4th, because hardly any GPU supports
+// sampling 3-channel formats.
+
+// When the format a texture is uploaded as doesn't match the data (say, a
+// grayscale image uploaded as R8G8B8A8Srgb), the data is converted to fit,
+// see convert_for. BGRA formats get their red and blue swapped while
+// uploading. Snorm formats can't be converted to: 8-bit data is taken to be
+// unsigned everywhere except in to_bytes, where it's already the bits of i8s
+// (only CompressedTexture makes data like that).
+
+use image::codecs::hdr::HdrDecoder;
+use image::imageops::FilterType;
+use image::{
+    DynamicImage, GrayAlphaImage, GrayImage, ImageBuffer, Luma, LumaA, Pixel, Rgba, RgbaImage,
+};
+use vulkano::format::Format;
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+pub type Gray16Image = ImageBuffer<Luma<u16>, Vec<u16>>;
+pub type GrayAlpha16Image = ImageBuffer<LumaA<u16>, Vec<u16>>;
+pub type Rgba16Image = ImageBuffer<Rgba<u16>, Vec<u16>>;
+pub type RgbaF32Image = ImageBuffer<Rgba<f32>, Vec<f32>>;
+
+#[derive(Clone)]
+pub enum TextureData {
+    Luma8(GrayImage),
+    LumaA8(GrayAlphaImage),
+    Rgba8(RgbaImage),
+    Luma16(Gray16Image),
+    LumaA16(GrayAlpha16Image),
+    Rgba16(Rgba16Image),
+    RgbaF32(RgbaF32Image),
+}
+
+// what a format expects for each pixel
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Channel {
+    U8,
+    U16,
+    F16,
+    F32,
+}
+
+// applies the same expression to the image inside any variant, giving back the
+// same variant
+macro_rules! map_image {
+    ($data:expr, $image:ident => $body:expr) => {
+        match $data {
+            TextureData::Luma8($image) => TextureData::Luma8($body),
+            TextureData::LumaA8($image) => TextureData::LumaA8($body),
+            TextureData::Rgba8($image) => TextureData::Rgba8($body),
+            TextureData::Luma16($image) => TextureData::Luma16($body),
+            TextureData::LumaA16($image) => TextureData::LumaA16($body),
+            TextureData::Rgba16($image) => TextureData::Rgba16($body),
+            TextureData::RgbaF32($image) => TextureData::RgbaF32($body),
+        }
+    };
+}
+
+// same, but for expressions that don't give back an image
+macro_rules! with_image {
+    ($data:expr, $image:ident => $body:expr) => {
+        match $data {
+            TextureData::Luma8($image) => $body,
+            TextureData::LumaA8($image) => $body,
+            TextureData::Rgba8($image) => $body,
+            TextureData::Luma16($image) => $body,
+            TextureData::LumaA16($image) => $body,
+            TextureData::Rgba16($image) => $body,
+            TextureData::RgbaF32($image) => $body,
+        }
+    };
+}
+
+impl TextureData {
+    // Radiance .hdr and OpenEXR files are loaded as floats, anything else the
+    // image crate can open is loaded with its own channels and bit depth
+    pub fn open(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_lowercase());
+
+        match extension.as_deref() {
+            Some("hdr") => open_hdr(path),
+            Some("exr") => open_exr(path),
+            _ => Self::from_dynamic(
+                image::open(path).unwrap_or_else(|e| panic!("couldn't open {:?}: {}", path, e)),
+            ),
+        }
+    }
+
+    pub fn from_dynamic(image: DynamicImage) -> Self {
+        match image {
+            DynamicImage::ImageLuma8(image) => TextureData::Luma8(image),
+            DynamicImage::ImageLumaA8(image) => TextureData::LumaA8(image),
+            DynamicImage::ImageLuma16(image) => TextureData::Luma16(image),
+            DynamicImage::ImageLumaA16(image) => TextureData::LumaA16(image),
+            DynamicImage::ImageRgb16(image) => TextureData::Rgba16(ImageBuffer::from_fn(
+                image.width(),
+                image.height(),
+                |x, y| image.get_pixel(x, y).to_rgba(),
+            )),
+            DynamicImage::ImageRgba16(image) => TextureData::Rgba16(image),
+            image => TextureData::Rgba8(image.to_rgba8()),
+        }
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        with_image!(self, image => image.dimensions())
+    }
+
+    // the format that holds the data exactly as it is
+    pub fn native_format(&self) -> Format {
+        match self {
+            TextureData::Luma8(_) => Format::R8Unorm,
+            TextureData::LumaA8(_) => Format::R8G8Unorm,
+            TextureData::Rgba8(_) => Format::R8G8B8A8Unorm,
+            TextureData::Luma16(_) => Format::R16Unorm,
+            TextureData::LumaA16(_) => Format::R16G16Unorm,
+            TextureData::Rgba16(_) => Format::R16G16B16A16Unorm,
+            TextureData::RgbaF32(_) => Format::R32G32B32A32Sfloat,
+        }
+    }
+
+    // converts the data to have as many channels as the format, with the same
+    // bit depth. data that already fits is left alone. 16-bit float formats
+    // get 32-bit floats, which are converted while uploading.
+    pub fn convert_for(self, format: Format) -> Self {
+        assert!(
+            !is_snorm(format),
+            "loading textures as {:?} isn't supported, use a unorm format",
+            format
+        );
+
+        if self.fits(format) {
+            return self;
+        }
+
+        let (channels, channel) = layout(format);
+
+        let rgba = self.to_rgba_f32();
+        let (width, height) = rgba.dimensions();
+        let pixel = |x, y| rgba.get_pixel(x, y).channels().to_vec();
+
+        // values are between 0 and 1 from here on, except for HDR data
+        let to_u8 = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let to_u16 = |value: f32| (value.clamp(0.0, 1.0) * 65535.0).round() as u16;
+
+        match (channels, channel) {
+            (1, Channel::U8) => TextureData::Luma8(ImageBuffer::from_fn(width, height, |x, y| {
+                Luma([to_u8(pixel(x, y)[0])])
+            })),
+            (2, Channel::U8) => TextureData::LumaA8(ImageBuffer::from_fn(width, height, |x, y| {
+                let p = pixel(x, y);
+                LumaA([to_u8(p[0]), to_u8(p[3])])
+            })),
+            (4, Channel::U8) => TextureData::Rgba8(ImageBuffer::from_fn(width, height, |x, y| {
+                let p = pixel(x, y);
+                Rgba([to_u8(p[0]), to_u8(p[1]), to_u8(p[2]), to_u8(p[3])])
+            })),
+            (1, Channel::U16) => {
+                TextureData::Luma16(ImageBuffer::from_fn(width, height, |x, y| {
+                    Luma([to_u16(pixel(x, y)[0])])
+                }))
+            }
+            (2, Channel::U16) => {
+                TextureData::LumaA16(ImageBuffer::from_fn(width, height, |x, y| {
+                    let p = pixel(x, y);
+                    LumaA([to_u16(p[0]), to_u16(p[3])])
+                }))
+            }
+            (4, Channel::U16) => {
+                TextureData::Rgba16(ImageBuffer::from_fn(width, height, |x, y| {
+                    let p = pixel(x, y);
+                    Rgba([to_u16(p[0]), to_u16(p[1]), to_u16(p[2]), to_u16(p[3])])
+                }))
+            }
+            (4, Channel::F16) | (4, Channel::F32) => TextureData::RgbaF32(rgba),
+            _ => panic!("can't convert texture data to {:?}", format),
+        }
+    }
+
+    pub fn resize(&self, width: u32, height: u32) -> Self {
+        map_image!(self, image => image::imageops::resize(image, width, height, FilterType::Triangle))
+    }
+
+    // the raw bytes to upload to a texture of the given format, which should
+    // come from convert_for. for snorm formats the data has to be the bits of
+    // i8s already.
+    pub fn to_bytes(&self, format: Format) -> Vec<u8> {
+        assert!(
+            self.fits(format),
+            "texture data doesn't match {:?}, use convert_for first",
+            format
+        );
+        let (_, channel) = layout(format);
+
+        match self {
+            TextureData::Luma8(image) => image.clone().into_raw(),
+            TextureData::LumaA8(image) => image.clone().into_raw(),
+            TextureData::Rgba8(image) if is_bgra(format) => image
+                .pixels()
+                .flat_map(|p| vec![p[2], p[1], p[0], p[3]])
+                .collect(),
+            TextureData::Rgba8(image) => image.clone().into_raw(),
+            TextureData::Luma16(image) => u16_bytes(image),
+            TextureData::LumaA16(image) => u16_bytes(image),
+            TextureData::Rgba16(image) => u16_bytes(image),
+            TextureData::RgbaF32(image) if channel == Channel::F16 => image
+                .iter()
+                .flat_map(|&value| f32_to_f16(value).to_le_bytes().to_vec())
+                .collect(),
+            TextureData::RgbaF32(image) => image
+                .iter()
+                .flat_map(|value| value.to_le_bytes().to_vec())
+                .collect(),
+        }
+    }
+
+    // whether the data can be uploaded as the format without converting it
+    pub fn fits(&self, format: Format) -> bool {
+        let (channels, channel) = layout(format);
+        let (own_channels, own_channel) = self.layout();
+
+        channels == own_channels
+            && (channel == own_channel || (channel == Channel::F16 && own_channel == Channel::F32))
+    }
+
+    fn layout(&self) -> (usize, Channel) {
+        match self {
+            TextureData::Luma8(_) => (1, Channel::U8),
+            TextureData::LumaA8(_) => (2, Channel::U8),
+            TextureData::Rgba8(_) => (4, Channel::U8),
+            TextureData::Luma16(_) => (1, Channel::U16),
+            TextureData::LumaA16(_) => (2, Channel::U16),
+            TextureData::Rgba16(_) => (4, Channel::U16),
+            // halved while uploading if the format wants 16-bit floats
+            TextureData::RgbaF32(_) => (4, Channel::F32),
+        }
+    }
+
+    // every channel between 0 and 1, grayscale spread over RGB
+    fn to_rgba_f32(&self) -> RgbaF32Image {
+        let (width, height) = self.dimensions();
+        let u8_value = |value: u8| f32::from(value) / 255.0;
+        let u16_value = |value: u16| f32::from(value) / 65535.0;
+
+        match self {
+            TextureData::Luma8(image) => ImageBuffer::from_fn(width, height, |x, y| {
+                let l = u8_value(image.get_pixel(x, y)[0]);
+                Rgba([l, l, l, 1.0])
+            }),
+            TextureData::LumaA8(image) => ImageBuffer::from_fn(width, height, |x, y| {
+                let p = image.get_pixel(x, y);
+                let l = u8_value(p[0]);
+                Rgba([l, l, l, u8_value(p[1])])
+            }),
+            TextureData::Rgba8(image) => ImageBuffer::from_fn(width, height, |x, y| {
+                let p = image.get_pixel(x, y);
+                Rgba([
+                    u8_value(p[0]),
+                    u8_value(p[1]),
+                    u8_value(p[2]),
+                    u8_value(p[3]),
+                ])
+            }),
+            TextureData::Luma16(image) => ImageBuffer::from_fn(width, height, |x, y| {
+                let l = u16_value(image.get_pixel(x, y)[0]);
+                Rgba([l, l, l, 1.0])
+            }),
+            TextureData::LumaA16(image) => ImageBuffer::from_fn(width, height, |x, y| {
+                let p = image.get_pixel(x, y);
+                let l = u16_value(p[0]);
+                Rgba([l, l, l, u16_value(p[1])])
+            }),
+            TextureData::Rgba16(image) => ImageBuffer::from_fn(width, height, |x, y| {
+                let p = image.get_pixel(x, y);
+                Rgba([
+                    u16_value(p[0]),
+                    u16_value(p[1]),
+                    u16_value(p[2]),
+                    u16_value(p[3]),
+                ])
+            }),
+            TextureData::RgbaF32(image) => image.clone(),
+        }
+    }
+}
+
+// 3 channels become 4, with an alpha of 1
+fn open_hdr(path: &Path) -> TextureData {
+    let file = File::open(path).unwrap_or_else(|e| panic!("couldn't open {:?}: {}", path, e));
+    let decoder = HdrDecoder::new(BufReader::new(file))
+        .unwrap_or_else(|e| panic!("couldn't decode {:?}: {}", path, e));
+    let metadata = decoder.metadata();
+    let pixels = decoder
+        .read_image_hdr()
+        .unwrap_or_else(|e| panic!("couldn't decode {:?}: {}", path, e));
+
+    let (width, height) = (metadata.width, metadata.height);
+    TextureData::RgbaF32(ImageBuffer::from_fn(width, height, |x, y| {
+        let rgb = pixels[(y * width + x) as usize].channels();
+        Rgba([rgb[0], rgb[1], rgb[2], 1.0])
+    }))
+}
+
+// only the first layer's RGBA channels are read. a missing alpha channel is 1.
+fn open_exr(path: &Path) -> TextureData {
+    use exr::prelude::{read_first_rgba_layer_from_file, Vec2};
+
+    let image = read_first_rgba_layer_from_file(
+        path,
+        |resolution: Vec2<usize>, _| {
+            (
+                resolution.width(),
+                vec![0.0; resolution.width() * resolution.height() * 4],
+            )
+        },
+        |(width, pixels): &mut (usize, Vec<f32>),
+         position: Vec2<usize>,
+         (r, g, b, a): (f32, f32, f32, f32)| {
+            let idx = (position.y() * *width + position.x()) * 4;
+            pixels[idx..idx + 4].copy_from_slice(&[r, g, b, a]);
+        },
+    )
+    .unwrap_or_else(|e| panic!("couldn't decode {:?}: {}", path, e));
+
+    let size = image.layer_data.size;
+    let (_, pixels) = image.layer_data.channel_data.pixels;
+
+    TextureData::RgbaF32(
+        ImageBuffer::from_raw(size.width() as u32, size.height() as u32, pixels).unwrap(),
+    )
+}
+
+// how many channels the format has and what each one is. only formats that
+// textures are likely to be loaded into are supported.
+fn layout(format: Format) -> (usize, Channel) {
+    match format {
+        Format::R8Unorm | Format::R8Snorm | Format::R8Uint | Format::R8Srgb => (1, Channel::U8),
+        Format::R8G8Unorm | Format::R8G8Snorm | Format::R8G8Uint | Format::R8G8Srgb => {
+            (2, Channel::U8)
+        }
+        Format::R8G8B8A8Unorm
+        | Format::R8G8B8A8Snorm
+        | Format::R8G8B8A8Uint
+        | Format::R8G8B8A8Srgb
+        | Format::B8G8R8A8Unorm
+        | Format::B8G8R8A8Srgb => (4, Channel::U8),
+        Format::R16Unorm | Format::R16Uint => (1, Channel::U16),
+        Format::R16G16Unorm | Format::R16G16Uint => (2, Channel::U16),
+        Format::R16G16B16A16Unorm | Format::R16G16B16A16Uint => (4, Channel::U16),
+        Format::R16G16B16A16Sfloat => (4, Channel::F16),
+        Format::R32G32B32A32Sfloat => (4, Channel::F32),
+        _ => panic!("loading textures as {:?} isn't supported", format),
+    }
+}
+
+fn is_snorm(format: Format) -> bool {
+    matches!(
+        format,
+        Format::R8Snorm | Format::R8G8Snorm | Format::R8G8B8A8Snorm
+    )
+}
+
+fn is_bgra(format: Format) -> bool {
+    matches!(format, Format::B8G8R8A8Unorm | Format::B8G8R8A8Srgb)
+}
+
+fn u16_bytes<P: Pixel<Subpixel = u16> + 'static>(image: &ImageBuffer<P, Vec<u16>>) -> Vec<u8> {
+    image
+        .iter()
+        .flat_map(|value| value.to_le_bytes().to_vec())
+        .collect()
+}
+
+// rounds towards zero, which is close enough for textures. values too big for
+// a half become infinity and NaNs stay NaNs.
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x007f_ffff;
+
+    if exponent == 0xff {
+        // infinity or NaN
+        let nan = if mantissa != 0 { 0x0200 } else { 0 };
+        return sign | 0x7c00 | nan;
+    }
+
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        sign | 0x7c00
+    } else if exponent <= 0 {
+        // subnormal, or too small for a half at all
+        if exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x0080_0000;
+        sign | (mantissa >> (14 - exponent)) as u16
+    } else {
+        sign | ((exponent as u16) << 10) | (mantissa >> 13) as u16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_halves() {
+        assert_eq!(f32_to_f16(0.0), 0x0000);
+        assert_eq!(f32_to_f16(-0.0), 0x8000);
+        assert_eq!(f32_to_f16(1.0), 0x3c00);
+        assert_eq!(f32_to_f16(0.5), 0x3800);
+        assert_eq!(f32_to_f16(-2.0), 0xc000);
+        // the largest half
+        assert_eq!(f32_to_f16(65504.0), 0x7bff);
+    }
+
+    #[test]
+    fn rounds_towards_zero() {
+        let step = 2f32.powi(-10);
+        assert_eq!(f32_to_f16(1.0 + step), 0x3c01);
+        // halfway and just below the next half
+        assert_eq!(f32_to_f16(1.0 + step / 2.0), 0x3c00);
+        assert_eq!(f32_to_f16(1.0 + step * 0.99), 0x3c00);
+        assert_eq!(f32_to_f16(-1.0 - step * 0.99), 0xbc00);
+        // between the largest half and infinity
+        assert_eq!(f32_to_f16(65520.0), 0x7bff);
+    }
+
+    #[test]
+    fn subnormals() {
+        // the smallest normal half, then subnormals down to the smallest one
+        assert_eq!(f32_to_f16(2f32.powi(-14)), 0x0400);
+        assert_eq!(f32_to_f16(2f32.powi(-15)), 0x0200);
+        assert_eq!(f32_to_f16(2f32.powi(-24)), 0x0001);
+        assert_eq!(f32_to_f16(-2f32.powi(-24)), 0x8001);
+        assert_eq!(f32_to_f16(1.5 * 2f32.powi(-24)), 0x0001);
+        // too small for a half, and f32 subnormals
+        assert_eq!(f32_to_f16(2f32.powi(-25)), 0x0000);
+        assert_eq!(f32_to_f16(-1e-40), 0x8000);
+    }
+
+    #[test]
+    fn infinity_and_nan() {
+        assert_eq!(f32_to_f16(65536.0), 0x7c00);
+        assert_eq!(f32_to_f16(-1e10), 0xfc00);
+        assert_eq!(f32_to_f16(f32::INFINITY), 0x7c00);
+        assert_eq!(f32_to_f16(f32::NEG_INFINITY), 0xfc00);
+
+        let nan = f32_to_f16(f32::NAN);
+        assert_eq!(nan & 0x7c00, 0x7c00);
+        assert_ne!(nan & 0x03ff, 0);
+    }
+}
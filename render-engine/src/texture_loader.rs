@@ -0,0 +1,331 @@
+// Loads textures in the background, so loading lots of them at startup or
+// while streaming in a level doesn't stall the render loop.
+
+// Files are decoded (and their mipmaps made, if that has to happen on the
+// CPU) by a pool of worker threads. One more thread records the decoded
+// textures into a command buffer, as many as are ready at once up to
+// BATCH_SIZE, and submits them together, so there's one GPU round trip per
+// batch instead of one per texture like with load_texture.
+
+// load() gives back a TextureHandle straight away. get() on it returns None
+// until the texture is on the GPU, and wait() blocks until it is.
+
+// Textures are loaded exactly like load_texture would, including KTX2 and DDS
+// files. Anything that would make load_texture panic makes get() and wait()
+// panic instead.
+
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::image::{Dimensions, MipmapsCount};
+
+use crate::compressed::{is_compressed_file, CompressedTexture};
+use crate::texture::{
+    cpu_levels, mip_level_count, record_layers, record_mip_chain, submit_and_wait,
+    supports_linear_blit,
+};
+use crate::texture_data::TextureData;
+use crate::utils::is_srgb;
+use crate::Image;
+
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+// the most textures uploaded by one command buffer
+const BATCH_SIZE: usize = 16;
+
+pub struct TextureLoader {
+    jobs: Sender<Job>,
+}
+
+#[derive(Clone)]
+pub struct TextureHandle {
+    path: Arc<PathBuf>,
+    state: Arc<(Mutex<State>, Condvar)>,
+}
+
+enum State {
+    Loading,
+    Ready(Image),
+    Failed(String),
+}
+
+struct Job {
+    path: PathBuf,
+    format: Format,
+    mipmaps: MipmapsCount,
+    handle: TextureHandle,
+}
+
+// what the workers give the upload thread
+enum Decoded {
+    // the mipmaps are made on the GPU with blits while uploading
+    Blit {
+        data: TextureData,
+        format: Format,
+        mipmaps: MipmapsCount,
+    },
+    // every level is already made, compressed or not
+    Levels {
+        width: u32,
+        height: u32,
+        format: Format,
+        levels: Vec<Vec<u8>>,
+    },
+}
+
+type Result<T> = std::result::Result<T, String>;
+
+impl TextureLoader {
+    // one decoding thread per CPU core
+    pub fn new(queue: Arc<Queue>) -> Self {
+        let threads = thread::available_parallelism()
+            .map(|threads| threads.get())
+            .unwrap_or(4);
+
+        Self::with_threads(queue, threads)
+    }
+
+    pub fn with_threads(queue: Arc<Queue>, threads: usize) -> Self {
+        let (jobs, job_receiver) = channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let (decoded, decoded_receiver) = channel();
+
+        for _ in 0..threads.max(1) {
+            let job_receiver = job_receiver.clone();
+            let decoded = decoded.clone();
+            let device = queue.device().clone();
+            thread::spawn(move || decode_jobs(job_receiver, decoded, device));
+        }
+        thread::spawn(move || upload_batches(queue, decoded_receiver));
+
+        Self { jobs }
+    }
+
+    pub fn load(&self, path: &Path, format: Format) -> TextureHandle {
+        self.load_with_mips(path, format, MipmapsCount::Log2)
+    }
+
+    pub fn load_with_mips(
+        &self,
+        path: &Path,
+        format: Format,
+        mipmaps: MipmapsCount,
+    ) -> TextureHandle {
+        let handle = TextureHandle {
+            path: Arc::new(path.to_path_buf()),
+            state: Arc::new((Mutex::new(State::Loading), Condvar::new())),
+        };
+
+        self.jobs
+            .send(Job {
+                path: path.to_path_buf(),
+                format,
+                mipmaps,
+                handle: handle.clone(),
+            })
+            .expect("texture loader threads have stopped");
+
+        handle
+    }
+}
+
+impl TextureHandle {
+    // None while the texture is still loading
+    pub fn get(&self) -> Option<Image> {
+        let result = self.state.0.lock().unwrap().result();
+        result.map(|result| self.unwrap(result))
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.get().is_some()
+    }
+
+    // blocks until the texture is loaded
+    pub fn wait(&self) -> Image {
+        let (lock, condvar) = &*self.state;
+        let mut state = lock.lock().unwrap();
+
+        let result = loop {
+            if let Some(result) = state.result() {
+                break result;
+            }
+            state = condvar.wait(state).unwrap();
+        };
+        drop(state);
+
+        self.unwrap(result)
+    }
+
+    // only ever called without the lock held, because panicking while holding
+    // it would poison it for every other clone of the handle
+    fn unwrap(&self, result: Result<Image>) -> Image {
+        result.unwrap_or_else(|e| panic!("couldn't load texture {:?}: {}", self.path, e))
+    }
+
+    // only the first result counts
+    fn resolve(&self, result: Result<Image>) {
+        let (lock, condvar) = &*self.state;
+        let mut state = lock.lock().unwrap();
+
+        if let State::Loading = *state {
+            *state = match result {
+                Ok(image) => State::Ready(image),
+                Err(e) => State::Failed(e),
+            };
+            condvar.notify_all();
+        }
+    }
+}
+
+impl State {
+    // None while still loading
+    fn result(&self) -> Option<Result<Image>> {
+        match self {
+            State::Loading => None,
+            State::Ready(image) => Some(Ok(image.clone())),
+            State::Failed(e) => Some(Err(e.clone())),
+        }
+    }
+}
+
+// runs on each worker thread until the loader is dropped
+fn decode_jobs(
+    jobs: Arc<Mutex<Receiver<Job>>>,
+    decoded: Sender<(TextureHandle, Result<Decoded>)>,
+    device: Arc<Device>,
+) {
+    loop {
+        // the lock is only held while waiting for a job, not while decoding
+        let job = match jobs.lock().unwrap().recv() {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+
+        let result =
+            panic::catch_unwind(AssertUnwindSafe(|| decode(&job, &device))).map_err(panic_message);
+        if decoded.send((job.handle, result)).is_err() {
+            return;
+        }
+    }
+}
+
+fn decode(job: &Job, device: &Arc<Device>) -> Decoded {
+    if is_compressed_file(&job.path) {
+        let texture = CompressedTexture::open(&job.path)
+            .in_colour_space(is_srgb(job.format))
+            .unwrap_or_else(|e| panic!("couldn't load {:?}: {}", job.path, e));
+        let (format, levels) = texture.upload_data(device);
+
+        return Decoded::Levels {
+            width: texture.width,
+            height: texture.height,
+            format,
+            levels,
+        };
+    }
+
+    let data = TextureData::open(&job.path).convert_for(job.format);
+    let (width, height) = data.dimensions();
+    let level_count = mip_level_count(width, height, job.mipmaps);
+
+    if level_count > 1 && supports_linear_blit(job.format) {
+        Decoded::Blit {
+            data,
+            format: job.format,
+            mipmaps: job.mipmaps,
+        }
+    } else {
+        Decoded::Levels {
+            width,
+            height,
+            format: job.format,
+            levels: cpu_levels(&data, job.format, level_count),
+        }
+    }
+}
+
+// runs on the upload thread until the loader and all its workers are gone
+fn upload_batches(queue: Arc<Queue>, decoded: Receiver<(TextureHandle, Result<Decoded>)>) {
+    // blocks until something is decoded, then takes whatever else is ready too
+    while let Ok(first) = decoded.recv() {
+        let mut batch = vec![first];
+        while batch.len() < BATCH_SIZE {
+            match decoded.try_recv() {
+                Ok(next) => batch.push(next),
+                Err(_) => break,
+            }
+        }
+
+        let handles: Vec<_> = batch.iter().map(|(handle, _)| handle.clone()).collect();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| upload_batch(&queue, batch)));
+
+        // if recording or submitting failed, nothing in the batch was loaded
+        if let Err(e) = result {
+            let e = panic_message(e);
+            for handle in handles {
+                handle.resolve(Err(e.clone()));
+            }
+        }
+    }
+}
+
+fn upload_batch(queue: &Arc<Queue>, batch: Vec<(TextureHandle, Result<Decoded>)>) {
+    let mut builder =
+        AutoCommandBufferBuilder::primary_one_time_submit(queue.device().clone(), queue.family())
+            .unwrap();
+    let mut uploaded = vec![];
+
+    for (handle, result) in batch {
+        let decoded = match result {
+            Ok(decoded) => decoded,
+            Err(e) => {
+                handle.resolve(Err(e));
+                continue;
+            }
+        };
+
+        let (next_builder, image) = match decoded {
+            Decoded::Blit {
+                data,
+                format,
+                mipmaps,
+            } => {
+                let (width, height) = data.dimensions();
+                let dimensions = Dimensions::Dim2d { width, height };
+                record_layers(builder, queue, vec![data], dimensions, format, mipmaps)
+            }
+            Decoded::Levels {
+                width,
+                height,
+                format,
+                levels,
+            } => record_mip_chain(builder, queue, width, height, format, levels),
+        };
+        builder = next_builder;
+        uploaded.push((handle, image));
+    }
+
+    if uploaded.is_empty() {
+        return;
+    }
+
+    submit_and_wait(builder, queue.clone());
+    for (handle, image) in uploaded {
+        handle.resolve(Ok(image));
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown error".to_string()
+    }
+}
@@ -1,12 +1,17 @@
 use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer, ImmutableBuffer};
 use vulkano::device::{Device, Queue};
 use vulkano::format::Format;
-use vulkano::image::{Dimensions, ImageViewAccess, ImmutableImage};
+use vulkano::image::{ImageViewAccess, MipmapsCount};
 use vulkano::memory::Content;
-use vulkano::sampler::{Filter, MipmapMode, Sampler, SamplerAddressMode};
-use vulkano::sync::GpuFuture;
+use vulkano::sampler::Sampler;
 
+use crate::compressed::{is_compressed_file, CompressedTexture};
 use crate::input::get_elapsed;
+use crate::layout::Pod;
+use crate::resources::Resources;
+use crate::sampler::SamplerDesc;
+use crate::texture::{equirectangular_to_faces, upload_cubemap, upload_texture};
+use crate::texture_data::TextureData;
 
 use std::path::Path;
 use std::sync::Arc;
@@ -43,49 +48,97 @@ pub fn upload_data<T: Content + 'static + Send + Sync>(
     CpuAccessibleBuffer::from_data(device, BufferUsage::all(), data).unwrap()
 }
 
+// the raw bytes of some data, for use as push constants. structs that derive
+// Data are Pod as well.
+pub fn as_bytes<T: Pod>(data: &T) -> Vec<u8> {
+    let size = std::mem::size_of::<T>();
+    // fine because Pod types have no padding, so every byte is initialized
+    unsafe { std::slice::from_raw_parts(data as *const T as *const u8, size) }.to_vec()
+}
+
+// loads a texture with a full chain of mipmaps
+// .hdr and .exr files are loaded as floats, for formats like
+// R16G16B16A16Sfloat. anything else keeps its channels and bit depth, so a
+// grayscale image can be loaded as R8Unorm without being made RGBA first.
+// .ktx2 and .dds files keep their own compressed format and mipmaps. legacy
+// .dds files, which don't say whether they're sRGB, are told by the format,
+// the others have to agree with it.
 pub fn load_texture(
     queue: Arc<Queue>,
     path: &Path,
     format: Format,
 ) -> Arc<dyn ImageViewAccess + Send + Sync> {
-    let (texture, tex_future) = {
-        let image = image::open(path).unwrap().to_rgba();
-        let (width, height) = image.dimensions();
-        let image_data = image.into_raw().clone();
-
-        ImmutableImage::from_iter(
-            image_data.iter().cloned(),
-            Dimensions::Dim2d { width, height },
-            format,
-            queue.clone(),
-        )
-        .unwrap()
-    };
+    load_texture_with_mips(queue, path, format, MipmapsCount::Log2)
+}
 
-    tex_future
-        .then_signal_fence_and_flush()
-        .unwrap()
-        .wait(None)
-        .unwrap();
-
-    texture
-}
-
-pub fn default_sampler(device: Arc<Device>) -> Arc<Sampler> {
-    Sampler::new(
-        device,
-        Filter::Linear,
-        Filter::Linear,
-        MipmapMode::Nearest,
-        SamplerAddressMode::Repeat,
-        SamplerAddressMode::Repeat,
-        SamplerAddressMode::Repeat,
-        0.0,
-        1.0,
-        0.0,
-        0.0,
+// MipmapsCount::One for no mipmaps, or Specific(n) for at most n levels
+pub fn load_texture_with_mips(
+    queue: Arc<Queue>,
+    path: &Path,
+    format: Format,
+    mipmaps: MipmapsCount,
+) -> Arc<dyn ImageViewAccess + Send + Sync> {
+    if is_compressed_file(path) {
+        load_compressed_texture(queue, path, is_srgb(format))
+    } else {
+        upload_texture(queue, TextureData::open(path), format, mipmaps)
+    }
+}
+
+// loads a BC-compressed texture from a KTX2 or DDS file, with the mipmaps
+// that are in the file. legacy DDS files don't say whether they're sRGB, so
+// srgb decides. KTX2 and DX10 DDS files keep their own format, and panic if
+// it doesn't agree with srgb.
+pub fn load_compressed_texture(
+    queue: Arc<Queue>,
+    path: &Path,
+    srgb: bool,
+) -> Arc<dyn ImageViewAccess + Send + Sync> {
+    CompressedTexture::open(path)
+        .in_colour_space(srgb)
+        .unwrap_or_else(|e| panic!("couldn't load {:?}: {}", path, e))
+        .upload(queue)
+}
+
+pub(crate) fn is_srgb(format: Format) -> bool {
+    matches!(
+        format,
+        Format::R8Srgb
+            | Format::R8G8Srgb
+            | Format::R8G8B8A8Srgb
+            | Format::B8G8R8A8Srgb
+            | Format::A8B8G8R8SrgbPack32
     )
-    .unwrap()
+}
+
+// loads a cubemap from 6 square images, in the order +X, -X, +Y, -Y, +Z, -Z
+pub fn load_cubemap(
+    queue: Arc<Queue>,
+    paths: [&Path; 6],
+    format: Format,
+) -> Arc<dyn ImageViewAccess + Send + Sync> {
+    let faces = paths.iter().map(|path| TextureData::open(path)).collect();
+
+    upload_cubemap(queue, faces, format, MipmapsCount::Log2)
+}
+
+// loads a cubemap from an equirectangular panorama, like most HDRI skies
+pub fn load_equirectangular(
+    queue: Arc<Queue>,
+    path: &Path,
+    face_size: u32,
+    format: Format,
+) -> Arc<dyn ImageViewAccess + Send + Sync> {
+    let panorama = TextureData::open(path);
+    let faces = equirectangular_to_faces(&panorama, face_size);
+
+    upload_cubemap(queue, faces, format, MipmapsCount::Log2)
+}
+
+// linear, repeating, blending between mip levels. shared with every other user
+// of SamplerDesc::default with the same resources.
+pub fn default_sampler(resources: &Resources) -> Arc<Sampler> {
+    resources.samplers.get(&SamplerDesc::default())
 }
 
 // used for averaging times for benchmarks
@@ -133,6 +186,12 @@ pub struct Stopwatch {
     start_time: Instant,
 }
 
+impl Default for Stopwatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Stopwatch {
     pub fn new() -> Self {
         Self {
@@ -0,0 +1,327 @@
+// Checks the data given to ObjectPrototype::build against what the shaders
+// actually declare, so a wrong vertex type or collection is reported with the
+// shader, set and binding involved instead of failing somewhere inside
+// vulkano.
+
+use vulkano::descriptor::descriptor::DescriptorDescTy;
+use vulkano::pipeline::vertex::VertexMemberTy;
+
+use crate::collection::ProvidedDescriptor;
+use crate::mesh::Vertex;
+use crate::reflection::DescriptorBinding;
+use crate::shader_report::{descriptor_kind, ShaderReport};
+use crate::shaders::{ShaderError, ShaderStage};
+
+use std::fmt;
+use std::path::PathBuf;
+
+// the minimum maxPushConstantsSize, so the most that works on every device
+pub const MAX_PUSH_CONSTANTS_SIZE: usize = 128;
+
+#[derive(Debug, Clone)]
+pub enum BuildError {
+    // the shaders or the pipeline couldn't be created
+    Shader(ShaderError),
+    // the shaders were fine, but the data doesn't fit them
+    Mismatch(ValidationError),
+}
+
+#[derive(Debug, Clone)]
+pub struct ValidationError {
+    // the shader that declares the thing that didn't match
+    pub path: PathBuf,
+    pub stage: ShaderStage,
+    pub item: MismatchedItem,
+    pub expected: String,
+    pub provided: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MismatchedItem {
+    Binding { set: u32, binding: u32 },
+    VertexInput { location: u32, name: String },
+    PushConstants,
+}
+
+// every input of the vertex shader has to have a member with the same name
+// and a compatible type in V
+pub fn validate_vertex<V: Vertex>(report: &ShaderReport) -> Result<(), ValidationError> {
+    let vs = match report.stages.first() {
+        Some(vs) => vs,
+        None => return Ok(()),
+    };
+
+    for var in vs.reflection.inputs.iter() {
+        for entry in var.entries.iter() {
+            let name = match entry.name.as_ref() {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+            let num_locs = entry.location.end - entry.location.start;
+
+            let provided = match V::member(&name) {
+                Some(info) if info.ty.matches(info.array_size, entry.format, num_locs) => continue,
+                Some(info) => format!(
+                    "{}::{} of type {}",
+                    std::any::type_name::<V>(),
+                    name,
+                    rust_type(info.ty, info.array_size)
+                ),
+                None => format!("{} with no member called {}", std::any::type_name::<V>(), name),
+            };
+
+            return Err(ValidationError {
+                path: vs.path.clone(),
+                stage: vs.stage,
+                item: MismatchedItem::VertexInput {
+                    location: entry.location.start,
+                    name,
+                },
+                expected: format!("{} ({:?})", var.type_name, entry.format),
+                provided,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+// each set in the collection has to line up binding for binding with the set
+// of the same index in the shaders. sets the shaders use but the collection
+// doesn't provide are allowed, since they might be bound some other way.
+pub fn validate_collection(
+    report: &ShaderReport,
+    sets: &[Vec<ProvidedDescriptor>],
+    set_idx_offset: usize,
+) -> Result<(), ValidationError> {
+    let declared = report.descriptors();
+
+    for (idx, provided_set) in sets.iter().enumerate() {
+        let set = (idx + set_idx_offset) as u32;
+        let declared_set: Vec<&DescriptorBinding> = declared
+            .iter()
+            .map(|(descriptor, _)| *descriptor)
+            .filter(|descriptor| descriptor.set == set)
+            .collect();
+
+        // elements are bound to bindings 0, 1, 2... in order
+        let binding_count = declared_set
+            .iter()
+            .map(|descriptor| descriptor.binding as usize + 1)
+            .max()
+            .unwrap_or(0)
+            .max(provided_set.len());
+
+        for binding in 0..binding_count {
+            let declared = declared_set
+                .iter()
+                .find(|descriptor| descriptor.binding == binding as u32);
+            let provided = provided_set.get(binding);
+
+            let matches = match (declared, provided) {
+                (Some(declared), Some(provided)) => descriptor_matches(declared, provided),
+                (None, None) => true,
+                _ => false,
+            };
+            if matches {
+                continue;
+            }
+
+            let (path, stage) = declaring_shader(report, set, binding as u32);
+            return Err(ValidationError {
+                path,
+                stage,
+                item: MismatchedItem::Binding {
+                    set,
+                    binding: binding as u32,
+                },
+                expected: declared.map_or("nothing".to_string(), |declared| describe(declared)),
+                provided: provided.map_or("nothing".to_string(), |provided| provided.to_string()),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+// the push constants have to cover the largest block any stage declares, and
+// fit in the 128 bytes every device supports
+pub fn validate_push_constants(
+    report: &ShaderReport,
+    provided_size: Option<usize>,
+) -> Result<(), ValidationError> {
+    let declared = report
+        .stages
+        .iter()
+        .filter_map(|stage| {
+            stage
+                .reflection
+                .push_constants_size
+                .map(|size| (stage, size))
+        })
+        .max_by_key(|(_, size)| *size);
+
+    let matches = match (declared, provided_size) {
+        (Some((_, declared_size)), Some(provided_size)) => {
+            provided_size >= declared_size as usize && provided_size <= MAX_PUSH_CONSTANTS_SIZE
+        }
+        (None, None) => true,
+        _ => false,
+    };
+    if matches {
+        return Ok(());
+    }
+
+    let (path, stage) = match (declared, report.stages.first()) {
+        (Some((stage, _)), _) | (None, Some(stage)) => (stage.path.clone(), stage.stage),
+        (None, None) => (PathBuf::new(), ShaderStage::Vertex),
+    };
+    Err(ValidationError {
+        path,
+        stage,
+        item: MismatchedItem::PushConstants,
+        expected: declared.map_or("nothing".to_string(), |(_, size)| format!("{} bytes", size)),
+        provided: match provided_size {
+            Some(size) if size > MAX_PUSH_CONSTANTS_SIZE => format!(
+                "{} bytes, more than the {} bytes guaranteed to be available",
+                size, MAX_PUSH_CONSTANTS_SIZE
+            ),
+            Some(size) => format!("{} bytes", size),
+            None => "nothing".to_string(),
+        },
+    })
+}
+
+fn descriptor_matches(declared: &DescriptorBinding, provided: &ProvidedDescriptor) -> bool {
+    match (&declared.desc.ty, provided) {
+        // a struct smaller than the block would leave the end of it undefined.
+        // bigger is fine, it might just have padding at the end.
+        (DescriptorDescTy::Buffer(_), ProvidedDescriptor::Buffer { size, .. }) => declared
+            .block_size
+            .is_none_or(|block_size| *size >= block_size as usize),
+        // comparison samplers only work with sampler2DShadow and the like,
+        // and those only work with comparison samplers
+        (DescriptorDescTy::CombinedImageSampler(_), ProvidedDescriptor::Image { compare }) => {
+            declared.desc.array_count == 1 && *compare == declared.compare
+        }
+        // the array's length is only known when uploading, so any size passes
+        (DescriptorDescTy::CombinedImageSampler(_), ProvidedDescriptor::ImageArray { compare }) => {
+            *compare == declared.compare
+        }
+        // a read-only slice can't be bound where the shader writes
+        (DescriptorDescTy::Buffer(desc), ProvidedDescriptor::StorageBuffer { writable, .. }) => {
+            desc.storage && (*writable || declared.desc.readonly)
+        }
+        (DescriptorDescTy::Image(desc), ProvidedDescriptor::StorageImage) => !desc.sampled,
+        _ => false,
+    }
+}
+
+fn describe(declared: &DescriptorBinding) -> String {
+    let mut description = format!("{} {}", descriptor_kind(&declared.desc.ty), declared.type_name);
+    if let Some(name) = &declared.name {
+        description.push_str(&format!(" {}", name));
+    }
+    if declared.desc.array_count > 1 {
+        description.push_str(&format!("[{}]", declared.desc.array_count));
+    }
+    if let Some(size) = declared.block_size {
+        description.push_str(&format!(" ({} bytes)", size));
+    }
+    if let DescriptorDescTy::Buffer(desc) = &declared.desc.ty {
+        if desc.storage && !declared.desc.readonly {
+            description.push_str(" that the shader writes to");
+        }
+    }
+
+    description
+}
+
+// the first stage that uses the binding, or any stage that uses the set if
+// no stage uses the binding, or the vertex shader if nothing uses the set
+fn declaring_shader(report: &ShaderReport, set: u32, binding: u32) -> (PathBuf, ShaderStage) {
+    let uses = |binding_matches: &dyn Fn(u32) -> bool| {
+        report.stages.iter().find(|stage| {
+            stage
+                .reflection
+                .descriptors
+                .iter()
+                .any(|descriptor| descriptor.set == set && binding_matches(descriptor.binding))
+        })
+    };
+
+    uses(&|other| other == binding)
+        .or_else(|| uses(&|_| true))
+        .or_else(|| report.stages.first())
+        .map_or((PathBuf::new(), ShaderStage::Vertex), |stage| {
+            (stage.path.clone(), stage.stage)
+        })
+}
+
+fn rust_type(ty: VertexMemberTy, array_size: usize) -> String {
+    let name = match ty {
+        VertexMemberTy::I8 => "i8",
+        VertexMemberTy::U8 => "u8",
+        VertexMemberTy::I16 => "i16",
+        VertexMemberTy::U16 => "u16",
+        VertexMemberTy::I32 => "i32",
+        VertexMemberTy::U32 => "u32",
+        VertexMemberTy::F32 => "f32",
+        VertexMemberTy::F64 => "f64",
+    };
+
+    if array_size == 1 {
+        name.to_string()
+    } else {
+        format!("[{}; {}]", name, array_size)
+    }
+}
+
+impl From<ShaderError> for BuildError {
+    fn from(e: ShaderError) -> Self {
+        BuildError::Shader(e)
+    }
+}
+
+impl From<ValidationError> for BuildError {
+    fn from(e: ValidationError) -> Self {
+        BuildError::Mismatch(e)
+    }
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BuildError::Shader(e) => write!(f, "{}", e),
+            BuildError::Mismatch(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "mismatch with {} shader {:?}: ", self.stage, self.path)?;
+
+        match &self.item {
+            MismatchedItem::Binding { set, binding } => write!(
+                f,
+                "set {}, binding {} expects {}, but the collection provides {}",
+                set, binding, self.expected, self.provided
+            ),
+            MismatchedItem::VertexInput { location, name } => write!(
+                f,
+                "vertex input {} at location {} expects {}, but the vertex type is {}",
+                name, location, self.expected, self.provided
+            ),
+            MismatchedItem::PushConstants => write!(
+                f,
+                "push constants expect {}, but the object provides {}",
+                self.expected, self.provided
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+impl std::error::Error for ValidationError {}
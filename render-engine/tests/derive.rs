@@ -0,0 +1,10 @@
+// The compile-time layout checks of #[derive(Data)]. The fail cases have the
+// compiler's output next to them, regenerate it with TRYBUILD=overwrite after
+// changing a message.
+
+#[test]
+fn data_layouts() {
+    let cases = trybuild::TestCases::new();
+    cases.pass("tests/derive/pass/*.rs");
+    cases.compile_fail("tests/derive/fail/*.rs");
+}
@@ -0,0 +1,9 @@
+use render_engine::collection::Data;
+
+#[derive(Clone, Data)]
+#[repr(C)]
+struct Flags {
+    enabled: bool,
+}
+
+fn main() {}
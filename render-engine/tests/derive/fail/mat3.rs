@@ -0,0 +1,10 @@
+use render_engine::collection::Data;
+
+#[derive(Clone, Data)]
+#[repr(C)]
+#[data(std430)]
+struct Normal {
+    matrix: [[f32; 3]; 3],
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use render_engine::collection::Data;
+
+// 4 vec3 columns, not an array of vec3s
+#[derive(Clone, Data)]
+#[repr(C)]
+#[data(std430)]
+struct Bones {
+    transform: [[f32; 3]; 4],
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use render_engine::collection::Data;
+
+// the vec3 starts at 16 in GLSL, but at 4 here
+#[derive(Clone, Data)]
+#[repr(C)]
+struct Light {
+    strength: f32,
+    position: [f32; 3],
+}
+
+fn main() {}
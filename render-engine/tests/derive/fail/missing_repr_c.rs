@@ -0,0 +1,8 @@
+use render_engine::collection::Data;
+
+#[derive(Clone, Data)]
+struct Light {
+    position: [f32; 4],
+}
+
+fn main() {}
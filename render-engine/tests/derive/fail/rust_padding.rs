@@ -0,0 +1,13 @@
+use render_engine::collection::Data;
+
+// GLSL and Rust agree that the double starts at 8, but the 4 bytes in between
+// are padding, which as_bytes can't read
+#[derive(Clone, Data)]
+#[repr(C)]
+#[data(std430)]
+struct Mixed {
+    index: u32,
+    value: f64,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use render_engine::collection::Data;
+
+#[derive(Clone, Data)]
+#[repr(C)]
+struct Weights {
+    weights: [f32; 8],
+}
+
+fn main() {}
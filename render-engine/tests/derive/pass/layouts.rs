@@ -0,0 +1,43 @@
+use render_engine::collection::Data;
+use render_engine::layout::GlslLayout;
+use render_engine::utils::as_bytes;
+
+// a float fits in after a vec3
+#[derive(Clone, Data)]
+#[repr(C)]
+struct Light {
+    position: [f32; 3],
+    strength: f32,
+    color: [f32; 4],
+}
+
+// arrays of floats are fine in std430
+#[derive(Clone, Data)]
+#[repr(C)]
+#[data(std430)]
+struct Weights {
+    weights: [f32; 5],
+    count: u32,
+}
+
+// explicit padding where GLSL has a gap, and another Data struct
+#[derive(Clone, Data)]
+#[repr(C)]
+struct Scene {
+    light_count: u32,
+    _padding: [u32; 3],
+    lights: [Light; 2],
+    view_proj: [[f32; 4]; 4],
+}
+
+fn main() {
+    assert_eq!(Light::STD140_SIZE, 32);
+    assert_eq!(Weights::STD430_SIZE, 24);
+    assert_eq!(Scene::STD140_SIZE, 16 + 2 * 32 + 64);
+    let weights = Weights {
+        weights: [0.0; 5],
+        count: 7,
+    };
+    assert_eq!(as_bytes(&weights).len(), 24);
+    assert!(Scene::glsl_struct().contains("Light lights[2];"));
+}